@@ -0,0 +1,175 @@
+//! Named addresses that transfers are often sent to, kept in a file of their own.
+//!
+//! The file is read from `$XDG_DATA_HOME/substrate-wallet/address-book.txt` (or
+//! `~/.local/share/substrate-wallet/address-book.txt`) unless the config file names
+//! another. It has one contact per line: an SS58 address, a tab and the contact's name.
+
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::wallet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contact {
+    pub name: String,
+    /// SS58 address, in the format it was entered in
+    pub address: String,
+}
+
+/// The contacts of an address book file, in the order they were added.
+#[derive(Debug, Clone)]
+pub struct AddressBook {
+    path: PathBuf,
+    contacts: Vec<Contact>,
+}
+
+impl AddressBook {
+    /// Reads the address book at `path`, which is empty when the file does not exist.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut book = Self {
+            path: path.to_path_buf(),
+            contacts: Vec::new(),
+        };
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(book),
+            Err(e) => return Err(e),
+        };
+
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (address, name) = line.split_once('\t').unwrap_or((line, ""));
+            book.set(None, name, address).map_err(|e| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Line {} of {}: {}", index + 1, path.display(), e),
+                )
+            })?;
+        }
+        Ok(book)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    /// Checks that `name` can be given to a new contact, or to the one at `index`: it must
+    /// be a single line that no other contact has, ignoring case.
+    pub fn check_name(&self, index: Option<usize>, name: &str) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Enter a name".to_string());
+        }
+        if name.chars().any(char::is_control) {
+            return Err("Name must not contain control characters".to_string());
+        }
+        match self
+            .contacts
+            .iter()
+            .enumerate()
+            .find(|(i, contact)| Some(*i) != index && contact.name.eq_ignore_ascii_case(name))
+        {
+            Some((_, other)) => Err(format!("There is a contact called {} already", other.name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds a contact, or replaces the one at `index`, returning where it now is.
+    ///
+    /// Fails unless `address` is a valid SS58 address and `name` passes
+    /// [`AddressBook::check_name`].
+    pub fn set(
+        &mut self,
+        index: Option<usize>,
+        name: &str,
+        address: &str,
+    ) -> Result<usize, String> {
+        let (name, address) = (name.trim(), address.trim());
+        self.check_name(index, name)?;
+        wallet::parse_address(address).map_err(|e| format!("Invalid address: {}", e))?;
+
+        let contact = Contact {
+            name: name.to_string(),
+            address: address.to_string(),
+        };
+        match index.filter(|&index| index < self.contacts.len()) {
+            Some(index) => {
+                self.contacts[index] = contact;
+                Ok(index)
+            }
+            None => {
+                self.contacts.push(contact);
+                Ok(self.contacts.len() - 1)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Contact> {
+        (index < self.contacts.len()).then(|| self.contacts.remove(index))
+    }
+
+    /// Writes the contacts to the address book file, creating its directory if needed.
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let contents: String = self
+            .contacts
+            .iter()
+            .map(|contact| format!("{}\t{}\n", contact.address, contact.name))
+            .collect();
+        std::fs::write(&self.path, contents)
+    }
+}
+
+/// Where the address book is kept unless the config file names another file.
+pub fn default_path() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+        })?;
+
+    Some(data_dir.join("substrate-wallet").join("address-book.txt"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+
+    #[test]
+    fn contacts_are_checked_and_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.txt");
+
+        let mut book = AddressBook::load(&path).unwrap();
+        assert!(book.contacts().is_empty());
+        assert_eq!(book.set(None, " Alice ", ALICE), Ok(0));
+        assert_eq!(book.set(None, "Bob", BOB), Ok(1));
+        assert!(book.set(None, "alice", BOB).is_err());
+        assert!(book.set(None, "Carol", "5Grwva").is_err());
+        assert!(book.set(None, "", ALICE).is_err());
+        assert_eq!(book.set(Some(1), "Bobby", BOB), Ok(1));
+        book.save().unwrap();
+
+        let loaded = AddressBook::load(&path).unwrap();
+        assert_eq!(loaded.contacts(), book.contacts());
+        assert_eq!(loaded.contacts()[0].name, "Alice");
+        assert_eq!(book.remove(0).unwrap().address, ALICE);
+        assert_eq!(book.remove(1), None);
+
+        std::fs::write(&path, format!("{}\tAlice\nnot an address\tBob\n", ALICE)).unwrap();
+        let e = AddressBook::load(&path).unwrap_err();
+        assert!(e.to_string().starts_with("Line 2 of "), "{}", e);
+    }
+}
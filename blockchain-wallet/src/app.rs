@@ -1,277 +1,7186 @@
-use color_eyre::{owo_colors::OwoColorize, Result};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use hex;
-use rand::{rngs::OsRng, RngCore, TryRngCore};
-use ratatui::{
-    prelude::{Constraint, Direction, Layout, Modifier, Style},
-    style::Color,
-    text::{Line, Span, Text},
-    widgets::{Block, Borders, Paragraph},
-    DefaultTerminal, Frame,
-};
-use sp_core::{
-    crypto::{Pair, SecretString, Ss58AddressFormat, Ss58Codec},
-    sr25519::{Pair as Sr25519Pair, Public},
-    Encode,
-};
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-use std::path::Path;
-use std::time::{Duration, Instant};
-
-/// The main application which holds the state and logic of the application.
-#[derive(Debug, Default)]
-pub struct App {
-    /// Is the application running?
-    running: bool,
-    /// Has the button been pressed?
-    button_pressed: bool,
-    /// List of seeds loaded from file
-    seeds: Vec<[u8; 32]>,
-    /// Last time seeds were checked
-    last_check: Option<Instant>,
-    /// Path to the keys file
-    keys_path: String,
-    /// Last known modification time of the keys file
-    last_modified: Option<std::time::SystemTime>,
-}
-
-#[derive(Debug)]
-pub struct Wallet {
-    pub public_key: String,
-    pub private_key: String,
-    pub address: String,
-}
-
-impl App {
-    /// Construct a new instance of [`App`].
-    pub fn new() -> Self {
-        Self {
-            running: true,
-            button_pressed: false,
-            seeds: Vec::new(),
-            last_check: None,
-            keys_path: "./keys.txt".to_string(),
-            last_modified: None,
-        }
-    }
-
-    /// Run the application's main loop.
-    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        self.running = true;
-        self.load_seeds()?;
-
-        while self.running {
-            self.check_for_updates()?;
-
-            terminal.draw(|frame| self.render(frame))?;
-            if event::poll(Duration::from_millis(100))? {
-                self.handle_crossterm_events()?;
-            }
-        }
-        Ok(())
-    }
-
-    fn check_for_updates(&mut self) -> Result<()> {
-        let now = Instant::now();
-
-        if let Some(last_check) = self.last_check {
-            if now.duration_since(last_check) < Duration::from_millis(100) {
-                return Ok(());
-            }
-        }
-
-        self.last_check = Some(now);
-
-        let path = Path::new(&self.keys_path);
-        if path.exists() {
-            match path.metadata() {
-                Ok(metadata) => match metadata.modified() {
-                    Ok(modified_time) => {
-                        if self.last_modified.is_none() || self.last_modified != Some(modified_time)
-                        {
-                            self.last_modified = Some(modified_time);
-                            self.load_seeds()?;
-                        }
-                    }
-                    Err(e) => eprintln!("Error getting modified time: {}", e),
-                },
-                Err(e) => eprintln!("Error getting metadata: {}", e),
-            }
-        }
-
-        Ok(())
-    }
-
-    fn generate_random_wallet() -> (Sr25519Pair, String, [u8; 32]) {
-        let mut seed = [0u8; 32];
-        let mut rng = OsRng;
-        let _ = rng.try_fill_bytes(&mut seed);
-
-        let pair = Sr25519Pair::from_seed(&seed);
-
-        let address = pair.public().to_ss58check();
-
-        (pair, address, seed)
-    }
-
-    fn save_wallet_to_file(file_path: &str, seed: &[u8; 32]) -> Result<(), std::io::Error> {
-        let path = Path::new(file_path);
-
-        let mut file = OpenOptions::new().append(true).create(true).open(path)?;
-
-        let seed_hex = hex::encode(seed);
-
-        writeln!(file, "{}", seed_hex)?;
-
-        Ok(())
-    }
-
-    fn load_wallets_from_file(file_path: &str) -> Result<Vec<[u8; 32]>, std::io::Error> {
-        let path = Path::new(file_path);
-
-        if !path.exists() {
-            return Ok(Vec::new());
-        }
-
-        let file = File::open(path)?;
-
-        let reader = BufReader::new(file);
-        let mut seeds: Vec<[u8; 32]> = Vec::new();
-
-        for line in reader.lines() {
-            let line = line?;
-
-            if line.trim().is_empty() {
-                continue;
-            }
-
-            let seed_bytes = hex::decode(line)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-
-            if seed_bytes.len() != 32 {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Seed must be 32 bytes",
-                ));
-            }
-
-            let mut seed = [0u8; 32];
-            seed.copy_from_slice(&seed_bytes[..32]);
-
-            seeds.push(seed);
-        }
-
-        Ok(seeds)
-    }
-
-    fn load_seeds(&mut self) -> Result<()> {
-        match Self::load_wallets_from_file(&self.keys_path) {
-            Ok(seeds) => {
-                self.seeds = seeds;
-                Ok(())
-            }
-            Err(e) => {
-                eprintln!("Error loading seeds: {}", e);
-                Err(e.into())
-            }
-        }
-    }
-
-    fn render(&mut self, frame: &mut Frame) {
-        let mut lines = Vec::new();
-
-        if self.seeds.is_empty() {
-            lines.push(Line::from(vec![
-                Span::styled("No wallets found. ", Style::default().fg(Color::Yellow)),
-                Span::raw("Press 'A' to generate one!"),
-            ]));
-        } else {
-            for (i, seed) in self.seeds.iter().enumerate() {
-                let pair = Sr25519Pair::from_seed(&seed);
-                let address = pair.public().to_ss58check();
-                lines.push(Line::from(vec![
-                    Span::styled(
-                        format!("Wallet {}: ", i + 1),
-                        Style::default().fg(Color::Blue),
-                    ),
-                    Span::raw(address),
-                ]));
-            }
-        }
-
-        let text = Text::from(lines);
-
-        let layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-            .split(frame.size());
-
-        let title = Line::from(vec![
-            Span::styled("Substrate ", Style::default().fg(Color::Green)),
-            Span::styled("Wallet ", Style::default().fg(Color::Yellow)),
-            Span::styled("Manager", Style::default().fg(Color::Blue)),
-        ])
-        .centered();
-
-        let button_text = if self.button_pressed {
-            "New wallet generated! Press 'A' to generate another one."
-        } else {
-            "Press 'A' to generate a new wallet"
-        };
-
-        let button = Paragraph::new(button_text)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .style(Style::default()),
-            )
-            .style(Style::default().fg(Color::Green))
-            .centered();
-
-        frame.render_widget(button.block(Block::bordered().title(title)), layout[0]);
-
-        let wallet_count = self.seeds.len();
-        let wallet_title = format!("Wallets ({} total)", wallet_count);
-
-        let seed_paragraph = Paragraph::new(text)
-            .block(Block::default().borders(Borders::ALL).title(wallet_title))
-            .style(Style::default());
-
-        frame.render_widget(seed_paragraph, layout[1]);
-    }
-
-    /// Reads the crossterm events and updates the state of [`App`].
-    fn handle_crossterm_events(&mut self) -> Result<()> {
-        match event::read()? {
-            Event::Key(key) if key.kind == KeyEventKind::Press => self.on_key_event(key),
-            Event::Mouse(_) => {}
-            Event::Resize(_, _) => {}
-            _ => {}
-        }
-        Ok(())
-    }
-
-    fn on_key_event(&mut self, key: KeyEvent) {
-        match (key.modifiers, key.code) {
-            (_, KeyCode::Esc | KeyCode::Char('q'))
-            | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
-            (_, KeyCode::Char('a')) => self.press_button(),
-            // Add other key handlers here.
-            _ => {}
-        }
-    }
-
-    fn quit(&mut self) {
-        self.running = false;
-    }
-
-    fn press_button(&mut self) {
-        self.button_pressed = true;
-        let (_, address, seed) = Self::generate_random_wallet();
-
-        if let Err(e) = Self::save_wallet_to_file(&self.keys_path, &seed) {
-            eprintln!("Failed to save wallet: {}", e);
-        }
-    }
-}
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
+use ratatui::{
+    layout::{Margin, Position},
+    prelude::{Constraint, Direction, Layout, Modifier, Rect, Style},
+    style::Color,
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+use zeroize::Zeroizing;
+
+use crate::address_book::{self, AddressBook};
+use crate::archive;
+use crate::config::{self, Config};
+use crate::keymap::{self, Action, KeyBinding};
+use crate::keystore;
+use crate::payload::{self, PayloadSummary};
+use crate::rpc::{self, RpcClient};
+use crate::state;
+use crate::theme::Theme;
+use crate::transfer::{self, TransferEvent};
+use crate::vault::{self, Vault};
+use crate::wallet::{
+    self, verify_signature, KeyEntry, KeyScheme, Wallet, WalletKeys, MNEMONIC_WORDS,
+};
+
+/// Number of wrong passwords accepted on the lock screen before the app quits.
+const MAX_UNLOCK_ATTEMPTS: u8 = 3;
+
+/// Largest number of wallets a single batch generation may create.
+pub const MAX_BATCH_SIZE: usize = 10_000;
+
+/// Characters that can appear in an SS58 address.
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Keys file used when no other path is given.
+pub const DEFAULT_KEYS_PATH: &str = "./keys.txt";
+
+/// Light modules drawn around a QR code, which scanners need to find its edges.
+const QR_QUIET_ZONE: usize = 2;
+
+/// How long a revealed secret stays on screen unless a key hides it first.
+const REVEAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a copied secret stays on the clipboard, unless the config file says otherwise.
+const DEFAULT_CLIPBOARD_CLEAR: Duration = Duration::from_secs(30);
+
+/// How long the UI waits for input before checking on background work, unless the
+/// config file or the command line says otherwise.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Poll intervals in milliseconds that may be configured. Shorter ones only burn CPU,
+/// longer ones make the UI feel stuck.
+pub const POLL_INTERVAL_RANGE_MS: std::ops::RangeInclusive<u64> = 10..=1000;
+
+/// How long the keys file has to stay unchanged before a change is reloaded, unless the
+/// config file says otherwise. Editors often write a file several times in a row.
+const DEFAULT_RELOAD_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Span of the most recent work that generation rates are measured over.
+const RATE_WINDOW: Duration = Duration::from_secs(2);
+
+/// Number of changes to the keys file that can be undone, most recent first.
+const UNDO_LEVELS: usize = 2;
+
+/// How long a status message stays in the status line.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// Longest time between two clicks on a wallet for them to count as a double click.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
+/// Widest a label gets in the wallet list; longer ones are cut short there but shown in
+/// full in the details.
+const LABEL_COLUMN_WIDTH: usize = 20;
+
+/// Logo shown in the wallet list while it is empty. Its lines are equally wide, so that
+/// they stay aligned when centered.
+const LOGO: [&str; 6] = [
+    ".--------------------.",
+    "|  .--------------.  |",
+    "|  |  SUBSTRATE   |  |",
+    "|  |   WALLET   o |  |",
+    "|  '--------------'  |",
+    "'--------------------'",
+];
+
+/// The main application which holds the state and logic of the application.
+#[derive(Debug)]
+pub struct App {
+    /// Is the application running?
+    running: bool,
+    /// Whether the keys file has been unlocked yet
+    mode: AppMode,
+    /// Whether the keys file was encrypted at rest when last checked: on start, on
+    /// locking, on switching profiles and on reloads
+    keys_encrypted: bool,
+    /// Password typed on the lock screen so far
+    password_input: Zeroizing<String>,
+    /// First entry of a new password while waiting for it to be repeated
+    new_password: Option<Zeroizing<String>>,
+    /// First entry of the passphrase of a wallet about to be generated, likewise
+    new_passphrase: Option<Zeroizing<String>>,
+    /// Error from the last failed unlock attempt
+    lock_error: Option<String>,
+    /// Number of failed unlock attempts
+    failed_unlocks: u8,
+    /// Has the button been pressed?
+    button_pressed: bool,
+    /// List of seeds loaded from file
+    seeds: Vec<KeyEntry>,
+    /// Keys and SS58 addresses of `seeds`, rebuilt only when the seeds change
+    wallets: Vec<WalletKeys>,
+    /// Ethereum-style addresses of the ecdsa entries in `seeds`
+    eth_addresses: Vec<Option<String>>,
+    /// Account ids of `seeds`, which balances are looked up by
+    account_ids: Vec<AccountId32>,
+    /// Fingerprints of `seeds`, which marks are kept by
+    fingerprints: Vec<[u8; 32]>,
+    /// SS58 format each of `wallets` is rendered in, `None` for addresses that don't parse
+    address_formats: Vec<Option<Ss58AddressFormat>>,
+    /// Accounts the seed of each of `seeds` gives under each of [`KeyScheme::ALL`], empty
+    /// for entries without a seed
+    scheme_accounts: Vec<Vec<Result<AccountId32, String>>>,
+    /// Whether each wallet's public key is shown next to its address
+    show_public_keys: bool,
+    /// Index of the wallet that per-wallet actions apply to
+    selected: usize,
+    /// Row of the selected wallet and the first one in view of the wallet list
+    list_state: ListState,
+    /// Number of wallet list lines that fit on screen, as of the last render
+    list_height: usize,
+    /// Area of the wallet list, borders included, as of the last render
+    list_area: Rect,
+    /// Index of the wallet on each wallet list line in view, top to bottom, as of the last
+    /// render
+    list_wallets: Vec<usize>,
+    /// Wallet clicked last and when, to tell a double click from a single one
+    last_click: Option<(usize, Instant)>,
+    /// Lowercase address substring the wallet list is narrowed to, if any
+    filter: Option<String>,
+    /// Fingerprints of the wallets marked for deleting or exporting together
+    marked: HashSet<[u8; 32]>,
+    /// Order the wallet list is shown in
+    sort: SortOrder,
+    /// Signature scheme used for newly generated wallets
+    scheme: KeyScheme,
+    /// SS58 format addresses are rendered in
+    ss58_format: Ss58AddressFormat,
+    /// Word count of the mnemonic for newly generated wallets, or `None` for a raw seed
+    mnemonic_words: Option<usize>,
+    /// Config file the chosen mnemonic length is remembered in, if any
+    config_path: Option<PathBuf>,
+    /// Path to the keys file
+    keys_path: String,
+    /// Keys files that can be switched between, empty unless the config file lists some
+    profiles: Vec<Profile>,
+    /// Index of the profile whose keys file is open
+    profile: usize,
+    /// Scheme of the URIs addresses are copied as, by SS58 prefix
+    uri_schemes: HashMap<u16, String>,
+    /// Filesystem watcher reporting changes to the keys file once it is unlocked
+    watcher: Option<KeysWatcher>,
+    /// Whether the keys file existed when it was last loaded
+    keys_file_exists: bool,
+    /// How long the keys file has to stay unchanged before a change is reloaded
+    reload_debounce: Duration,
+    /// When the pending reload of the keys file is due, if one is pending
+    reload_due: Option<Instant>,
+    /// Transient message shown in the status line
+    status: Option<(String, StatusKind)>,
+    /// When `status` was set, to clear it after [`STATUS_TIMEOUT`]
+    status_set_at: Option<Instant>,
+    /// Lazily opened handle to the OS clipboard
+    clipboard: Option<ClipboardHandle>,
+    /// Secret last copied to the clipboard and when, until it is cleared again
+    copied_secret: Option<(Zeroizing<String>, Instant)>,
+    /// How long a copied secret stays on the clipboard, or `None` to leave it there
+    clipboard_clear: Option<Duration>,
+    /// How long an encrypted keys file stays unlocked without input, or `None` for ever
+    auto_lock: Option<Duration>,
+    /// When the last key was pressed or the mouse last clicked
+    last_input: Instant,
+    /// Text entry currently shown over the main view, if any
+    input: Option<Input>,
+    /// Message box currently shown over the main view, if any
+    popup: Option<Popup>,
+    /// Password of the keys file when it is encrypted at rest
+    vault: Option<Vault>,
+    /// Vanity address search in progress, if any
+    vanity: Option<VanitySearch>,
+    /// Batch of wallets being generated in the background, if any
+    batch: Option<BatchGeneration>,
+    /// Node RPC endpoint balances are fetched from
+    rpc_url: String,
+    /// How long to wait for input before checking on background work
+    poll_interval: Duration,
+    /// Colors the UI is drawn with
+    theme: Theme,
+    /// Keys of the wallet list and the actions they run
+    key_bindings: Vec<KeyBinding>,
+    /// Problem with the startup settings, shown until the wallet is unlocked
+    startup_warning: Option<String>,
+    /// Free balances of the accounts fetched so far
+    balances: HashMap<AccountId32, Balance>,
+    /// Connection attempt to the node that balances or a transfer wait for, if any
+    connecting: Option<Connecting>,
+    /// Results of the balance fetch running in the background, if any
+    balance_updates: Option<Receiver<(AccountId32, Result<u128, String>)>>,
+    /// Balance transfer being submitted or tracked in the background, if any
+    transfer: Option<Transfer>,
+    /// QR code of an address or signature shown over the main view, if any
+    qr_code: Option<QrView>,
+    /// Secret of a wallet shown over the main view on request, if any
+    revealed: Option<RevealedSecret>,
+    /// Yes/no question shown over the main view before an action runs, if any
+    confirm: Option<Confirm>,
+    /// Whether the key bindings are shown over the main view
+    show_help: bool,
+    /// Whether changes stay in memory instead of being written to disk
+    dry_run: bool,
+    /// Changes to the keys file that can be undone, oldest first, up to [`UNDO_LEVELS`]
+    undo: Vec<Undo>,
+    /// File the address book is kept in, unless there is nowhere to keep one
+    address_book_path: Option<PathBuf>,
+    /// File the wallet last selected in each keys file is remembered in, if there is one
+    state_path: Option<PathBuf>,
+    /// Named transfer destinations, read when the address book is first opened
+    address_book: Option<AddressBook>,
+    /// Address book shown over the main view, if any
+    book_view: Option<BookView>,
+}
+
+/// A single-line text entry rendered as a popup over the main view.
+#[derive(Debug)]
+struct Input {
+    kind: InputKind,
+    value: Zeroizing<String>,
+    /// Validation error shown below the value
+    error: Option<String>,
+    /// Result of the value typed so far, shown below it while there is no error
+    preview: Option<String>,
+}
+
+/// What the text typed into an [`Input`] is used for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum InputKind {
+    /// A BIP39 mnemonic phrase to import as a new wallet
+    ImportPhrase,
+    /// Password to encrypt the selected wallet's JSON keystore export with
+    ExportKeystorePassword,
+    /// Path of a polkadot-js JSON keystore to import
+    ImportKeystorePath,
+    /// Password of the keystore at `path`
+    ImportKeystorePassword { path: String },
+    /// Number of wallets to generate at once
+    GenerateCount,
+    /// Pattern a generated wallet's address must match
+    VanityPattern,
+    /// Address substring to narrow the wallet list to, applied as it is typed
+    Filter,
+    /// New label of the selected wallet
+    Rename,
+    /// Derivation path of a new account of the selected wallet's secret
+    DerivationPath,
+    /// Node RPC endpoint to fetch balances from
+    RpcUrl,
+    /// Message to sign with the selected wallet
+    SignMessage,
+    /// Hex signing payload of a transaction to sign with the selected wallet offline
+    SignPayload,
+    /// SS58 address whose signature is to be verified
+    VerifyAddress,
+    /// Message that was signed by `address`
+    VerifyMessage { address: String },
+    /// Hex signature of `message` by `address`
+    VerifySignature { address: String, message: String },
+    /// SS58 address to transfer tokens from the selected wallet to
+    TransferDestination,
+    /// Amount of tokens to transfer to `destination`
+    TransferAmount { destination: String },
+    /// Addresses to add as watch-only wallets, or the path of a file listing them
+    WatchAddresses,
+    /// Path of a CSV file to write every wallet's address to
+    ExportCsvPath,
+    /// Password to encrypt the archive of all wallets with
+    ExportArchivePassword,
+    /// Path of an archive to import all wallets from
+    ImportArchivePath,
+    /// Keys file the wallets of the archive at `path` are restored into
+    ImportArchiveKeysPath { path: String },
+    /// Password of the archive at `path`
+    ImportArchivePassword { path: String, keys_path: String },
+    /// Path of another keys file whose wallets are merged into the open one
+    MergeKeysFilePath,
+    /// Password of the encrypted keys file at `path`
+    MergeKeysFilePassword { path: String },
+    /// Name of a new address book contact, or the new name of the one at `index`
+    ContactName { index: Option<usize> },
+    /// Address of the contact called `name`, which replaces the one at `index` if any
+    ContactAddress { index: Option<usize>, name: String },
+    /// Password of the keys file, asked for again before the secret of the wallet at
+    /// `index`, or its mnemonic phrase if `phrase`, is revealed, or copied to the clipboard
+    /// if `copy`
+    RevealPassword {
+        index: usize,
+        address: String,
+        phrase: bool,
+        copy: bool,
+    },
+    /// Password of the keys file, asked for again before the wallet at `index` is
+    /// inspected
+    InspectPassword { index: usize, address: String },
+    /// Passphrase of the protected wallet at `index`, needed for `purpose`
+    Passphrase {
+        index: usize,
+        address: String,
+        purpose: PassphraseFor,
+    },
+    /// Passphrase of a new protected wallet
+    NewPassphrase,
+    /// The passphrase of the new protected wallet again, to rule out typos
+    RepeatPassphrase,
+}
+
+/// The address book shown over the main view, to manage its contacts or to pick the
+/// destination of a transfer.
+#[derive(Debug)]
+struct BookView {
+    /// Index of the highlighted contact
+    selected: usize,
+    /// What had been typed as the transfer destination, when the book was opened from that
+    /// input to pick one
+    picking: Option<String>,
+}
+
+/// A named keys file from the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Profile {
+    name: String,
+    keys_path: String,
+}
+
+/// A read-only message box shown over the main view until a key is pressed.
+#[derive(Debug)]
+struct Popup {
+    title: String,
+    lines: Vec<Line<'static>>,
+    /// Value copied to the clipboard when 'c' is pressed
+    copy: Option<String>,
+    /// Key hint shown instead of the default one
+    hint: Option<&'static str>,
+    /// Whether the value copied is secret, to be cleared from the clipboard again like a
+    /// copied secret seed
+    secret: bool,
+}
+
+/// Actions whose keys the footer always shows, with a short name for each.
+const FOOTER_HINTS: &[(Action, &str)] = &[
+    (Action::Generate, "new"),
+    (Action::Delete, "delete"),
+    (Action::Undo, "undo"),
+    (Action::CopyAddress, "copy"),
+    (Action::Filter, "filter"),
+    (Action::Help, "help"),
+    (Action::Quit, "quit"),
+];
+
+/// Actions whose keys the footer shows in the detail view of a wallet.
+const DETAIL_HINTS: &[(Action, &str)] = &[
+    (Action::CopyAddress, "copy"),
+    (Action::Sign, "sign"),
+    (Action::ShowQrCode, "QR"),
+    (Action::ExportKeystore, "export"),
+    (Action::RevealSecret, "secret"),
+    (Action::Back, "back"),
+];
+
+/// A yes/no dialog that runs `action` when answered with 'y'.
+#[derive(Debug)]
+struct Confirm {
+    prompt: String,
+    action: ConfirmAction,
+}
+
+/// An action that only runs once it has been confirmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ConfirmAction {
+    /// Generate one wallet with the current settings
+    Generate,
+    /// Generate this many wallets at once
+    GenerateBatch(usize),
+    /// Delete the wallet at `index`, as long as it still has `address`
+    Delete { index: usize, address: String },
+    /// Delete the wallets with these fingerprints
+    DeleteMarked(Vec<[u8; 32]>),
+    /// Show the secret of the wallet at `index`, or its mnemonic phrase if `phrase`, as
+    /// long as it still has `address`
+    RevealSecret {
+        index: usize,
+        address: String,
+        phrase: bool,
+    },
+    /// Copy the secret of the wallet at `index` to the clipboard, once the password of the
+    /// keys file is entered again
+    CopySecret { index: usize, address: String },
+    /// Show what `subkey inspect` prints for the wallet at `index`, as long as it still has
+    /// `address`
+    Inspect { index: usize, address: String },
+    /// Replace the key of the wallet at `index` with a new one, as long as it still has
+    /// `address`
+    Rotate { index: usize, address: String },
+    /// Sign the signing payload of a transaction with the wallet at `index` offline, as
+    /// long as it still has `address`
+    SignPayload {
+        index: usize,
+        address: String,
+        payload: Vec<u8>,
+    },
+    /// Make the keys file readable and writable by its owner only
+    RestrictPermissions,
+    /// Reverse the most recent change on the undo stack
+    Undo,
+}
+
+/// A change to the keys file that can be undone.
+#[derive(Debug)]
+enum Undo {
+    /// Wallets were generated; undoing it deletes them again
+    Generated { fingerprints: Vec<[u8; 32]> },
+    /// A wallet was deleted from `index`; undoing it puts it back there
+    Deleted { index: usize, entry: KeyEntry },
+    /// The marked wallets were deleted from these indices, in ascending order; undoing it
+    /// puts them back there
+    DeletedMarked { entries: Vec<(usize, KeyEntry)> },
+}
+
+/// The secret of a wallet, shown until a key is pressed or [`REVEAL_TIMEOUT`] passes.
+#[derive(Debug)]
+struct RevealedSecret {
+    address: String,
+    /// Hex seed, or the hex secret key of keystore imports whose seed is unknown, or the
+    /// mnemonic phrase if `phrase`
+    secret: Zeroizing<String>,
+    phrase: bool,
+    shown_at: Instant,
+}
+
+/// A text, such as an address, and the modules of its QR code.
+#[derive(Debug)]
+struct QrView {
+    /// What the text is, e.g. `Address`
+    title: &'static str,
+    text: String,
+    /// Number of modules per side, excluding the quiet zone
+    width: usize,
+    /// Whether each module is dark, row by row
+    dark: Vec<bool>,
+}
+
+impl QrView {
+    fn new(title: &'static str, text: String) -> Result<Self, qrcode::types::QrError> {
+        let code = qrcode::QrCode::new(text.as_bytes())?;
+        let dark = code
+            .to_colors()
+            .into_iter()
+            .map(|color| color == qrcode::Color::Dark)
+            .collect();
+
+        Ok(Self {
+            title,
+            text,
+            width: code.width(),
+            dark,
+        })
+    }
+
+    /// Whether the module at `x`, `y` is dark, counting the quiet zone.
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        let (Some(x), Some(y)) = (x.checked_sub(QR_QUIET_ZONE), y.checked_sub(QR_QUIET_ZONE))
+        else {
+            return false;
+        };
+        x < self.width && y < self.width && self.dark[y * self.width + x]
+    }
+}
+
+/// A balance transfer from one of the wallets, submitted by [`transfer::run`].
+#[derive(Debug)]
+struct Transfer {
+    from: String,
+    to: String,
+    /// Amount in planck
+    amount: u128,
+    /// Address format the amount was entered for
+    ss58_format: Ss58AddressFormat,
+    /// Estimated fee in planck, once the transfer is signed
+    fee: Option<u128>,
+    /// Submits the signed transfer when sent to, or cancels it when dropped
+    confirm: Option<mpsc::Sender<()>>,
+    /// Whether the fee is known and the transfer waits for 'y' to be submitted
+    awaiting_confirmation: bool,
+    hash: Option<[u8; 32]>,
+    /// Latest progress, or the error it failed with
+    state: Line<'static>,
+    /// Whether the transfer is finalized or has failed
+    done: bool,
+    events: Receiver<TransferEvent>,
+}
+
+/// The part of an address a vanity search is looking for.
+///
+/// The pattern is matched against the address after its first character, which is fixed
+/// by the network prefix.
+#[derive(Debug, Clone)]
+struct VanityPattern {
+    prefix: String,
+    suffix: String,
+}
+
+impl VanityPattern {
+    /// Parses a `prefix`, `prefix*suffix` or `*suffix` pattern.
+    fn parse(pattern: &str, ss58_format: Ss58AddressFormat) -> Result<Self, String> {
+        let pattern = pattern.trim();
+        if let Some(c) = pattern
+            .chars()
+            .find(|&c| c != '*' && !BASE58_ALPHABET.contains(c))
+        {
+            return Err(format!("'{}' can never appear in an address", c));
+        }
+
+        let (prefix, suffix) = pattern.split_once('*').unwrap_or((pattern, ""));
+        if suffix.contains('*') {
+            return Err("Only one '*' is allowed".to_string());
+        }
+        if prefix.is_empty() && suffix.is_empty() {
+            return Err("Enter a prefix, prefix*suffix or *suffix".to_string());
+        }
+        if !Self::prefix_reachable(prefix, ss58_format) {
+            return Err(format!(
+                "No {} address can start with '{}' after its first character",
+                network_name(ss58_format),
+                prefix
+            ));
+        }
+
+        Ok(Self {
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+        })
+    }
+
+    /// Whether any address of the format continues with `prefix` after its first character.
+    ///
+    /// All addresses of a format have the same length and lie between those of the lowest
+    /// and highest account ids, so the digits right after the network character are only
+    /// partly free: Substrate addresses, say, all start with 5C to 5H.
+    fn prefix_reachable(prefix: &str, ss58_format: Ss58AddressFormat) -> bool {
+        let digits = |address: &str| -> Vec<usize> {
+            address
+                .chars()
+                .map(|c| BASE58_ALPHABET.find(c).unwrap_or(0))
+                .collect()
+        };
+        let lowest = digits(&AccountId32::new([0; 32]).to_ss58check_with_version(ss58_format));
+        let highest = digits(&AccountId32::new([0xff; 32]).to_ss58check_with_version(ss58_format));
+        let prefix = digits(prefix);
+
+        (0..BASE58_ALPHABET.len()).any(|first| {
+            let mut smallest = vec![first];
+            smallest.extend(&prefix);
+            let mut largest = smallest.clone();
+            smallest.resize(lowest.len(), 0);
+            largest.resize(highest.len(), BASE58_ALPHABET.len() - 1);
+
+            smallest <= highest && largest >= lowest
+        })
+    }
+
+    fn matches(&self, address: &str) -> bool {
+        let body = &address[1..];
+        body.starts_with(&self.prefix) && body.ends_with(&self.suffix)
+    }
+
+    /// Expected number of attempts: each pattern character matches with odds of 1 in 58.
+    fn expected_attempts(&self) -> f64 {
+        58f64.powi((self.prefix.len() + self.suffix.len()) as i32)
+    }
+}
+
+/// A vanity search running on one worker thread per available core.
+///
+/// Workers share an attempt counter and a stop flag; the first one to find a match, or to
+/// fail to generate a wallet, sends the result back and every worker stops. Dropping the
+/// search stops and joins the workers.
+#[derive(Debug)]
+struct VanitySearch {
+    pattern: VanityPattern,
+    scheme: KeyScheme,
+    attempts: Throughput,
+    stop: Arc<AtomicBool>,
+    found: Receiver<Result<(String, KeyEntry), String>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl VanitySearch {
+    /// Starts searching for a raw-seed wallet of `scheme` matching `pattern`. Deriving a
+    /// seed from a mnemonic is far too slow to search through.
+    fn start(pattern: VanityPattern, scheme: KeyScheme, ss58_format: Ss58AddressFormat) -> Self {
+        let attempts = Throughput::new();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, found) = mpsc::channel();
+
+        let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let workers = (0..threads)
+            .map(|_| {
+                let (pattern, attempts, stop, sender) = (
+                    pattern.clone(),
+                    attempts.counter(),
+                    stop.clone(),
+                    sender.clone(),
+                );
+                std::thread::spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        let (address, entry) =
+                            match wallet::generate_random_wallet(scheme, None, ss58_format) {
+                                Ok(wallet) => wallet,
+                                Err(e) => {
+                                    stop.store(true, Ordering::Relaxed);
+                                    let _ = sender.send(Err(e.to_string()));
+                                    return;
+                                }
+                            };
+                        attempts.fetch_add(1, Ordering::Relaxed);
+
+                        if pattern.matches(&address) {
+                            stop.store(true, Ordering::Relaxed);
+                            let _ = sender.send(Ok((address, entry)));
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            pattern,
+            scheme,
+            attempts,
+            stop,
+            found,
+            workers,
+        }
+    }
+}
+
+impl Drop for VanitySearch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Frames of the spinner shown while waiting for the node, one [`SPINNER_STEP`] each.
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+const SPINNER_STEP: Duration = Duration::from_millis(80);
+
+/// The spinner frame to show once `elapsed` has passed.
+fn spinner(elapsed: Duration) -> char {
+    let frame = elapsed.as_millis() / SPINNER_STEP.as_millis();
+    SPINNER_FRAMES[frame as usize % SPINNER_FRAMES.len()]
+}
+
+/// What the passphrase of a protected wallet is asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PassphraseFor {
+    Sign {
+        message: String,
+    },
+    /// Transfer `amount` planck to `destination`
+    Transfer {
+        destination: String,
+        amount: u128,
+    },
+    /// Sign the signing payload of a transaction without the node
+    SignPayload {
+        payload: Vec<u8>,
+    },
+}
+
+/// Work that needs the node, waiting for the connection to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NodeTask {
+    /// Fetch the free balance of every wallet
+    Balances,
+    /// Transfer `amount` planck from the wallet at `index`, as long as it still has the
+    /// address `from`, to `destination`, signing with `entry`
+    Transfer {
+        index: usize,
+        from: String,
+        destination: String,
+        amount: u128,
+        entry: Box<KeyEntry>,
+    },
+}
+
+/// An attempt to reach the node at `url` before running `task`, shown over the main view
+/// until it succeeds, or until it is dismissed after failing.
+#[derive(Debug)]
+struct Connecting {
+    url: String,
+    task: NodeTask,
+    started: Instant,
+    /// Outcome of the attempt, once the worker has one
+    result: Receiver<Result<(), String>>,
+    /// Why the attempt failed, while it can be retried
+    error: Option<String>,
+}
+
+impl Connecting {
+    /// Starts reaching the node at `url` in the background.
+    fn start(url: &str, task: NodeTask) -> Self {
+        let client = RpcClient::new(url);
+        let (sender, result) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(client.connect().map_err(|e| e.to_string()));
+        });
+
+        Self {
+            url: url.to_string(),
+            task,
+            started: Instant::now(),
+            result,
+            error: None,
+        }
+    }
+}
+
+/// How far a long-running operation has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Progress {
+    /// `done` of `total` steps
+    Steps { done: usize, total: usize },
+    /// An unknown share, shown by a block sweeping back and forth for as long as the
+    /// operation has been running
+    Ongoing { elapsed: Duration },
+}
+
+impl Progress {
+    /// Time the sweeping block of [`Progress::Ongoing`] takes to move by one column.
+    const SWEEP_STEP: Duration = Duration::from_millis(40);
+
+    /// Columns from `start` to `end` of a bar `width` columns wide the sweeping block
+    /// covers after `elapsed`.
+    fn sweep(width: usize, elapsed: Duration) -> (usize, usize) {
+        let size = (width / 5).max(1).min(width);
+        let travel = width - size;
+        if travel == 0 {
+            return (0, size);
+        }
+        let step = (elapsed.as_millis() / Self::SWEEP_STEP.as_millis()) as usize % (2 * travel);
+        let start = if step <= travel {
+            step
+        } else {
+            2 * travel - step
+        };
+        (start, start + size)
+    }
+}
+
+/// A batch of wallets being generated on one worker thread per available core, up to
+/// one per wallet.
+///
+/// Each worker sends the wallets of its share back once it has generated them all, or
+/// the error that stopped it. Dropping the batch stops and joins the workers, and what
+/// they generated is discarded.
+#[derive(Debug)]
+struct BatchGeneration {
+    count: usize,
+    scheme: KeyScheme,
+    generated: Throughput,
+    stop: Arc<AtomicBool>,
+    results: Receiver<Result<Vec<KeyEntry>, String>>,
+    /// Wallets sent back by the workers done so far
+    entries: Vec<KeyEntry>,
+    /// Number of workers that have not sent their wallets back yet
+    pending: usize,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl BatchGeneration {
+    fn start(
+        count: usize,
+        scheme: KeyScheme,
+        mnemonic_words: Option<usize>,
+        ss58_format: Ss58AddressFormat,
+    ) -> Self {
+        let generated = Throughput::new();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, results) = mpsc::channel();
+
+        let threads = std::thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(count);
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let share = count / threads + usize::from(worker < count % threads);
+                let (generated, stop, sender) = (generated.counter(), stop.clone(), sender.clone());
+                std::thread::spawn(move || {
+                    let mut entries = Vec::with_capacity(share);
+                    for _ in 0..share {
+                        if stop.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        match wallet::generate_random_wallet(scheme, mnemonic_words, ss58_format) {
+                            Ok((_, entry)) => entries.push(entry),
+                            Err(e) => {
+                                stop.store(true, Ordering::Relaxed);
+                                let _ = sender.send(Err(e.to_string()));
+                                return;
+                            }
+                        }
+                        generated.fetch_add(1, Ordering::Relaxed);
+                    }
+                    let _ = sender.send(Ok(entries));
+                })
+            })
+            .collect();
+
+        Self {
+            count,
+            scheme,
+            generated,
+            stop,
+            results,
+            entries: Vec::with_capacity(count),
+            pending: workers.len(),
+            workers,
+        }
+    }
+}
+
+impl Drop for BatchGeneration {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Counts work done on any number of threads, and measures how fast it is done over the
+/// last [`RATE_WINDOW`].
+#[derive(Debug)]
+struct Throughput {
+    count: Arc<AtomicU64>,
+    started: Instant,
+    /// Counts taken by [`Throughput::sample`], oldest first, reaching just past the window
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl Throughput {
+    fn new() -> Self {
+        Self {
+            count: Arc::new(AtomicU64::new(0)),
+            started: Instant::now(),
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// The counter for workers to add to.
+    fn counter(&self) -> Arc<AtomicU64> {
+        self.count.clone()
+    }
+
+    fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Takes the count at `now`, for the rate to follow. Called on every poll.
+    fn sample(&mut self, now: Instant) {
+        self.samples.push_back((now, self.count()));
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(at, _)| now.duration_since(*at) >= RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Work done per second over the last [`RATE_WINDOW`], or since the start until two
+    /// samples have been taken.
+    fn per_second(&self) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(&(first_at, first)), Some(&(last_at, last))) if last_at > first_at => {
+                (last - first) as f64 / (last_at - first_at).as_secs_f64()
+            }
+            _ => self.count() as f64 / self.started.elapsed().as_secs_f64().max(f64::EPSILON),
+        }
+    }
+}
+
+impl Transfer {
+    fn popup(&self, theme: &Theme) -> Popup {
+        let field = |name: &'static str, value: String| {
+            Line::from(vec![
+                Span::styled(name, Style::default().fg(theme.label)),
+                Span::raw(value),
+            ])
+        };
+        let hash = self.hash.map(|hash| format!("0x{}", hex::encode(hash)));
+        let fee = match self.fee {
+            Some(fee) => format_balance(fee, self.ss58_format),
+            None => "…".to_string(),
+        };
+
+        Popup {
+            title: "Transfer".to_string(),
+            lines: vec![
+                field("From:      ", self.from.clone()),
+                field("To:        ", self.to.clone()),
+                field("Amount:    ", format_balance(self.amount, self.ss58_format)),
+                field("Fee:       ", format!("{} (estimated)", fee)),
+                field(
+                    "Tx hash:   ",
+                    hash.clone().unwrap_or_else(|| "—".to_string()),
+                ),
+                self.state.clone(),
+            ],
+            copy: hash,
+            hint: self
+                .awaiting_confirmation
+                .then_some("y: submit  any other key: cancel"),
+            secret: false,
+        }
+    }
+}
+
+impl Input {
+    fn new(kind: InputKind) -> Self {
+        Self {
+            kind,
+            value: Zeroizing::new(String::new()),
+            error: None,
+            preview: None,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self.kind {
+            InputKind::ImportPhrase => {
+                "Import mnemonic phrase or secret URI (phrase//path///password)"
+            }
+            InputKind::ExportKeystorePassword => "Keystore password",
+            InputKind::ImportKeystorePath => "Import JSON keystore from path",
+            InputKind::ImportKeystorePassword { .. } => "Password of the keystore",
+            InputKind::GenerateCount => "Number of wallets to generate",
+            InputKind::VanityPattern => "Vanity address: prefix, prefix*suffix or *suffix",
+            InputKind::Filter => "Filter wallets by address",
+            InputKind::Rename => "Label of the selected wallet (empty to remove)",
+            InputKind::DerivationPath => "Derivation path, e.g. //polkadot//0 or //hard/soft",
+            InputKind::RpcUrl => "Fetch balances from node RPC URL",
+            InputKind::SignMessage => "Message to sign",
+            InputKind::SignPayload => "Sign offline: hex signing payload of the transaction",
+            InputKind::VerifyAddress => "Verify: address of the signer",
+            InputKind::VerifyMessage { .. } => "Verify: signed message",
+            InputKind::VerifySignature { .. } => "Verify: hex signature",
+            InputKind::TransferDestination => "Transfer: destination address",
+            InputKind::TransferAmount { .. } => "Transfer: amount in tokens, e.g. 1.5",
+            InputKind::RevealPassword { .. } | InputKind::InspectPassword { .. } => {
+                "Password of the keys file"
+            }
+            InputKind::Passphrase { .. } => "Passphrase of the wallet",
+            InputKind::NewPassphrase => "Passphrase of the new wallet, needed to sign with it",
+            InputKind::RepeatPassphrase => "Passphrase of the new wallet again",
+            InputKind::ExportCsvPath => "Export addresses to CSV file",
+            InputKind::ExportArchivePassword => "Archive password",
+            InputKind::ImportArchivePath => "Import all wallets from archive at path",
+            InputKind::ImportArchiveKeysPath { .. } => "Restore the wallets into keys file",
+            InputKind::ImportArchivePassword { .. } => "Password of the archive",
+            InputKind::MergeKeysFilePath => "Merge in the wallets of keys file at path",
+            InputKind::MergeKeysFilePassword { .. } => "Password of the keys file to merge",
+            InputKind::WatchAddresses => "Watch: addresses, or the path of a file listing them",
+            InputKind::ContactName { .. } => "Address book: name of the contact",
+            InputKind::ContactAddress { .. } => "Address book: address of the contact",
+        }
+    }
+
+    /// Whether the value is a password that must not be echoed.
+    fn is_secret(&self) -> bool {
+        matches!(
+            self.kind,
+            InputKind::ExportKeystorePassword
+                | InputKind::ImportKeystorePassword { .. }
+                | InputKind::ExportArchivePassword
+                | InputKind::ImportArchivePassword { .. }
+                | InputKind::MergeKeysFilePassword { .. }
+                | InputKind::RevealPassword { .. }
+                | InputKind::InspectPassword { .. }
+                | InputKind::Passphrase { .. }
+                | InputKind::NewPassphrase
+                | InputKind::RepeatPassphrase
+        )
+    }
+}
+
+/// Order the wallet list is shown in. The keys file always stays in the order wallets
+/// were added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SortOrder {
+    #[default]
+    File,
+    Address,
+    /// Labelled wallets by label, followed by the unlabelled ones
+    Label,
+    /// Oldest first, after the wallets whose creation time is unknown
+    Created,
+}
+
+impl SortOrder {
+    fn next(self) -> Self {
+        match self {
+            SortOrder::File => SortOrder::Address,
+            SortOrder::Address => SortOrder::Label,
+            SortOrder::Label => SortOrder::Created,
+            SortOrder::Created => SortOrder::File,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SortOrder::File => "file order",
+            SortOrder::Address => "address",
+            SortOrder::Label => "label",
+            SortOrder::Created => "creation time",
+        }
+    }
+}
+
+/// The free balance of an account, as far as it is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Balance {
+    /// Still being fetched
+    Loading,
+    /// Free balance in planck
+    Free(u128),
+    /// The node could not be reached or returned garbage
+    Unavailable,
+}
+
+/// Whether a status message reports a success or a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusKind {
+    Success,
+    Warning,
+    Error,
+}
+
+impl StatusKind {
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            StatusKind::Success => theme.success,
+            StatusKind::Warning => theme.warning,
+            StatusKind::Error => theme.error,
+        }
+    }
+}
+
+/// The top-level screen the app is showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppMode {
+    /// Waiting for the keys file password before anything is loaded
+    Locked,
+    /// Showing the wallet list
+    Unlocked,
+    /// Showing everything about the selected wallet in place of the list
+    Details,
+}
+
+/// Watches the directory of the keys file, so that it being replaced, deleted or
+/// recreated is noticed as well as it being modified in place.
+struct KeysWatcher {
+    _watcher: notify::RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl std::fmt::Debug for KeysWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KeysWatcher")
+    }
+}
+
+/// Keeps the OS clipboard handle alive; on Linux the copied text is lost once it is dropped.
+struct ClipboardHandle(arboard::Clipboard);
+
+impl std::fmt::Debug for ClipboardHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ClipboardHandle")
+    }
+}
+
+/// A network preset whose address format can be selected in the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Network {
+    pub name: &'static str,
+    pub ss58_prefix: u16,
+    /// Number of decimal places of the native token
+    pub token_decimals: u8,
+    pub token_symbol: &'static str,
+}
+
+impl Network {
+    pub fn ss58_format(&self) -> Ss58AddressFormat {
+        Ss58AddressFormat::custom(self.ss58_prefix)
+    }
+}
+
+/// Presets cycled through with 'n', starting with the generic Substrate format.
+pub const NETWORKS: [Network; 3] = [
+    Network {
+        name: "Substrate",
+        ss58_prefix: 42,
+        token_decimals: 12,
+        token_symbol: "UNIT",
+    },
+    Network {
+        name: "Polkadot",
+        ss58_prefix: 0,
+        token_decimals: 10,
+        token_symbol: "DOT",
+    },
+    Network {
+        name: "Kusama",
+        ss58_prefix: 2,
+        token_decimals: 12,
+        token_symbol: "KSM",
+    },
+];
+
+/// Number of fractional digits balances are shown with.
+const BALANCE_PRECISION: u32 = 4;
+
+/// Name of the network an address format belongs to, falling back to the SS58 registry
+/// for formats that are not one of the [`NETWORKS`] presets.
+pub fn network_name(format: Ss58AddressFormat) -> String {
+    NETWORKS
+        .iter()
+        .find(|network| network.ss58_prefix == format.prefix())
+        .map(|network| network.name.to_string())
+        .unwrap_or_else(|| format.to_string())
+}
+
+/// The CSV the wallets of `entries`, whose keys are `wallets`, are exported as: one row
+/// per wallet with its index, label, `network` and address, and its secret too if
+/// `secrets`.
+pub fn addresses_csv(
+    entries: &[KeyEntry],
+    wallets: &[WalletKeys],
+    network: &str,
+    secrets: bool,
+) -> String {
+    let mut csv = String::from(match secrets {
+        true => "index,label,network,address,secret\n",
+        false => "index,label,network,address\n",
+    });
+    for (i, (entry, wallet)) in entries.iter().zip(wallets).enumerate() {
+        csv.push_str(&format!(
+            "{},{},{},{}",
+            i + 1,
+            csv_field(entry.label.as_deref().unwrap_or_default()),
+            csv_field(network),
+            wallet.address
+        ));
+        if secrets {
+            csv.push_str(&format!(",{}", wallet.private_key().unwrap_or_default()));
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// `text` padded to exactly `width` characters, ending in "…" if it had to be cut short.
+fn column(text: &str, width: usize) -> String {
+    match text.chars().count() > width {
+        true => {
+            let kept: String = text.chars().take(width.saturating_sub(1)).collect();
+            format!("{}…", kept)
+        }
+        false => format!("{:<width$}", text, width = width),
+    }
+}
+
+/// The [`NETWORKS`] preset of an address format, falling back to the generic Substrate
+/// one whose token formats that are not a preset use.
+fn token_network(format: Ss58AddressFormat) -> &'static Network {
+    NETWORKS
+        .iter()
+        .find(|network| network.ss58_prefix == format.prefix())
+        .unwrap_or(&NETWORKS[0])
+}
+
+/// Formats a time in seconds since the Unix epoch as a UTC date and time, e.g.
+/// `2024-05-17 09:41`.
+fn format_timestamp(secs: u64) -> String {
+    let (days, time) = (secs / 86_400, secs % 86_400);
+
+    // The proleptic Gregorian date of a day count, as in Howard Hinnant's
+    // `civil_from_days`: years are counted from March, in 400-year eras of 146097 days.
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60
+    )
+}
+
+/// Formats a balance in planck as tokens of the network an address format belongs to,
+/// e.g. `1,234.5678 DOT`.
+pub fn format_balance(planck: u128, format: Ss58AddressFormat) -> String {
+    let network = token_network(format);
+
+    let unit = 10u128.pow(network.token_decimals.into());
+    let whole = planck / unit;
+    let shown_digits = BALANCE_PRECISION.min(network.token_decimals.into());
+    let fraction = planck % unit / 10u128.pow(u32::from(network.token_decimals) - shown_digits);
+
+    let digits = whole.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    if planck > 0 && whole == 0 && fraction == 0 {
+        return format!(
+            "<0.{:0>width$} {}",
+            1,
+            network.token_symbol,
+            width = shown_digits as usize
+        );
+    }
+
+    let fraction = format!("{:0>width$}", fraction, width = shown_digits as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{} {}", grouped, network.token_symbol)
+    } else {
+        format!("{}.{} {}", grouped, fraction, network.token_symbol)
+    }
+}
+
+/// Parses an amount of tokens of the network an address format belongs to, such as
+/// `1.5` or `1,000`, into planck.
+pub fn parse_balance(amount: &str, format: Ss58AddressFormat) -> Result<u128, String> {
+    let network = token_network(format);
+    let amount = amount.trim().replace(',', "");
+    let (whole, fraction) = amount.split_once('.').unwrap_or((&amount, ""));
+
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if whole.is_empty() && fraction.is_empty() || !is_digits(whole) || !is_digits(fraction) {
+        return Err(format!(
+            "Not an amount of {}: {}",
+            network.token_symbol, amount
+        ));
+    }
+    if fraction.len() > network.token_decimals.into() {
+        return Err(format!(
+            "{} has only {} decimal places",
+            network.token_symbol, network.token_decimals
+        ));
+    }
+
+    let too_large = || format!("Amount is too large: {}", amount);
+    let unit = 10u128.pow(network.token_decimals.into());
+    let whole: u128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| too_large())?
+    };
+    let fraction = format!(
+        "{:0<width$}",
+        fraction,
+        width = network.token_decimals as usize
+    );
+    let fraction: u128 = fraction.parse().unwrap_or(0);
+
+    whole
+        .checked_mul(unit)
+        .and_then(|planck| planck.checked_add(fraction))
+        .ok_or_else(too_large)
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new(DEFAULT_KEYS_PATH)
+    }
+}
+
+impl App {
+    /// Construct a new instance of [`App`] managing the keys file at `keys_path`, which
+    /// is created on first write if it does not exist.
+    pub fn new(keys_path: impl Into<String>) -> Self {
+        let mut app = Self {
+            running: true,
+            mode: AppMode::Locked,
+            keys_encrypted: false,
+            password_input: Zeroizing::new(String::new()),
+            new_password: None,
+            new_passphrase: None,
+            lock_error: None,
+            failed_unlocks: 0,
+            button_pressed: false,
+            seeds: Vec::new(),
+            wallets: Vec::new(),
+            eth_addresses: Vec::new(),
+            account_ids: Vec::new(),
+            fingerprints: Vec::new(),
+            address_formats: Vec::new(),
+            scheme_accounts: Vec::new(),
+            show_public_keys: false,
+            selected: 0,
+            list_state: ListState::default(),
+            list_height: 0,
+            list_area: Rect::default(),
+            list_wallets: Vec::new(),
+            last_click: None,
+            filter: None,
+            marked: HashSet::new(),
+            sort: SortOrder::default(),
+            scheme: KeyScheme::Sr25519,
+            ss58_format: NETWORKS[0].ss58_format(),
+            mnemonic_words: None,
+            config_path: None,
+            keys_path: keys_path.into(),
+            profiles: Vec::new(),
+            profile: 0,
+            uri_schemes: HashMap::new(),
+            watcher: None,
+            keys_file_exists: false,
+            reload_debounce: DEFAULT_RELOAD_DEBOUNCE,
+            reload_due: None,
+            status: None,
+            status_set_at: None,
+            clipboard: None,
+            copied_secret: None,
+            clipboard_clear: Some(DEFAULT_CLIPBOARD_CLEAR),
+            auto_lock: None,
+            last_input: Instant::now(),
+            input: None,
+            popup: None,
+            vault: None,
+            vanity: None,
+            batch: None,
+            rpc_url: rpc::DEFAULT_URL.to_string(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            theme: Theme::default(),
+            key_bindings: keymap::DEFAULT_KEY_BINDINGS.to_vec(),
+            startup_warning: None,
+            balances: HashMap::new(),
+            connecting: None,
+            balance_updates: None,
+            transfer: None,
+            qr_code: None,
+            revealed: None,
+            confirm: None,
+            show_help: false,
+            dry_run: false,
+            undo: Vec::new(),
+            state_path: None,
+            address_book_path: address_book::default_path(),
+            address_book: None,
+            book_view: None,
+        };
+        app.check_keys_encrypted();
+        app
+    }
+
+    /// Applies the settings of the config file. The keys path is left alone, since it is
+    /// resolved together with the command line.
+    pub fn with_config(mut self, config: &Config) -> Self {
+        if let Some(format) = config.ss58_format() {
+            self.ss58_format = format;
+        }
+        if let Some(url) = &config.rpc_url {
+            self.rpc_url = url.clone();
+        }
+        self.mnemonic_words = config.mnemonic_words;
+        if let Some(secs) = config.clipboard_clear_secs {
+            self.clipboard_clear = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        self.auto_lock = config
+            .auto_lock_secs
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs);
+        self.uri_schemes = config
+            .uri_schemes
+            .iter()
+            .filter_map(|(name, scheme)| {
+                let network = NETWORKS
+                    .iter()
+                    .find(|network| network.name.eq_ignore_ascii_case(name))?;
+                Some((network.ss58_prefix, scheme.clone()))
+            })
+            .collect();
+        self.config_path = config.path.clone();
+        if let Some(path) = &config.address_book {
+            self.address_book_path = Some(path.clone());
+        }
+        if let Some(interval) = config.poll_interval_ms {
+            self.poll_interval = Duration::from_millis(interval);
+        }
+        if let Some(debounce) = config.reload_debounce_ms {
+            self.reload_debounce = Duration::from_millis(debounce);
+        }
+        if let Some(theme) = config.theme.as_deref().and_then(Theme::by_name) {
+            self.theme = theme;
+        }
+        config.colors.apply(&mut self.theme);
+        self.set_profiles(&config.profiles);
+        // A keymap with conflicts never gets here, `Config::load` rejects it.
+        if let Ok(bindings) = keymap::key_bindings(config.keymap.as_deref(), &config.keys) {
+            self.key_bindings = bindings;
+        }
+        self
+    }
+
+    /// Makes the keys files of `profiles` available to switch to. The keys file opened at
+    /// startup becomes the profile with the same path, or a `default` one in front.
+    fn set_profiles(&mut self, profiles: &BTreeMap<String, String>) {
+        self.profiles = profiles
+            .iter()
+            .map(|(name, keys_path)| Profile {
+                name: name.clone(),
+                keys_path: keys_path.clone(),
+            })
+            .collect();
+        if self.profiles.is_empty() {
+            return;
+        }
+
+        match self
+            .profiles
+            .iter()
+            .position(|profile| profile.keys_path == self.keys_path)
+        {
+            Some(current) => self.profile = current,
+            None => {
+                self.profiles.insert(
+                    0,
+                    Profile {
+                        name: "default".to_string(),
+                        keys_path: self.keys_path.clone(),
+                    },
+                );
+                self.profile = 0;
+            }
+        }
+    }
+
+    /// Keeps every change in memory when `dry_run` is set: the keys file and exports are
+    /// never written, while the wallet list shows the changes as if they were.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Remembers the wallet last selected in each keys file in the state file at `path`,
+    /// and selects it again on unlocking. Without one, the first wallet is selected.
+    pub fn with_state_file(mut self, path: Option<PathBuf>) -> Self {
+        self.state_path = path;
+        self
+    }
+
+    /// Shows `warning` on the lock screen, and in the status line once unlocked. Several
+    /// warnings are shown together.
+    pub fn with_startup_warning(mut self, warning: impl Into<String>) -> Self {
+        let warning = warning.into();
+        warn!("{}", warning);
+        self.startup_warning = Some(match self.startup_warning.take() {
+            Some(earlier) => format!("{}; {}", earlier, warning),
+            None => warning,
+        });
+        self
+    }
+
+    /// Run the application's main loop.
+    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
+        self.running = true;
+
+        while self.running {
+            if self.mode != AppMode::Locked {
+                self.check_for_updates();
+            }
+
+            if self
+                .status_set_at
+                .is_some_and(|set_at| set_at.elapsed() >= STATUS_TIMEOUT)
+            {
+                self.status = None;
+                self.status_set_at = None;
+            }
+
+            if self
+                .revealed
+                .as_ref()
+                .is_some_and(|revealed| revealed.shown_at.elapsed() >= REVEAL_TIMEOUT)
+            {
+                self.revealed = None;
+            }
+
+            if let (Some((_, copied_at)), Some(timeout)) =
+                (&self.copied_secret, self.clipboard_clear)
+            {
+                if copied_at.elapsed() >= timeout {
+                    self.clear_copied_secret();
+                }
+            }
+
+            self.check_idle();
+
+            if self.vanity.is_some() {
+                self.check_vanity();
+            }
+
+            if self.batch.is_some() {
+                self.check_batch();
+            }
+
+            if self.connecting.is_some() {
+                self.check_connection();
+            }
+
+            if self.balance_updates.is_some() {
+                self.check_balances();
+            }
+
+            if self
+                .transfer
+                .as_ref()
+                .is_some_and(|transfer| !transfer.done)
+            {
+                self.check_transfer();
+            }
+
+            terminal.draw(|frame| self.render(frame))?;
+            if event::poll(self.poll_interval)? {
+                self.handle_crossterm_events()?;
+            }
+        }
+        self.clear_copied_secret();
+
+        if self.failed_unlocks >= MAX_UNLOCK_ATTEMPTS {
+            return Err(eyre!("Too many failed password attempts"));
+        }
+        Ok(())
+    }
+
+    /// Notes whether the keys file exists and is encrypted at rest, so that the lock screen
+    /// knows whether to ask for its password.
+    fn check_keys_encrypted(&mut self) {
+        self.keys_encrypted =
+            std::fs::read(&self.keys_path).is_ok_and(|data| vault::is_encrypted(&data));
+    }
+
+    /// Decrypts the keys file with `password`, keeping it for later reloads and writes.
+    ///
+    /// A wrong password leaves both the app and the file untouched.
+    fn unlock(&mut self, password: &str) -> Result<(), std::io::Error> {
+        let mut vault = Vault::new(password);
+        let loaded = wallet::load_wallets_from_file(&self.keys_path, Some(&mut vault))?;
+
+        self.vault = Some(vault);
+        self.set_loaded(loaded);
+        Ok(())
+    }
+
+    /// Encrypts a plaintext keys file with `password`. All later writes stay encrypted.
+    fn encrypt_keys(&mut self, password: &str) -> Result<(), std::io::Error> {
+        let loaded = wallet::load_wallets_from_file(&self.keys_path, None)?;
+        let mut vault = Vault::new(password);
+
+        // Skipped lines are left behind in the backup of the plaintext file.
+        if !self.dry_run {
+            wallet::write_wallets_to_file(&self.keys_path, &loaded.seeds, Some(&mut vault))?;
+        }
+
+        self.vault = Some(vault);
+        self.set_loaded(loaded);
+        Ok(())
+    }
+
+    /// Starts watching the keys file for changes made outside the app.
+    fn watch_keys_file(&mut self) -> notify::Result<()> {
+        use notify::Watcher;
+
+        let path = Path::new(&self.keys_path);
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(
+            wallet::parent_dir(path),
+            notify::RecursiveMode::NonRecursive,
+        )?;
+
+        self.watcher = Some(KeysWatcher {
+            _watcher: watcher,
+            events,
+        });
+        Ok(())
+    }
+
+    /// Reloads the seeds once the keys file has stayed unchanged for the debounce time
+    /// after the watcher reported a change, or after the file was deleted or reappeared.
+    /// A burst of changes thus reloads only once. A deleted file reloads as an empty list.
+    ///
+    /// Whether the file exists is checked on every call as well, since a watcher misses
+    /// the deletion when the whole directory goes away.
+    fn check_for_updates(&mut self) {
+        use notify::event::{EventKind, ModifyKind};
+
+        let keys_path = Path::new(&self.keys_path).to_path_buf();
+        let existed = self.keys_file_exists;
+        // Unlike events, this stays true until the reload, so it must not postpone it.
+        let appeared_or_vanished = keys_path.exists() != existed;
+        let mut changed = false;
+
+        let events: Vec<_> = match &self.watcher {
+            Some(watcher) => watcher.events.try_iter().collect(),
+            None => Vec::new(),
+        };
+        let file_name = keys_path.file_name();
+
+        for event in events {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    self.set_status(
+                        StatusKind::Error,
+                        format!("Error watching the keys file: {}", e),
+                    );
+                    continue;
+                }
+            };
+            // Reading the file, including our own reloads, must not trigger another one.
+            if matches!(
+                event.kind,
+                EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(_))
+            ) {
+                continue;
+            }
+            changed |= event.paths.iter().any(|path| path.file_name() == file_name);
+        }
+
+        let now = Instant::now();
+        if changed || appeared_or_vanished && self.reload_due.is_none() {
+            self.reload_due = Some(now + self.reload_debounce);
+        }
+
+        if self.reload_due.is_some_and(|due| now >= due) {
+            self.reload_due = None;
+            info!(path = %keys_path.display(), "Keys file changed, reloading");
+            if let Err(e) = self.load_seeds() {
+                self.set_status(StatusKind::Error, format!("Error loading seeds: {}", e));
+            } else if existed && !self.keys_file_exists {
+                self.set_status(StatusKind::Warning, "The keys file was deleted");
+            } else if !existed && self.keys_file_exists {
+                self.set_status(StatusKind::Success, "The keys file has reappeared");
+            }
+        }
+    }
+
+    /// Reloads the keys file right away instead of waiting for the watcher, dropping any
+    /// reload it has pending.
+    fn reload(&mut self) {
+        self.reload_due = None;
+        self.status = None;
+        info!(path = %self.keys_path, "Reloading the keys file");
+        self.check_keys_encrypted();
+        if let Err(e) = self.load_seeds() {
+            self.set_status(StatusKind::Error, format!("Error loading seeds: {}", e));
+            return;
+        }
+
+        let reloaded = format!(
+            "Reloaded {} wallet{}",
+            self.seeds.len(),
+            if self.seeds.len() == 1 { "" } else { "s" }
+        );
+        match self.status.take() {
+            Some((warning, StatusKind::Warning)) => {
+                self.set_status(StatusKind::Warning, format!("{}. {}", reloaded, warning))
+            }
+            _ => self.set_status(StatusKind::Success, reloaded),
+        }
+    }
+
+    fn load_seeds(&mut self) -> Result<()> {
+        let loaded = wallet::load_wallets_from_file(&self.keys_path, self.vault.as_mut())?;
+        self.set_loaded(loaded);
+        Ok(())
+    }
+
+    /// Appends `entries` to the keys file and shows the wallets it then holds. A dry run
+    /// only adds them to the list, after the same checks.
+    fn save_wallets(&mut self, entries: &[KeyEntry]) -> Result<(), std::io::Error> {
+        if self.dry_run {
+            wallet::check_new_entries(&self.seeds, entries)?;
+            let mut seeds = self.seeds.clone();
+            seeds.extend_from_slice(entries);
+            self.set_seeds(seeds);
+            return Ok(());
+        }
+
+        wallet::save_wallets_to_file(&self.keys_path, entries, self.vault.as_mut())?;
+        let loaded = wallet::load_wallets_from_file(&self.keys_path, self.vault.as_mut())?;
+        self.set_loaded(loaded);
+        Ok(())
+    }
+
+    /// Replaces the wallets of the keys file with `seeds` and shows them. A dry run only
+    /// shows them.
+    fn write_wallets(&mut self, seeds: Vec<KeyEntry>) -> Result<(), std::io::Error> {
+        if !self.dry_run {
+            wallet::write_wallets_to_file(&self.keys_path, &seeds, self.vault.as_mut())?;
+        }
+        self.set_seeds(seeds);
+        Ok(())
+    }
+
+    /// Writes an export to the file at `path`, unless this is a dry run.
+    fn write_export(&self, path: &Path, contents: impl AsRef<[u8]>) -> Result<(), String> {
+        if self.dry_run {
+            return Ok(());
+        }
+        std::fs::write(path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// The wallets an export writes: the marked ones if any are, otherwise all of them.
+    fn exported_entries(&self) -> Vec<KeyEntry> {
+        self.seeds
+            .iter()
+            .zip(&self.fingerprints)
+            .filter(|(_, fingerprint)| self.marked.is_empty() || self.marked.contains(*fingerprint))
+            .map(|(entry, _)| entry.clone())
+            .collect()
+    }
+
+    /// What an export did, for its status message: `Exported` unless this is a dry run.
+    fn exported(&self) -> &'static str {
+        if self.dry_run {
+            "Dry run, not exported"
+        } else {
+            "Exported"
+        }
+    }
+
+    /// Shows the wallets read from the keys file, warning about any lines that were
+    /// skipped.
+    fn set_loaded(&mut self, loaded: wallet::LoadedWallets) {
+        debug!(wallets = loaded.seeds.len(), "Loaded the keys file");
+        self.keys_file_exists = Path::new(&self.keys_path).exists();
+        self.set_seeds(loaded.seeds);
+
+        let mut warnings = Vec::new();
+        let skipped: Vec<String> = loaded
+            .skipped
+            .iter()
+            .map(|(line, e)| format!("line {}: {}", line, e))
+            .collect();
+        match skipped.len() {
+            0 => {}
+            1 => warnings.push(format!("Skipped invalid {} of the keys file", skipped[0])),
+            count => warnings.push(format!(
+                "Skipped {} invalid lines of the keys file: {}",
+                count,
+                skipped.join("; ")
+            )),
+        }
+        match loaded.duplicates {
+            0 => {}
+            1 => warnings.push("Ignored 1 duplicate wallet in the keys file".to_string()),
+            count => warnings.push(format!(
+                "Ignored {} duplicate wallets in the keys file",
+                count
+            )),
+        }
+
+        if !warnings.is_empty() {
+            self.set_status(StatusKind::Warning, warnings.join(". "));
+        }
+    }
+
+    /// Replaces the in-memory seeds and rebuilds the cached addresses.
+    fn set_seeds(&mut self, seeds: Vec<KeyEntry>) {
+        self.seeds = seeds;
+        self.refresh_addresses();
+        let fingerprints: HashSet<&[u8; 32]> = self.fingerprints.iter().collect();
+        self.marked
+            .retain(|fingerprint| fingerprints.contains(fingerprint));
+        self.selected = self.selected.min(self.seeds.len().saturating_sub(1));
+        self.move_selection(0);
+    }
+
+    /// Rebuilds the cached keys and addresses from `seeds` in the active SS58 format.
+    fn refresh_addresses(&mut self) {
+        self.wallets = self
+            .seeds
+            .iter()
+            .map(|entry| WalletKeys::from_entry(entry, self.ss58_format))
+            .collect();
+        self.eth_addresses = self.seeds.iter().map(KeyEntry::eth_address).collect();
+        self.account_ids = self.seeds.iter().map(KeyEntry::account_id).collect();
+        self.fingerprints = self.seeds.iter().map(KeyEntry::fingerprint).collect();
+        self.address_formats = self
+            .wallets
+            .iter()
+            .map(|wallet| {
+                AccountId32::from_ss58check_with_version(&wallet.address)
+                    .ok()
+                    .map(|(_, format)| format)
+            })
+            .collect();
+        self.scheme_accounts = self
+            .seeds
+            .iter()
+            .map(|entry| match entry.seed() {
+                Some(_) => KeyScheme::ALL
+                    .into_iter()
+                    .map(|scheme| entry.account_under(scheme))
+                    .collect(),
+                None => Vec::new(),
+            })
+            .collect();
+    }
+
+    /// Switches to the next network preset and re-renders all addresses in its format.
+    fn cycle_network(&mut self) {
+        let next = NETWORKS
+            .iter()
+            .position(|network| network.ss58_prefix == self.ss58_format.prefix())
+            .map_or(0, |i| (i + 1) % NETWORKS.len());
+
+        self.ss58_format = NETWORKS[next].ss58_format();
+        self.refresh_addresses();
+    }
+
+    /// Opens the keys file of the next profile, asking for its password first if it is
+    /// encrypted.
+    fn cycle_profile(&mut self) {
+        if self.profiles.len() < 2 {
+            self.set_status(
+                StatusKind::Warning,
+                "List profiles in the config file to switch between keys files",
+            );
+            return;
+        }
+        if self
+            .transfer
+            .as_ref()
+            .is_some_and(|transfer| !transfer.done)
+        {
+            self.set_status(
+                StatusKind::Error,
+                "Wait for the transfer to finish before switching profiles",
+            );
+            return;
+        }
+
+        self.switch_profile((self.profile + 1) % self.profiles.len());
+    }
+
+    /// Forgets the wallets of the open keys file and its password, along with the work
+    /// tied to them, such as a vanity search that would save into it. Secrets are wiped
+    /// from memory as they are dropped.
+    fn close_keys_file(&mut self) {
+        self.vault = None;
+        self.watcher = None;
+        self.reload_due = None;
+        self.vanity = None;
+        self.batch = None;
+        self.undo.clear();
+        self.connecting = None;
+        self.transfer = None;
+        self.input = None;
+        self.popup = None;
+        self.confirm = None;
+        self.qr_code = None;
+        self.revealed = None;
+        self.book_view = None;
+        self.filter = None;
+        self.marked.clear();
+        self.selected = 0;
+        *self.list_state.offset_mut() = 0;
+        self.set_seeds(Vec::new());
+
+        self.password_input = Zeroizing::new(String::new());
+        self.new_password = None;
+        self.new_passphrase = None;
+        self.lock_error = None;
+    }
+
+    /// Locks an encrypted keys file again: its wallets are wiped from memory until the
+    /// password is entered, which decrypts them from disk anew.
+    fn lock(&mut self) {
+        if self.vault.is_none() {
+            self.set_status(
+                StatusKind::Error,
+                "The keys file is not encrypted, so there is no password to lock it with",
+            );
+            return;
+        }
+        if self
+            .transfer
+            .as_ref()
+            .is_some_and(|transfer| !transfer.done)
+        {
+            self.set_status(
+                StatusKind::Error,
+                "Wait for the transfer to finish before locking",
+            );
+            return;
+        }
+
+        info!(path = %self.keys_path, "Locking");
+        self.remember_selection();
+        self.clear_copied_secret();
+        self.close_keys_file();
+        self.show_help = false;
+        self.check_keys_encrypted();
+        self.mode = AppMode::Locked;
+        self.set_status(StatusKind::Success, "Locked");
+    }
+
+    /// Locks an unlocked, encrypted keys file once there has been no input for the
+    /// configured time. A running transfer holds it off until it is done.
+    fn check_idle(&mut self) {
+        let Some(timeout) = self.auto_lock else {
+            return;
+        };
+        if self.mode == AppMode::Locked
+            || self.vault.is_none()
+            || self.last_input.elapsed() < timeout
+            || self
+                .transfer
+                .as_ref()
+                .is_some_and(|transfer| !transfer.done)
+        {
+            return;
+        }
+
+        info!(idle_secs = timeout.as_secs(), "Locking after no input");
+        self.lock();
+        self.set_status(
+            StatusKind::Warning,
+            format!("Locked after {} seconds without input", timeout.as_secs()),
+        );
+    }
+
+    /// Closes the open keys file and opens the one of the profile at `index` instead.
+    /// Work tied to the old file, such as a vanity search that would save into it, is
+    /// dropped.
+    fn switch_profile(&mut self, index: usize) {
+        let profile = self.profiles[index].clone();
+        info!(profile = %profile.name, path = %profile.keys_path, "Switching profile");
+        self.remember_selection();
+
+        self.profile = index;
+        self.keys_path = profile.keys_path;
+        self.close_keys_file();
+
+        // Failed unlocks keep counting, so that switching back and forth gains no tries.
+        self.check_keys_encrypted();
+        if self.keys_encrypted {
+            self.mode = AppMode::Locked;
+            return;
+        }
+        self.mode = AppMode::Unlocked;
+
+        if let Err(e) = self.load_seeds() {
+            self.set_status(StatusKind::Error, format!("Error loading seeds: {}", e));
+            return;
+        }
+        match self.watch_keys_file() {
+            Ok(()) => self.set_status(
+                StatusKind::Success,
+                format!("Switched to profile {}", profile.name),
+            ),
+            Err(e) => self.set_status(
+                StatusKind::Error,
+                format!("Not watching the keys file: {}", e),
+            ),
+        }
+        self.check_keys_permissions();
+    }
+
+    /// Warns when users other than its owner may read or write the keys file, and offers to
+    /// restrict it to the owner, unless this is a dry run.
+    fn check_keys_permissions(&mut self) {
+        let Some(mode) = wallet::exposed_mode(Path::new(&self.keys_path)) else {
+            return;
+        };
+
+        warn!(path = %self.keys_path, mode = format!("{:o}", mode), "Keys file is exposed");
+        self.set_status(
+            StatusKind::Warning,
+            format!(
+                "The keys file has mode {:04o}, so other users can access it",
+                mode
+            ),
+        );
+        if !self.dry_run && self.confirm.is_none() {
+            self.confirm = Some(Confirm {
+                prompt: format!(
+                    "{} has mode {:04o}. Make it private to you (0600)?",
+                    self.keys_path, mode
+                ),
+                action: ConfirmAction::RestrictPermissions,
+            });
+        }
+    }
+
+    fn restrict_keys_permissions(&mut self) {
+        match wallet::restrict_permissions(Path::new(&self.keys_path)) {
+            Ok(()) => {
+                info!(path = %self.keys_path, "Restricted the keys file to its owner");
+                self.set_status(StatusKind::Success, "The keys file is now private to you")
+            }
+            Err(e) => self.set_status(
+                StatusKind::Error,
+                format!("Failed to change the permissions of the keys file: {}", e),
+            ),
+        }
+    }
+
+    /// The key bound to `action`, quoted if `quoted`, or `no key` when it has none.
+    fn key_label(&self, action: Action, quoted: bool) -> String {
+        match self
+            .key_bindings
+            .iter()
+            .find(|binding| binding.action == action)
+        {
+            Some(binding) if quoted => format!("'{}'", binding.label()),
+            Some(binding) => binding.label(),
+            None => "no key".to_string(),
+        }
+    }
+
+    /// The first-run screen of an empty wallet list: the logo above a few pointers on how
+    /// to get started, centered vertically in `height` lines. The logo is left out when
+    /// it does not fit.
+    fn splash_lines(&self, height: usize) -> Vec<Line<'static>> {
+        let key = |action| self.key_label(action, true);
+        let hints = [
+            Line::styled(
+                "No wallets yet",
+                Style::default()
+                    .fg(self.theme.warning)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Line::raw(""),
+            Line::raw(format!(
+                "Press {} to generate a wallet, {} to import a mnemonic phrase",
+                key(Action::Generate),
+                key(Action::ImportPhrase)
+            )),
+            Line::raw(format!(
+                "or {} to watch an address you don't own.",
+                key(Action::WatchAddresses)
+            )),
+            Line::styled(
+                format!("Press {} for all keys.", key(Action::Help)),
+                Style::default().fg(self.theme.hint),
+            ),
+        ];
+
+        let mut lines = Vec::new();
+        if LOGO.len() + 1 + hints.len() <= height {
+            let style = Style::default().fg(self.theme.accent);
+            lines.extend(LOGO.iter().map(|line| Line::styled(*line, style)));
+            lines.push(Line::raw(""));
+        }
+        lines.extend(hints);
+
+        let padding = height.saturating_sub(lines.len()) / 2;
+        std::iter::repeat_n(Line::raw(""), padding)
+            .chain(lines)
+            .collect()
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        if self.mode == AppMode::Locked {
+            self.render_lock_screen(frame);
+            return;
+        }
+
+        // The wallet shown in detail may have been deleted or reloaded away meanwhile.
+        if self.mode == AppMode::Details && !self.has_selection() {
+            self.mode = AppMode::Unlocked;
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Fill(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .split(frame.area());
+
+        match self.mode {
+            AppMode::Details => self.render_details(frame, layout[0]),
+            _ => self.render_list(frame, layout[0]),
+        }
+
+        if let Some((message, kind)) = &self.status {
+            frame.render_widget(
+                Line::styled(
+                    message.as_str(),
+                    Style::default().fg(kind.color(&self.theme)),
+                ),
+                layout[1],
+            );
+        }
+
+        self.render_footer(frame, layout[2]);
+
+        if let Some(popup) = &self.popup {
+            self.render_popup(frame, popup);
+        }
+
+        if let Some(search) = &self.vanity {
+            self.render_vanity(frame, search);
+        }
+
+        if let Some(batch) = &self.batch {
+            self.render_batch(frame, batch);
+        }
+
+        if self.balance_updates.is_some() {
+            self.render_balance_fetch(frame);
+        }
+
+        if let Some(connecting) = &self.connecting {
+            self.render_connecting(frame, connecting);
+        }
+
+        if let Some(view) = &self.book_view {
+            self.render_address_book(frame, view);
+        }
+
+        if let Some(input) = &self.input {
+            self.render_input(frame, input);
+        }
+
+        if let Some(qr_code) = &self.qr_code {
+            self.render_qr_code(frame, qr_code);
+        }
+
+        if let Some(confirm) = &self.confirm {
+            self.render_confirm(frame, confirm);
+        }
+
+        if self.show_help {
+            self.render_help(frame);
+        }
+
+        if let Some(revealed) = &self.revealed {
+            self.render_revealed(frame, revealed);
+        }
+    }
+
+    /// Draws the generate button above the wallet list into `area`.
+    fn render_list(&mut self, frame: &mut Frame, area: Rect) {
+        let visible = self.visible_indices();
+        let mut items = Vec::new();
+
+        if self.seeds.is_empty() {
+            // The splash screen is built below, once the height of the list is known.
+        } else if visible.is_empty() {
+            items.push(ListItem::new(Line::styled(
+                "No wallets match the filter. Press Esc to clear it.",
+                Style::default().fg(self.theme.warning),
+            )));
+        } else {
+            // The columns are as wide as their widest value among the wallets shown, so that
+            // they line up. Only labels are cut short; the address is always shown in full.
+            let tag = |i: usize| match self.seeds[i].is_watch_only() {
+                true => "[watch-only]".to_string(),
+                false => format!("[{}]", self.seeds[i].scheme.name()),
+            };
+            let network = |i: usize| self.address_formats[i];
+            let width = |cell: &dyn Fn(usize) -> usize| {
+                visible.iter().map(|&i| cell(i)).max().unwrap_or_default()
+            };
+            let index_width = (visible.iter().max().copied().unwrap_or_default() + 1)
+                .to_string()
+                .len();
+            let tag_width = width(&|i| tag(i).len());
+            let path_width =
+                width(&|i| self.seeds[i].path.as_ref().map_or(0, |p| p.chars().count()));
+            let label_width = width(&|i| {
+                self.seeds[i]
+                    .label
+                    .as_ref()
+                    .map_or(0, |label| label.chars().count())
+            })
+            .min(LABEL_COLUMN_WIDTH);
+            let network_width =
+                width(&|i| network(i).map_or(0, |format| network_name(format).chars().count()));
+            let address_width = width(&|i| self.wallets[i].address.len());
+
+            for i in visible.iter().copied() {
+                let (entry, address) = (&self.seeds[i], &self.wallets[i].address);
+                let mut spans = Vec::new();
+                // Checkboxes only show once something is marked.
+                if !self.marked.is_empty() {
+                    spans.push(match self.marked.contains(&self.fingerprints[i]) {
+                        true => Span::styled("[x] ", Style::default().fg(self.theme.highlight)),
+                        false => Span::styled("[ ] ", Style::default().fg(self.theme.hint)),
+                    });
+                }
+                spans.extend([
+                    Span::styled(
+                        format!("Wallet {:>width$}: ", i + 1, width = index_width),
+                        Style::default().fg(self.theme.label),
+                    ),
+                    match entry.is_watch_only() {
+                        true => Span::styled(
+                            format!("{} ", column(&tag(i), tag_width)),
+                            Style::default()
+                                .fg(self.theme.watch_only)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        false => Span::styled(
+                            format!("{} ", column(&tag(i), tag_width)),
+                            Style::default().fg(self.theme.hint),
+                        ),
+                    },
+                ]);
+
+                if path_width > 0 {
+                    spans.push(Span::styled(
+                        format!(
+                            "{} ",
+                            column(entry.path.as_deref().unwrap_or_default(), path_width)
+                        ),
+                        Style::default().fg(self.theme.detail),
+                    ));
+                }
+
+                if label_width > 0 {
+                    spans.push(Span::styled(
+                        format!(
+                            "{} ",
+                            column(entry.label.as_deref().unwrap_or_default(), label_width)
+                        ),
+                        Style::default()
+                            .fg(self.theme.highlight)
+                            .add_modifier(Modifier::BOLD),
+                    ));
+                }
+
+                // Watch-only addresses keep the network they were imported for, so those of
+                // another network than the selected one stand out.
+                if network_width > 0 {
+                    let (name, color) = match network(i) {
+                        Some(format) if format == self.ss58_format => {
+                            (network_name(format), self.theme.detail)
+                        }
+                        Some(format) => (network_name(format), self.theme.warning),
+                        None => (String::new(), self.theme.detail),
+                    };
+                    spans.push(Span::styled(
+                        format!("{} ", column(&name, network_width)),
+                        Style::default().fg(color),
+                    ));
+                }
+
+                spans.push(Span::raw(column(address, address_width)));
+
+                if self.show_public_keys {
+                    spans.push(Span::styled(
+                        format!("  {}", self.wallets[i].public_key.as_deref().unwrap_or("—")),
+                        Style::default().fg(self.theme.detail),
+                    ));
+                }
+
+                let balance = match self.balances.get(&self.account_ids[i]) {
+                    Some(Balance::Loading) => Some("…".to_string()),
+                    Some(Balance::Free(free)) => Some(format_balance(*free, self.ss58_format)),
+                    Some(Balance::Unavailable) => Some("—".to_string()),
+                    None => None,
+                };
+                if let Some(balance) = balance {
+                    spans.push(Span::styled(
+                        format!("  {}", balance),
+                        Style::default().fg(self.theme.success),
+                    ));
+                }
+
+                if let Some(eth_address) = &self.eth_addresses[i] {
+                    spans.push(Span::styled(
+                        format!("  {}", eth_address),
+                        Style::default().fg(self.theme.eth_address),
+                    ));
+                }
+
+                spans.push(Span::styled(
+                    match entry.created {
+                        Some(created) => format!("  created {}", format_timestamp(created)),
+                        None => "  created unknown".to_string(),
+                    },
+                    Style::default().fg(self.theme.detail),
+                ));
+
+                items.push(ListItem::new(Line::from(spans)));
+            }
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(30), Constraint::Fill(1)])
+            .split(area);
+
+        self.list_height = layout[1].height.saturating_sub(2).max(1) as usize;
+        self.list_area = layout[1];
+        self.list_state
+            .select(visible.iter().position(|&i| i == self.selected));
+
+        let title = Line::from(vec![
+            Span::styled("Substrate ", Style::default().fg(self.theme.title[0])),
+            Span::styled("Wallet ", Style::default().fg(self.theme.title[1])),
+            Span::styled("Manager", Style::default().fg(self.theme.title[2])),
+        ])
+        .centered();
+
+        let key = |action| self.key_label(action, true);
+        let button_text = if self.button_pressed {
+            format!(
+                "New {} wallet generated! Press {} to generate another one.",
+                self.scheme.name(),
+                key(Action::Generate)
+            )
+        } else {
+            format!(
+                "Press {} to generate a new {} wallet from {}, {} for several ({} to switch scheme, {} to switch secret, {} for all keys)",
+                key(Action::Generate),
+                self.scheme.name(),
+                self.secret_kind(),
+                key(Action::GenerateBatch),
+                key(Action::CycleScheme),
+                key(Action::CycleMnemonicWords),
+                key(Action::Help)
+            )
+        };
+
+        let button = Paragraph::new(button_text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default()),
+            )
+            .style(Style::default().fg(self.theme.accent))
+            .centered();
+
+        frame.render_widget(button.block(Block::bordered().title(title)), layout[0]);
+
+        let wallet_count = self.seeds.len();
+        let matching = match &self.filter {
+            Some(filter) => format!(", {} matching \"{}\"", visible.len(), filter),
+            None => String::new(),
+        };
+        let sorted = match self.sort {
+            SortOrder::File => String::new(),
+            sort => format!(", sorted by {}", sort.name()),
+        };
+        let marked = match self.marked.len() {
+            0 => String::new(),
+            count => format!(", {} marked", count),
+        };
+        let wallet_title = format!(
+            "Wallets ({} total{}{}{}) - {} network, prefix {} ({} to switch, {} for public keys)",
+            wallet_count,
+            matching,
+            sorted,
+            marked,
+            network_name(self.ss58_format),
+            self.ss58_format.prefix(),
+            key(Action::CycleNetwork),
+            key(Action::TogglePublicKeys)
+        );
+
+        let block = Block::default().borders(Borders::ALL).title(wallet_title);
+        if self.seeds.is_empty() {
+            let splash = Paragraph::new(self.splash_lines(self.list_height))
+                .block(block)
+                .centered();
+            self.list_wallets.clear();
+            frame.render_widget(splash, layout[1]);
+            return;
+        }
+
+        // The list keeps the selected wallet in view, moving the offset as little as it
+        // can, and clamps it after a resize.
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(self.theme.selection);
+        frame.render_stateful_widget(list, layout[1], &mut self.list_state);
+
+        self.list_wallets = visible
+            .into_iter()
+            .skip(self.list_state.offset())
+            .take(self.list_height)
+            .collect();
+    }
+
+    /// Draws everything known about the selected wallet into `area`. Its secret stays
+    /// hidden unless revealed the same way as from the list.
+    fn render_details(&self, frame: &mut Frame, area: Rect) {
+        let (Some(entry), Some(wallet)) = (
+            self.seeds.get(self.selected),
+            self.wallets.get(self.selected),
+        ) else {
+            return;
+        };
+        let field = |name: &str, value: String| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{:<13}", name),
+                    Style::default().fg(self.theme.label),
+                ),
+                Span::raw(value),
+            ])
+        };
+        let key = |action| self.key_label(action, false);
+
+        let mut lines = vec![field("Address:", wallet.address.clone())];
+        if let Some(format) = self.address_formats[self.selected] {
+            lines.push(field(
+                "Network:",
+                format!("{} (prefix {})", network_name(format), format.prefix()),
+            ));
+        }
+        lines.push(field(
+            "Scheme:",
+            match entry.is_watch_only() {
+                true => "watch-only".to_string(),
+                false => entry.scheme.name().to_string(),
+            },
+        ));
+        lines.push(field(
+            "Public key:",
+            wallet.public_key.clone().unwrap_or_else(|| "—".to_string()),
+        ));
+        if let Some(eth_address) = &self.eth_addresses[self.selected] {
+            lines.push(field("EVM address:", eth_address.clone()));
+        }
+        if let Some(path) = &entry.path {
+            lines.push(field("Path:", path.clone()));
+        }
+        lines.push(field(
+            "Label:",
+            entry.label.clone().unwrap_or_else(|| "—".to_string()),
+        ));
+        lines.push(field(
+            "Created:",
+            entry
+                .created
+                .map_or_else(|| "unknown".to_string(), format_timestamp),
+        ));
+        match self.balances.get(&self.account_ids[self.selected]) {
+            Some(Balance::Free(free)) => {
+                lines.push(field("Balance:", format_balance(*free, self.ss58_format)))
+            }
+            Some(Balance::Loading) => lines.push(field("Balance:", "…".to_string())),
+            Some(Balance::Unavailable) => lines.push(field("Balance:", "—".to_string())),
+            None => {}
+        }
+
+        // The same account on every preset network, to compare them without switching.
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            "Address on each network:",
+            Style::default().fg(self.theme.label),
+        ));
+        let account = &self.account_ids[self.selected];
+        for network in &NETWORKS {
+            lines.push(field(
+                &format!("  {}:", network.name),
+                account.to_ss58check_with_version(network.ss58_format()),
+            ));
+        }
+
+        // What the same seed gives under the other schemes, for chains expecting one.
+        let scheme_accounts = &self.scheme_accounts[self.selected];
+        if !scheme_accounts.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::styled(
+                "Address under each scheme:",
+                Style::default().fg(self.theme.label),
+            ));
+            for (scheme, account) in KeyScheme::ALL.into_iter().zip(scheme_accounts) {
+                let label = format!("  {}:", scheme.name());
+                lines.push(match account {
+                    Ok(account) => {
+                        let mut line =
+                            field(&label, account.to_ss58check_with_version(self.ss58_format));
+                        if scheme == entry.scheme {
+                            line.push_span(Span::styled(
+                                " (this wallet)",
+                                Style::default().fg(self.theme.hint),
+                            ));
+                        }
+                        line
+                    }
+                    // The label keeps its own colour over the line's.
+                    Err(e) => field(&label, e.clone()).style(Style::default().fg(self.theme.hint)),
+                });
+            }
+        }
+
+        lines.push(Line::default());
+        if entry.phrase().is_some() {
+            lines.push(field(
+                "Phrase:",
+                format!("hidden, {} to reveal", key(Action::RevealPhrase)),
+            ));
+        }
+        if entry.is_protected() {
+            lines.push(field(
+                "Secret:",
+                "needs the wallet's passphrase, asked for when signing".to_string(),
+            ));
+        } else if !entry.is_watch_only() {
+            lines.push(field(
+                "Secret:",
+                format!("hidden, {} to reveal", key(Action::RevealSecret)),
+            ));
+        }
+
+        let title = match &entry.label {
+            Some(label) => format!("Wallet {}: {}", self.selected + 1, label),
+            None => format!("Wallet {}", self.selected + 1),
+        };
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(title));
+        frame.render_widget(paragraph, area);
+    }
+
+    fn render_revealed(&self, frame: &mut Frame, revealed: &RevealedSecret) {
+        let remaining = REVEAL_TIMEOUT.saturating_sub(revealed.shown_at.elapsed());
+        let area = Self::centered_rect(frame.area(), 70, 7);
+        let lines = vec![
+            Line::from(vec![
+                Span::styled("Address: ", Style::default().fg(self.theme.label)),
+                Span::raw(revealed.address.clone()),
+            ]),
+            Line::from(vec![
+                Span::styled(
+                    if revealed.phrase {
+                        "Phrase:  "
+                    } else {
+                        "Secret:  "
+                    },
+                    Style::default().fg(self.theme.error),
+                ),
+                Span::raw(revealed.secret.as_str()),
+            ]),
+            Line::styled(
+                "Anyone who sees this can take the wallet's funds.",
+                Style::default().fg(self.theme.warning),
+            ),
+            Line::default(),
+            Line::styled(
+                format!("Hidden in {}s or on any key", remaining.as_secs() + 1),
+                Style::default().fg(self.theme.hint),
+            ),
+        ];
+
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::bordered()
+                .title(if revealed.phrase {
+                    "Mnemonic phrase"
+                } else {
+                    "Secret seed"
+                })
+                .border_style(Style::default().fg(self.theme.error)),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+
+    /// Shows the network, the number of wallets, what the keyboard is currently driving
+    /// and the most used keys.
+    fn render_footer(&self, frame: &mut Frame, area: Rect) {
+        let mode = if self.input.is_some() {
+            "Input"
+        } else if self.book_view.is_some() {
+            "Address book"
+        } else if self.vanity.is_some() {
+            "Vanity search"
+        } else if self.batch.is_some() {
+            "Generating"
+        } else if self.connecting.is_some() {
+            "Connecting"
+        } else if self.balance_updates.is_some() {
+            "Fetching balances"
+        } else if self
+            .transfer
+            .as_ref()
+            .is_some_and(|transfer| !transfer.done)
+        {
+            "Transfer"
+        } else if self.mode == AppMode::Details {
+            "Details"
+        } else if self.filter.is_some() {
+            "Filter"
+        } else {
+            "Browse"
+        };
+
+        let hints = if self.input.is_some() {
+            vec![Span::raw("Enter: submit  Esc: cancel")]
+        } else {
+            match self.mode {
+                AppMode::Details => DETAIL_HINTS,
+                _ => FOOTER_HINTS,
+            }
+            .iter()
+            .filter_map(|(action, hint)| {
+                let binding = self.key_bindings.iter().find(|b| b.action == *action)?;
+                Some(Span::styled(
+                    format!("{}: {}  ", binding.label(), hint),
+                    self.action_style(*action),
+                ))
+            })
+            .collect()
+        };
+
+        let mut footer = vec![Span::styled(
+            format!(
+                " {} ({}) ",
+                network_name(self.ss58_format),
+                self.ss58_format.prefix()
+            ),
+            self.theme.badge,
+        )];
+        if let Some(profile) = self.profiles.get(self.profile) {
+            footer.push(Span::raw(" "));
+            footer.push(Span::styled(
+                format!(" {} ", profile.name),
+                self.theme.badge,
+            ));
+        }
+        if self.dry_run {
+            footer.push(Span::raw(" "));
+            footer.push(Span::styled(
+                " DRY RUN ",
+                self.theme.badge.bg(self.theme.warning),
+            ));
+        }
+        footer.extend([Span::raw(format!(
+            " {} wallet{} │ {} │ ",
+            self.seeds.len(),
+            if self.seeds.len() == 1 { "" } else { "s" },
+            mode
+        ))]);
+        footer.extend(hints);
+
+        frame.render_widget(
+            Paragraph::new(Line::from(footer)).style(self.theme.footer),
+            area,
+        );
+    }
+
+    /// How the key hint of `action` is drawn: dimmed while it has nothing to do.
+    fn action_style(&self, action: Action) -> Style {
+        match action {
+            Action::Undo if self.undo.is_empty() => Style::new().add_modifier(Modifier::DIM),
+            _ => Style::new(),
+        }
+    }
+
+    /// Lists every action that has a key, with its keys, in two columns.
+    fn render_help(&self, frame: &mut Frame) {
+        let actions: Vec<(String, Action)> = Action::all()
+            .filter_map(|action| {
+                let keys: Vec<String> = self
+                    .key_bindings
+                    .iter()
+                    .filter(|binding| binding.action == action)
+                    .map(KeyBinding::label)
+                    .collect();
+                (!keys.is_empty()).then(|| (keys.join("/"), action))
+            })
+            .collect();
+        let rows = actions.len().div_ceil(2);
+        let area = Self::centered_rect(frame.area(), 90, rows as u16 + 4);
+        let block = Block::bordered().title("Keys");
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let [columns, hint] =
+            Layout::vertical([Constraint::Length(rows as u16 + 1), Constraint::Length(1)])
+                .areas(inner);
+        let columns: [Rect; 2] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(columns);
+
+        for (actions, area) in actions.chunks(rows.max(1)).zip(columns) {
+            let lines: Vec<Line> = actions
+                .iter()
+                .map(|(keys, action)| {
+                    let description = match action.needs_selection() {
+                        true => format!("{} (selected wallet)", action.description()),
+                        false => action.description().to_string(),
+                    };
+                    Line::from(vec![
+                        Span::styled(
+                            format!(" {:<10}", keys),
+                            Style::default().fg(self.theme.highlight),
+                        ),
+                        Span::styled(description, self.action_style(*action)),
+                    ])
+                })
+                .collect();
+            frame.render_widget(Paragraph::new(lines), area);
+        }
+
+        frame.render_widget(
+            Line::styled(
+                "In dialogs, Enter submits and Esc cancels. Press any key to close.",
+                Style::default().fg(self.theme.hint),
+            ),
+            hint,
+        );
+    }
+
+    fn render_confirm(&self, frame: &mut Frame, confirm: &Confirm) {
+        let inner_width = (frame.area().width * 50 / 100).saturating_sub(2).max(1) as usize;
+        // Prompts may span several lines, each of which can wrap.
+        let prompt = Text::raw(confirm.prompt.as_str());
+        let prompt_rows: usize = prompt
+            .lines
+            .iter()
+            .map(|line| line.width().div_ceil(inner_width).max(1))
+            .sum();
+        let area = Self::centered_rect(frame.area(), 50, prompt_rows as u16 + 4);
+
+        let mut lines = prompt.lines;
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(
+            "y: yes  n: no",
+            Style::default().fg(self.theme.hint),
+        ));
+        let dialog = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::bordered()
+                .title("Confirm")
+                .border_style(Style::default().fg(self.theme.warning)),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(dialog, area);
+    }
+
+    /// Draws the address book over the main view, scrolled to keep the selected contact in
+    /// view, with the keys it takes below.
+    fn render_address_book(&self, frame: &mut Frame, view: &BookView) {
+        let contacts = self
+            .address_book
+            .as_ref()
+            .map(AddressBook::contacts)
+            .unwrap_or_default();
+        let rows = contacts.len().clamp(1, 15);
+        let area = Self::centered_rect(frame.area(), 80, rows as u16 + 4);
+        let block = Block::bordered().title(match view.picking {
+            Some(_) => "Address book: pick the destination",
+            None => "Address book",
+        });
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let [list, hint] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(inner);
+        let name_width = contacts
+            .iter()
+            .map(|contact| Line::raw(contact.name.as_str()).width())
+            .max()
+            .unwrap_or_default();
+        let first = view
+            .selected
+            .saturating_sub(list.height.saturating_sub(1) as usize);
+        let mut lines: Vec<Line> = contacts
+            .iter()
+            .enumerate()
+            .skip(first)
+            .map(|(i, contact)| {
+                let line = Line::from(vec![
+                    Span::styled(
+                        format!("{:<width$}  ", contact.name, width = name_width),
+                        Style::default().fg(self.theme.highlight),
+                    ),
+                    Span::styled(
+                        contact.address.as_str(),
+                        Style::default().fg(self.theme.address),
+                    ),
+                ]);
+                match i == view.selected {
+                    true => line.style(self.theme.selection),
+                    false => line,
+                }
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push(Line::styled(
+                "No contacts yet. Press a to add one.",
+                Style::default().fg(self.theme.hint),
+            ));
+        }
+        frame.render_widget(Paragraph::new(lines), list);
+
+        let enter = match view.picking {
+            Some(_) => "Enter: pick",
+            None => "Enter: transfer to",
+        };
+        frame.render_widget(
+            Line::styled(
+                format!("{}  a: add  e: edit  d: delete  Esc: back", enter),
+                Style::default().fg(self.theme.hint),
+            ),
+            hint,
+        );
+    }
+
+    /// Draws a QR code over the whole view. Each cell shows two modules stacked with a
+    /// half block, so the code comes out roughly square in a terminal font. Colors are
+    /// fixed to black on white rather than the terminal's own, which scanners need.
+    fn render_qr_code(&self, frame: &mut Frame, qr_code: &QrView) {
+        let area = frame.area();
+        let block = Block::bordered().title(format!("{} QR code", qr_code.title));
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let size = qr_code.width + 2 * QR_QUIET_ZONE;
+        let rows = size.div_ceil(2);
+        let hint =
+            Line::styled("c: copy  Esc: back", Style::default().fg(self.theme.hint)).centered();
+        // Long texts such as signatures wrap onto several lines.
+        let text_rows = qr_code.text.len().div_ceil(inner.width.max(1) as usize);
+
+        // Below the QR code: a blank line, the text and the hint.
+        if (inner.width as usize) < size || (inner.height as usize) < rows + 2 + text_rows {
+            let message = Paragraph::new(vec![
+                Line::raw(format!(
+                    "Enlarge the terminal to at least {}x{} to show the QR code.",
+                    size + 2,
+                    rows + 4 + text_rows
+                )),
+                Line::raw(qr_code.text.as_str()),
+                hint,
+            ])
+            .wrap(Wrap { trim: false });
+            frame.render_widget(message, inner);
+            return;
+        }
+
+        // Scanners need black on white whatever the theme.
+        let module = |dark: bool| if dark { Color::Black } else { Color::White };
+        let mut lines: Vec<Line> = (0..rows)
+            .map(|row| {
+                let y = row * 2;
+                let spans: Vec<Span> = (0..size)
+                    .map(|x| {
+                        let top = module(qr_code.is_dark(x, y));
+                        let bottom = module(y + 1 < size && qr_code.is_dark(x, y + 1));
+                        Span::styled("▀", Style::default().fg(top).bg(bottom))
+                    })
+                    .collect();
+                Line::from(spans).centered()
+            })
+            .collect();
+        lines.push(Line::raw(""));
+        lines.push(Line::raw(qr_code.text.as_str()).centered());
+        lines.push(hint);
+
+        let height = (rows + 2 + text_rows) as u16;
+        let top = inner.y + (inner.height - height) / 2;
+        let area = Rect {
+            y: top,
+            height,
+            ..inner
+        };
+        frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), area);
+    }
+
+    fn render_vanity(&self, frame: &mut Frame, search: &VanitySearch) {
+        let mut target = vec![Span::raw("Looking for "), Span::raw("?")];
+        if !search.pattern.prefix.is_empty() {
+            target.push(Span::styled(
+                search.pattern.prefix.as_str(),
+                Style::default().fg(self.theme.highlight),
+            ));
+        }
+        target.push(Span::raw("…"));
+        if !search.pattern.suffix.is_empty() {
+            target.push(Span::styled(
+                search.pattern.suffix.as_str(),
+                Style::default().fg(self.theme.highlight),
+            ));
+        }
+        target.push(Span::raw(format!(" ({} wallet)", search.scheme.name())));
+
+        let lines = vec![
+            Line::from(target),
+            Line::raw(format!(
+                "{} attempts on {} threads, {:.0}/s, ~{:.0} expected",
+                search.attempts.count(),
+                search.workers.len(),
+                search.attempts.per_second(),
+                search.pattern.expected_attempts()
+            )),
+            Line::styled(
+                "Each extra character makes the search about 58 times slower.",
+                Style::default().fg(self.theme.warning),
+            ),
+        ];
+
+        self.render_progress(
+            frame,
+            "Vanity search",
+            lines,
+            Progress::Ongoing {
+                elapsed: search.attempts.started.elapsed(),
+            },
+            "Esc: cancel",
+        );
+    }
+
+    fn render_batch(&self, frame: &mut Frame, batch: &BatchGeneration) {
+        let lines = vec![
+            Line::from(vec![
+                Span::raw("Generating "),
+                Span::styled(
+                    batch.count.to_string(),
+                    Style::default().fg(self.theme.highlight),
+                ),
+                Span::raw(format!(" {} wallets", batch.scheme.name())),
+            ]),
+            Line::raw(format!(
+                "{} threads, {:.0}/s",
+                batch.workers.len(),
+                batch.generated.per_second()
+            )),
+        ];
+
+        self.render_progress(
+            frame,
+            "Batch generation",
+            lines,
+            Progress::Steps {
+                done: batch.generated.count() as usize,
+                total: batch.count,
+            },
+            "Esc: cancel, saving nothing",
+        );
+    }
+
+    /// Shows the balance fetch running in the background, by the balances still missing.
+    fn render_balance_fetch(&self, frame: &mut Frame) {
+        let total = self.balances.len();
+        let done = self
+            .balances
+            .values()
+            .filter(|balance| !matches!(balance, Balance::Loading))
+            .count();
+        let lines = vec![Line::from(vec![
+            Span::raw("Asking "),
+            Span::styled(
+                self.rpc_url.as_str(),
+                Style::default().fg(self.theme.highlight),
+            ),
+            Span::raw(format!(
+                " for {} balance{}",
+                total,
+                if total == 1 { "" } else { "s" }
+            )),
+        ])];
+
+        self.render_progress(
+            frame,
+            "Balances",
+            lines,
+            Progress::Steps { done, total },
+            "Esc: stop, keeping the balances fetched so far",
+        );
+    }
+
+    /// Shows the attempt to reach the node with a spinner, or why it failed.
+    fn render_connecting(&self, frame: &mut Frame, connecting: &Connecting) {
+        let (lines, hint) = match &connecting.error {
+            None => (
+                vec![Line::from(vec![
+                    Span::styled(
+                        format!("{} ", spinner(connecting.started.elapsed())),
+                        Style::default().fg(self.theme.accent),
+                    ),
+                    Span::raw("Connecting to "),
+                    Span::styled(
+                        connecting.url.as_str(),
+                        Style::default().fg(self.theme.highlight),
+                    ),
+                    Span::raw("…"),
+                ])],
+                "Esc: cancel",
+            ),
+            Some(error) => (
+                vec![
+                    Line::from(vec![
+                        Span::raw("Cannot connect to "),
+                        Span::styled(
+                            connecting.url.as_str(),
+                            Style::default().fg(self.theme.highlight),
+                        ),
+                    ]),
+                    Line::styled(error.as_str(), Style::default().fg(self.theme.error)),
+                ],
+                "r: retry  Esc: close",
+            ),
+        };
+
+        // One spare row for a line that wraps.
+        let area = Self::centered_rect(frame.area(), 60, lines.len() as u16 + 4);
+        let block = Block::bordered().title("Node");
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let [text, hint_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(inner);
+        frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), text);
+        frame.render_widget(
+            Line::styled(hint, Style::default().fg(self.theme.hint)),
+            hint_area,
+        );
+    }
+
+    /// Draws the overlay of a long-running operation: `lines` about it, a bar showing its
+    /// `progress` and `cancel`, the hint on how to stop it.
+    fn render_progress(
+        &self,
+        frame: &mut Frame,
+        title: &str,
+        lines: Vec<Line>,
+        progress: Progress,
+        cancel: &str,
+    ) {
+        // One spare row for a line that wraps.
+        let area = Self::centered_rect(frame.area(), 60, lines.len() as u16 + 5);
+        let block = Block::bordered().title(title);
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let [text, bar, hint] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(inner);
+        frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), text);
+
+        let style = Style::default().fg(self.theme.accent);
+        match progress {
+            Progress::Steps { done, total } => {
+                let gauge = Gauge::default()
+                    .gauge_style(style)
+                    .ratio((done as f64 / total.max(1) as f64).min(1.0))
+                    .label(format!("{} of {}", done, total));
+                frame.render_widget(gauge, bar);
+            }
+            Progress::Ongoing { elapsed } => {
+                let width = bar.width as usize;
+                let (start, end) = Progress::sweep(width, elapsed);
+                let line = Line::from(vec![
+                    Span::raw(" ".repeat(start)),
+                    Span::styled("█".repeat(end - start), style),
+                ]);
+                frame.render_widget(Paragraph::new(line), bar);
+            }
+        }
+
+        frame.render_widget(
+            Paragraph::new(Line::styled(cancel, Style::default().fg(self.theme.hint))),
+            hint,
+        );
+    }
+
+    fn render_popup(&self, frame: &mut Frame, popup: &Popup) {
+        let width = frame.area().width * 70 / 100;
+        let inner_width = width.saturating_sub(2).max(1) as usize;
+        // Word wrapping can push a long value onto its own row, so allow one extra row for
+        // every line that wraps.
+        let wrapped_rows: usize = popup
+            .lines
+            .iter()
+            .map(|line| {
+                let width = line.width().max(1);
+                width.div_ceil(inner_width) + usize::from(width > inner_width)
+            })
+            .sum();
+        let area = Self::centered_rect(frame.area(), 70, wrapped_rows as u16 + 3);
+
+        let hint = if let Some(hint) = popup.hint {
+            hint
+        } else if popup.copy.is_some() {
+            "c: copy  any other key: close"
+        } else {
+            "Press any key to close"
+        };
+
+        let mut lines = popup.lines.clone();
+        lines.push(Line::styled(hint, Style::default().fg(self.theme.hint)));
+
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(popup.title.as_str()));
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+
+    fn render_lock_screen(&self, frame: &mut Frame) {
+        // A startup warning gets room for a couple of wrapped lines.
+        let height = if self.startup_warning.is_some() {
+            10
+        } else {
+            7
+        };
+        let area = Self::centered_rect(frame.area(), 60, height);
+
+        let prompt = if self.keys_encrypted {
+            "Enter the password of the keys file:"
+        } else if self.new_password.is_some() {
+            "Repeat the new password:"
+        } else {
+            "Set a password to encrypt the keys file (leave empty to keep it unencrypted):"
+        };
+
+        let mut lines = vec![
+            Line::raw(prompt),
+            Line::raw("*".repeat(self.password_input.chars().count())),
+        ];
+
+        if let Some(warning) = &self.startup_warning {
+            lines.push(Line::styled(
+                warning.as_str(),
+                Style::default().fg(StatusKind::Warning.color(&self.theme)),
+            ));
+        }
+
+        if let Some(error) = &self.lock_error {
+            let message = if self.failed_unlocks > 0 {
+                format!(
+                    "{} ({} attempts left)",
+                    error,
+                    MAX_UNLOCK_ATTEMPTS - self.failed_unlocks
+                )
+            } else {
+                error.clone()
+            };
+            lines.push(Line::styled(message, Style::default().fg(self.theme.error)));
+        } else {
+            let hint = match self.profiles.len() {
+                0 | 1 => "Enter: unlock  Esc: quit",
+                _ => "Enter: unlock  Tab: next profile  Esc: quit",
+            };
+            lines.push(Line::styled(hint, Style::default().fg(self.theme.hint)));
+        }
+
+        let title = Line::from(vec![
+            Span::styled("Substrate ", Style::default().fg(self.theme.title[0])),
+            Span::styled("Wallet ", Style::default().fg(self.theme.title[1])),
+            Span::styled("Manager", Style::default().fg(self.theme.title[2])),
+            Span::raw(match self.profiles.get(self.profile) {
+                Some(profile) => format!(" - Locked ({})", profile.name),
+                None => " - Locked".to_string(),
+            }),
+        ])
+        .centered();
+
+        let popup = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(title));
+
+        frame.render_widget(popup, area);
+    }
+
+    fn render_input(&self, frame: &mut Frame, input: &Input) {
+        let area = Self::centered_rect(frame.area(), 60, 5);
+
+        let value = if input.is_secret() {
+            "*".repeat(input.value.chars().count())
+        } else {
+            input.value.to_string()
+        };
+
+        let mut lines = vec![Line::from(vec![
+            Span::raw(value),
+            Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+        ])];
+
+        match (&input.error, &input.preview) {
+            (Some(error), _) => lines.push(Line::styled(
+                error.as_str(),
+                Style::default().fg(self.theme.error),
+            )),
+            (None, Some(preview)) => lines.push(Line::styled(
+                preview.as_str(),
+                Style::default().fg(self.theme.success),
+            )),
+            (None, None) => lines.push(Line::styled(
+                match input.kind {
+                    InputKind::TransferDestination => {
+                        "Enter: confirm  Tab: address book  Esc: cancel"
+                    }
+                    _ => "Enter: confirm  Esc: cancel",
+                },
+                Style::default().fg(self.theme.hint),
+            )),
+        }
+
+        let popup = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(input.title()));
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+
+    /// Returns a rect `percent_x` wide and `height` rows tall, centered in `area`.
+    fn centered_rect(area: Rect, percent_x: u16, height: u16) -> Rect {
+        let vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Fill(1),
+                Constraint::Length(height),
+                Constraint::Fill(1),
+            ])
+            .split(area);
+
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ])
+            .split(vertical[1])[1]
+    }
+
+    /// Reads the crossterm events and updates the state of [`App`].
+    fn handle_crossterm_events(&mut self) -> Result<()> {
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                self.last_input = Instant::now();
+                self.on_key_event(key)
+            }
+            Event::Mouse(mouse) => {
+                if matches!(mouse.kind, MouseEventKind::Down(_)) {
+                    self.last_input = Instant::now();
+                }
+                self.on_mouse_event(mouse)
+            }
+            Event::Resize(_, _) => {}
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Selects the wallet clicked in the list, and copies its address on a double click.
+    /// Clicks anywhere else, or while something is shown over the list, are ignored.
+    fn on_mouse_event(&mut self, mouse: MouseEvent) {
+        if mouse.kind != MouseEventKind::Down(MouseButton::Left) || self.list_is_covered() {
+            return;
+        }
+
+        let inner = self.list_area.inner(Margin::new(1, 1));
+        if !inner.contains(Position::new(mouse.column, mouse.row)) {
+            return;
+        }
+        let Some(&index) = self.list_wallets.get(usize::from(mouse.row - inner.y)) else {
+            return;
+        };
+
+        let now = Instant::now();
+        let double_click = self.last_click.is_some_and(|(clicked, at)| {
+            clicked == index && now.duration_since(at) <= DOUBLE_CLICK_TIME
+        });
+        if double_click {
+            self.last_click = None;
+            self.copy_selected_address();
+            return;
+        }
+
+        self.last_click = Some((index, now));
+        if index != self.selected {
+            self.selected = index;
+        }
+    }
+
+    /// Whether the wallet list is out of view or has a dialog, popup or overlay shown over
+    /// it.
+    fn list_is_covered(&self) -> bool {
+        self.mode != AppMode::Unlocked
+            || self.input.is_some()
+            || self.popup.is_some()
+            || self.confirm.is_some()
+            || self.qr_code.is_some()
+            || self.book_view.is_some()
+            || self.revealed.is_some()
+            || self.vanity.is_some()
+            || self.batch.is_some()
+            || self.connecting.is_some()
+            || self.balance_updates.is_some()
+            || self.show_help
+    }
+
+    fn on_key_event(&mut self, key: KeyEvent) {
+        if self.mode == AppMode::Locked {
+            self.on_lock_key_event(key);
+            return;
+        }
+
+        if self.input.is_some() {
+            self.on_input_key_event(key);
+            return;
+        }
+
+        if self.revealed.take().is_some() {
+            return;
+        }
+
+        if self.show_help {
+            if (key.modifiers, key.code) == (KeyModifiers::CONTROL, KeyCode::Char('c')) {
+                self.quit();
+            }
+            self.show_help = false;
+            return;
+        }
+
+        if self.qr_code.is_some() {
+            match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
+                (_, KeyCode::Esc | KeyCode::Char('Q') | KeyCode::Char('q')) => self.qr_code = None,
+                (_, KeyCode::Char('c')) => {
+                    let view = self.qr_code.as_ref().expect("checked above");
+                    let (title, text) = (view.title, view.text.clone());
+                    match self.copy_to_clipboard(text) {
+                        Ok(()) => self.set_status(StatusKind::Success, format!("{} copied", title)),
+                        Err(e) => {
+                            self.set_status(StatusKind::Error, format!("Failed to copy: {}", e))
+                        }
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if self.book_view.is_some() {
+            self.on_book_key_event(key);
+            return;
+        }
+
+        if self.vanity.is_some() {
+            match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
+                (_, KeyCode::Esc) => {
+                    self.vanity = None;
+                    self.set_status(StatusKind::Error, "Vanity search cancelled");
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if self.batch.is_some() {
+            match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
+                (_, KeyCode::Esc) => {
+                    self.batch = None;
+                    self.set_status(StatusKind::Error, "Generation cancelled; nothing saved");
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some(connecting) = &self.connecting {
+            match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
+                (_, KeyCode::Esc) => {
+                    // The attempt still running finishes unnoticed.
+                    if connecting.error.is_none() {
+                        self.set_status(StatusKind::Warning, "Connecting cancelled");
+                    }
+                    self.connecting = None;
+                }
+                (_, KeyCode::Char('r')) if connecting.error.is_some() => {
+                    self.connecting =
+                        Some(Connecting::start(&connecting.url, connecting.task.clone()));
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if self.balance_updates.is_some() {
+            match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
+                (_, KeyCode::Esc) => {
+                    // Dropping the receiver stops the fetch at its next result.
+                    self.balance_updates = None;
+                    self.balances
+                        .retain(|_, balance| !matches!(balance, Balance::Loading));
+                    self.set_status(StatusKind::Warning, "Balance fetch stopped");
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some(confirm) = &self.confirm {
+            match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
+                (_, KeyCode::Char('y') | KeyCode::Char('Y')) => {
+                    let action = confirm.action.clone();
+                    self.confirm = None;
+                    self.run_confirmed(action);
+                }
+                (_, KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc) => self.confirm = None,
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some(transfer) = self.transfer.as_mut().filter(|t| t.awaiting_confirmation) {
+            if (key.modifiers, key.code) == (KeyModifiers::CONTROL, KeyCode::Char('c')) {
+                self.quit();
+                return;
+            }
+
+            // Dropping the sender without sending cancels the transfer.
+            transfer.awaiting_confirmation = false;
+            let confirm = transfer.confirm.take();
+            if key.code == KeyCode::Char('y') && confirm.is_some_and(|c| c.send(()).is_ok()) {
+                transfer.state = Line::raw("Submitting…");
+                self.popup = Some(transfer.popup(&self.theme));
+            } else {
+                transfer.done = true;
+                self.popup = None;
+                self.set_status(StatusKind::Error, "Transfer cancelled");
+            }
+            return;
+        }
+
+        if let Some(popup) = self.popup.take() {
+            if let (KeyCode::Char('c'), Some(value)) = (key.code, popup.copy) {
+                let secret = popup.secret.then(|| Zeroizing::new(value.clone()));
+                match self.copy_to_clipboard(value) {
+                    Ok(()) if secret.is_some() => {
+                        self.copied_secret = secret.map(|secret| (secret, Instant::now()));
+                        self.set_status(
+                            StatusKind::Warning,
+                            format!("{} copied. The clipboard holds a secret", popup.title),
+                        )
+                    }
+                    Ok(()) => {
+                        self.set_status(StatusKind::Success, format!("{} copied", popup.title))
+                    }
+                    Err(e) => self.set_status(StatusKind::Error, format!("Failed to copy: {}", e)),
+                }
+            }
+            return;
+        }
+
+        // Letters typed with Ctrl held arrive uppercase when Shift is held too.
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let code = match key.code {
+            KeyCode::Char(c) if ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        let Some(&binding) = self
+            .key_bindings
+            .iter()
+            .find(|binding| binding.key == code && binding.ctrl == ctrl)
+        else {
+            return;
+        };
+
+        if binding.action.needs_selection() && !self.has_selection() {
+            return;
+        }
+
+        if self.mode == AppMode::Details && !binding.action.works_in_details() {
+            return;
+        }
+
+        if binding.action.needs_secret() && self.seeds[self.selected].is_watch_only() {
+            self.set_status(
+                StatusKind::Error,
+                "The selected wallet is watch-only and has no key to do that with",
+            );
+            return;
+        }
+        if binding.action.needs_secret()
+            && !matches!(
+                binding.action,
+                Action::Sign | Action::SignOffline | Action::Transfer
+            )
+            && self.seeds[self.selected].is_protected()
+        {
+            self.set_status(
+                StatusKind::Error,
+                "The selected wallet is passphrase-protected; only signing and transfers can use it",
+            );
+            return;
+        }
+
+        self.perform(binding.action);
+    }
+
+    /// Runs an action bound to a key of the wallet list.
+    fn perform(&mut self, action: Action) {
+        debug!(action = action.name(), "Key pressed");
+        match action {
+            Action::Quit => self.quit(),
+            Action::Lock => self.lock(),
+            Action::Back if self.mode == AppMode::Details => self.mode = AppMode::Unlocked,
+            Action::Back if self.filter.is_some() => self.set_filter(""),
+            Action::Back => self.quit(),
+            Action::Help => self.show_help = true,
+            Action::ShowDetails => self.mode = AppMode::Details,
+            Action::Generate => {
+                self.confirm = Some(Confirm {
+                    prompt: format!("Generate a new {} wallet?", self.scheme.name()),
+                    action: ConfirmAction::Generate,
+                })
+            }
+            Action::GenerateBatch => self.input = Some(Input::new(InputKind::GenerateCount)),
+            Action::GenerateProtected => self.input = Some(Input::new(InputKind::NewPassphrase)),
+            Action::VanitySearch => self.input = Some(Input::new(InputKind::VanityPattern)),
+            Action::ImportPhrase => self.input = Some(Input::new(InputKind::ImportPhrase)),
+            Action::ImportKeystore => self.input = Some(Input::new(InputKind::ImportKeystorePath)),
+            Action::ImportArchive => self.input = Some(Input::new(InputKind::ImportArchivePath)),
+            Action::MergeKeysFile => self.input = Some(Input::new(InputKind::MergeKeysFilePath)),
+            Action::ExportArchive => {
+                self.input = Some(Input::new(InputKind::ExportArchivePassword))
+            }
+            Action::ExportKeystore => {
+                self.input = Some(Input::new(InputKind::ExportKeystorePassword))
+            }
+            Action::WatchAddresses => self.input = Some(Input::new(InputKind::WatchAddresses)),
+            Action::ExportCsv => {
+                let mut input = Input::new(InputKind::ExportCsvPath);
+                let path = Path::new(&self.keys_path).with_file_name("addresses.csv");
+                input.value.push_str(&path.to_string_lossy());
+                self.input = Some(input);
+            }
+            Action::Delete if !self.marked.is_empty() => {
+                let fingerprints: Vec<[u8; 32]> = self.marked.iter().copied().collect();
+                self.confirm = Some(Confirm {
+                    prompt: format!(
+                        "Delete the {} marked wallet{} from the keys file?",
+                        fingerprints.len(),
+                        if fingerprints.len() == 1 { "" } else { "s" }
+                    ),
+                    action: ConfirmAction::DeleteMarked(fingerprints),
+                })
+            }
+            Action::Mark => {
+                let fingerprint = self.fingerprints[self.selected];
+                if !self.marked.remove(&fingerprint) {
+                    self.marked.insert(fingerprint);
+                }
+                self.move_selection(1);
+            }
+            Action::MarkAll => {
+                let marked: Vec<[u8; 32]> = self
+                    .visible_indices()
+                    .into_iter()
+                    .map(|i| self.fingerprints[i])
+                    .collect();
+                self.marked.extend(marked);
+                self.set_status(
+                    StatusKind::Success,
+                    format!("{} wallets marked", self.marked.len()),
+                );
+            }
+            Action::ClearMarks => self.marked.clear(),
+            Action::Delete => {
+                let address = self.wallets[self.selected].address.clone();
+                self.confirm = Some(Confirm {
+                    prompt: format!("Delete wallet {} from the keys file?", address),
+                    action: ConfirmAction::Delete {
+                        index: self.selected,
+                        address,
+                    },
+                })
+            }
+            Action::CopyAddress => self.copy_selected_address(),
+            Action::CopyUri => self.copy_selected_uri(),
+            Action::CopyAllAddresses => self.copy_visible_addresses(),
+            Action::Undo => self.ask_undo(),
+            Action::Rename => {
+                let mut input = Input::new(InputKind::Rename);
+                if let Some(label) = &self.seeds[self.selected].label {
+                    input.value.push_str(label);
+                }
+                self.input = Some(input);
+            }
+            Action::Sign => self.input = Some(Input::new(InputKind::SignMessage)),
+            Action::SignOffline => self.input = Some(Input::new(InputKind::SignPayload)),
+            Action::Verify => self.input = Some(Input::new(InputKind::VerifyAddress)),
+            Action::Transfer => self.input = Some(Input::new(InputKind::TransferDestination)),
+            Action::AddressBook => self.open_address_book(None),
+            Action::ShowQrCode => self.show_qr_code(),
+            Action::RevealSecret => {
+                let address = self.wallets[self.selected].address.clone();
+                self.confirm = Some(Confirm {
+                    prompt: format!("Show the secret seed of {} on screen?", address),
+                    action: ConfirmAction::RevealSecret {
+                        index: self.selected,
+                        address,
+                        phrase: false,
+                    },
+                })
+            }
+            Action::RevealPhrase if self.seeds[self.selected].phrase().is_none() => self
+                .set_status(
+                    StatusKind::Error,
+                    "The selected wallet was not made from a mnemonic phrase",
+                ),
+            Action::RevealPhrase => {
+                let address = self.wallets[self.selected].address.clone();
+                self.confirm = Some(Confirm {
+                    prompt: format!("Show the mnemonic phrase of {} on screen?", address),
+                    action: ConfirmAction::RevealSecret {
+                        index: self.selected,
+                        address,
+                        phrase: true,
+                    },
+                })
+            }
+            Action::CopySecret if self.vault.is_none() => self.set_status(
+                StatusKind::Error,
+                "Copying a secret needs the password of the keys file, which has none",
+            ),
+            Action::CopySecret => {
+                let address = self.wallets[self.selected].address.clone();
+                self.confirm = Some(Confirm {
+                    prompt: format!(
+                        "Copy the secret seed of {} to the clipboard? Other programs can read it there.",
+                        address
+                    ),
+                    action: ConfirmAction::CopySecret {
+                        index: self.selected,
+                        address,
+                    },
+                })
+            }
+            Action::Inspect => {
+                let address = self.wallets[self.selected].address.clone();
+                self.confirm = Some(Confirm {
+                    prompt: format!(
+                        "Show the secret seed of {} on screen as subkey inspect does?",
+                        address
+                    ),
+                    action: ConfirmAction::Inspect {
+                        index: self.selected,
+                        address,
+                    },
+                })
+            }
+            Action::Rotate => {
+                let address = self.wallets[self.selected].address.clone();
+                self.confirm = Some(Confirm {
+                    prompt: format!(
+                        "Replace the key of {} with a new one? The old key is moved to {}; sweep its funds from there.",
+                        address,
+                        wallet::retired_keys_path(&self.keys_path)
+                    ),
+                    action: ConfirmAction::Rotate {
+                        index: self.selected,
+                        address,
+                    },
+                })
+            }
+            Action::DeriveNext => self.derive_next(),
+            Action::DerivationPath => self.input = Some(Input::new(InputKind::DerivationPath)),
+            Action::FetchBalances => {
+                let mut input = Input::new(InputKind::RpcUrl);
+                input.value.push_str(&self.rpc_url);
+                self.input = Some(input);
+            }
+            Action::Filter => {
+                let mut input = Input::new(InputKind::Filter);
+                input
+                    .value
+                    .push_str(self.filter.as_deref().unwrap_or_default());
+                self.input = Some(input);
+            }
+            Action::CycleScheme => self.scheme = self.scheme.next(),
+            Action::CycleMnemonicWords => self.cycle_mnemonic_words(),
+            Action::CycleNetwork => self.cycle_network(),
+            Action::CycleProfile => self.cycle_profile(),
+            Action::Reload => self.reload(),
+            Action::CycleSortOrder => self.sort = self.sort.next(),
+            Action::TogglePublicKeys => self.show_public_keys = !self.show_public_keys,
+            Action::Up => self.move_selection(-1),
+            Action::Down => self.move_selection(1),
+            Action::PageUp => self.move_selection(-(self.list_height.max(1) as isize)),
+            Action::PageDown => self.move_selection(self.list_height.max(1) as isize),
+        }
+    }
+
+    /// Handles keys on the lock screen. Typed characters are kept out of the rendered view.
+    fn on_lock_key_event(&mut self, key: KeyEvent) {
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc)
+            | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
+            (_, KeyCode::Enter) => self.submit_password(),
+            (_, KeyCode::Tab) if self.profiles.len() > 1 => {
+                self.switch_profile((self.profile + 1) % self.profiles.len())
+            }
+            (_, KeyCode::Backspace) => {
+                self.password_input.pop();
+            }
+            (_, KeyCode::Char(c)) => self.password_input.push(c),
+            _ => {}
+        }
+    }
+
+    /// Unlocks an encrypted keys file, or sets up encryption for a plaintext one once the
+    /// new password has been entered twice.
+    fn submit_password(&mut self) {
+        let password = std::mem::replace(&mut self.password_input, Zeroizing::new(String::new()));
+        self.check_keys_encrypted();
+        let encrypted = self.keys_encrypted;
+
+        let result = if encrypted {
+            self.unlock(&password).map_err(|e| e.to_string())
+        } else if password.is_empty() && self.new_password.is_none() {
+            self.load_seeds().map_err(|e| e.to_string())
+        } else if let Some(first) = self.new_password.take() {
+            if first == password {
+                self.encrypt_keys(&password).map_err(|e| e.to_string())
+            } else {
+                Err("Passwords do not match".to_string())
+            }
+        } else {
+            self.new_password = Some(password);
+            self.lock_error = None;
+            return;
+        };
+
+        match result {
+            Ok(()) => {
+                info!(path = %self.keys_path, encrypted = self.vault.is_some(), "Unlocked");
+                self.mode = AppMode::Unlocked;
+                self.restore_selection();
+                self.lock_error = None;
+                self.failed_unlocks = 0;
+                if let (Some(warning), None) = (self.startup_warning.take(), &self.status) {
+                    self.set_status(StatusKind::Warning, warning);
+                }
+                self.check_keys_permissions();
+                if let Err(e) = self.watch_keys_file() {
+                    self.set_status(
+                        StatusKind::Error,
+                        format!("Not watching the keys file: {}", e),
+                    );
+                }
+            }
+            Err(error) => {
+                // Only wrong passwords count; a mistyped new password just starts over.
+                if encrypted {
+                    self.failed_unlocks += 1;
+                }
+                warn!("Unlocking failed: {}", error);
+                self.lock_error = Some(error);
+                if self.failed_unlocks >= MAX_UNLOCK_ATTEMPTS {
+                    warn!("Too many failed unlocks, quitting");
+                    self.quit();
+                }
+            }
+        }
+    }
+
+    /// Handles keys while a text [`Input`] is open.
+    fn on_input_key_event(&mut self, key: KeyEvent) {
+        let Some(input) = &mut self.input else {
+            return;
+        };
+
+        match (key.modifiers, key.code) {
+            // Ctrl+C only closes the dialog, so that what was typed is not lost by accident
+            // along with the whole app. Pressed again, it quits from the wallet list.
+            (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C'))
+            | (_, KeyCode::Esc) => {
+                let discarded = key.code != KeyCode::Esc && !input.value.is_empty();
+                if input.kind == InputKind::Filter {
+                    self.set_filter("");
+                }
+                self.input = None;
+                if discarded {
+                    self.set_status(
+                        StatusKind::Warning,
+                        "Closed the dialog and discarded what was typed. Press Ctrl+C again to quit",
+                    );
+                }
+            }
+            (_, KeyCode::Enter) => self.submit_input(),
+            (_, KeyCode::Tab) if input.kind == InputKind::TransferDestination => {
+                let typed = input.value.to_string();
+                self.open_address_book(Some(typed));
+            }
+            (_, KeyCode::Backspace) => {
+                input.value.pop();
+                input.error = None;
+            }
+            (_, KeyCode::Char(c)) => {
+                input.value.push(c);
+                input.error = None;
+            }
+            _ => {}
+        }
+
+        if let Some(Input {
+            kind: InputKind::Filter,
+            value,
+            ..
+        }) = &self.input
+        {
+            let value = value.to_string();
+            self.set_filter(&value);
+        }
+
+        if let Some(
+            input @ Input {
+                kind: InputKind::DerivationPath,
+                ..
+            },
+        ) = &mut self.input
+        {
+            let derived = self
+                .seeds
+                .get(self.selected)
+                .map(|entry| Self::derive_path(entry, &input.value));
+            match derived {
+                Some(Ok(entry)) => {
+                    input.preview = Some(entry.address(self.ss58_format));
+                    input.error = None;
+                }
+                Some(Err(e)) => {
+                    input.preview = None;
+                    input.error = Some(e);
+                }
+                None => {}
+            }
+        }
+
+        self.check_address_input();
+    }
+
+    /// Shows whether what is typed into an input asking for an address is a valid one.
+    fn check_address_input(&mut self) {
+        if let Some(
+            input @ Input {
+                kind:
+                    InputKind::VerifyAddress
+                    | InputKind::TransferDestination
+                    | InputKind::ContactAddress { .. },
+                ..
+            },
+        ) = &mut self.input
+        {
+            let address = input.value.trim();
+            (input.preview, input.error) = match wallet::parse_address(address) {
+                _ if address.is_empty() => (None, None),
+                Ok((_, format)) => (
+                    Some(format!("Valid {} address", network_name(format))),
+                    None,
+                ),
+                Err(e) => (None, Some(e.to_string())),
+            };
+        }
+    }
+
+    /// Acts on the submitted input, keeping the popup open with an error when the value
+    /// is rejected.
+    fn submit_input(&mut self) {
+        let Some(input) = self.input.take() else {
+            return;
+        };
+
+        let result = match &input.kind {
+            InputKind::ImportPhrase => self.import_phrase(&input.value),
+            InputKind::ExportKeystorePassword => self.export_keystore(&input.value),
+            InputKind::ImportKeystorePath => self.open_keystore(&input.value),
+            InputKind::ImportKeystorePassword { path } => self.import_keystore(path, &input.value),
+            InputKind::GenerateCount => self.generate_batch(&input.value),
+            InputKind::VanityPattern => {
+                VanityPattern::parse(&input.value, self.ss58_format).map(|pattern| {
+                    self.vanity = Some(VanitySearch::start(pattern, self.scheme, self.ss58_format))
+                })
+            }
+            InputKind::Filter => Ok(()),
+            InputKind::Rename => self.rename_selected(&input.value),
+            InputKind::DerivationPath => self.add_derived(&input.value),
+            InputKind::RpcUrl => self.fetch_balances(&input.value),
+            InputKind::SignMessage => self.sign_message(&input.value),
+            InputKind::SignPayload => self.sign_payload(&input.value),
+            InputKind::VerifyAddress => self.enter_verify_address(&input.value),
+            InputKind::VerifyMessage { address } => {
+                self.input = Some(Input::new(InputKind::VerifySignature {
+                    address: address.clone(),
+                    message: input.value.to_string(),
+                }));
+                Ok(())
+            }
+            InputKind::VerifySignature { address, message } => {
+                self.verify_message(address, message, &input.value)
+            }
+            InputKind::TransferDestination => self.enter_transfer_destination(&input.value),
+            InputKind::TransferAmount { destination } => {
+                self.start_transfer(destination, &input.value)
+            }
+            InputKind::WatchAddresses => self.watch_addresses(&input.value),
+            InputKind::ExportCsvPath => {
+                self.export_csv(&input.value);
+                Ok(())
+            }
+            InputKind::ExportArchivePassword => self.export_archive(&input.value),
+            InputKind::ImportArchivePath => self.open_archive(&input.value),
+            InputKind::ImportArchiveKeysPath { path } => match input.value.trim() {
+                "" => Err("Enter the path of a keys file".to_string()),
+                keys_path => {
+                    self.input = Some(Input::new(InputKind::ImportArchivePassword {
+                        path: path.clone(),
+                        keys_path: keys_path.to_string(),
+                    }));
+                    Ok(())
+                }
+            },
+            InputKind::ImportArchivePassword { path, keys_path } => {
+                self.import_archive(path, keys_path, &input.value)
+            }
+            InputKind::MergeKeysFilePath => self.open_keys_file_to_merge(&input.value),
+            InputKind::MergeKeysFilePassword { path } => {
+                self.merge_keys_file(path, Some(&mut Vault::new(&input.value)))
+            }
+            InputKind::ContactName { index } => self.enter_contact_name(*index, &input.value),
+            InputKind::ContactAddress { index, name } => {
+                self.save_contact(*index, name, &input.value)
+            }
+            InputKind::RevealPassword {
+                index,
+                address,
+                phrase,
+                copy,
+            } => match self
+                .vault
+                .as_ref()
+                .map(|vault| vault.check_password(&input.value))
+            {
+                Some(false) => Err("Wrong password".to_string()),
+                _ if *copy => {
+                    self.copy_secret(*index, address);
+                    Ok(())
+                }
+                _ => {
+                    self.reveal_secret(*index, address, *phrase);
+                    Ok(())
+                }
+            },
+            InputKind::InspectPassword { index, address } => match self
+                .vault
+                .as_ref()
+                .map(|vault| vault.check_password(&input.value))
+            {
+                Some(false) => Err("Wrong password".to_string()),
+                _ => {
+                    self.inspect(*index, address);
+                    Ok(())
+                }
+            },
+            InputKind::Passphrase {
+                index,
+                address,
+                purpose,
+            } => self.use_passphrase(*index, address, purpose, &input.value),
+            InputKind::NewPassphrase if input.value.is_empty() => {
+                Err("Enter a passphrase".to_string())
+            }
+            InputKind::NewPassphrase => {
+                self.new_passphrase = Some(input.value.clone());
+                self.input = Some(Input::new(InputKind::RepeatPassphrase));
+                Ok(())
+            }
+            InputKind::RepeatPassphrase => match self.new_passphrase.take() {
+                Some(first) if first == input.value => self.generate_protected(&first),
+                _ => {
+                    self.input = Some(Input {
+                        error: Some("Passphrases do not match".to_string()),
+                        ..Input::new(InputKind::NewPassphrase)
+                    });
+                    Ok(())
+                }
+            },
+        };
+
+        if let Err(error) = result {
+            self.input = Some(Input {
+                error: Some(error),
+                ..input
+            });
+        }
+    }
+
+    /// Validates a mnemonic phrase, or a secret URI with a derivation path or password
+    /// after the phrase or hex seed, and appends the wallet it derives to the keys file.
+    fn import_phrase(&mut self, value: &str) -> Result<(), String> {
+        // Only the phrase is normalised; the path and password are taken as they are.
+        let (phrase, rest) = value.split_at(value.find('/').unwrap_or(value.len()));
+        let phrase = phrase
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let entry = if rest.is_empty() && !phrase.starts_with("0x") {
+            bip39::Mnemonic::parse_in(bip39::Language::English, phrase.as_str())
+                .map_err(|e| format!("Invalid mnemonic: {}", e))?;
+            KeyEntry::from_phrase(self.scheme, &phrase)
+        } else {
+            let suri = Zeroizing::new(format!("{}{}", phrase, rest.trim_end()));
+            KeyEntry::from_suri(self.scheme, &suri)
+        }
+        .map_err(|e| e.to_string())?
+        .stamped();
+
+        self.save_wallets(std::slice::from_ref(&entry))
+            .map_err(|e| format!("Failed to save wallet: {}", e))?;
+
+        self.select(self.seeds.len().saturating_sub(1));
+        self.set_import_status("Wallet imported".to_string(), std::slice::from_ref(&entry));
+
+        Ok(())
+    }
+
+    /// Adds watch-only wallets for the addresses in `value`, separated by whitespace or
+    /// commas. If `value` is the path of a file, its lines are read instead, each an
+    /// address optionally followed by a tab and a label.
+    fn watch_addresses(&mut self, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        let parse = |address: &str| {
+            wallet::parse_address(address)
+                .map(|(account, format)| KeyEntry::watch_only(account, format).stamped())
+                .map_err(|e| format!("Invalid address {}: {}", address, e))
+        };
+
+        let mut entries = Vec::new();
+        if Path::new(value).is_file() {
+            let contents = std::fs::read_to_string(value)
+                .map_err(|e| format!("Failed to read {}: {}", value, e))?;
+            for (i, line) in contents.lines().enumerate() {
+                let (address, label) = match line.split_once('\t') {
+                    Some((address, label)) => (address.trim(), label.trim()),
+                    None => (line.trim(), ""),
+                };
+                if address.is_empty() || address.starts_with('#') {
+                    continue;
+                }
+
+                // A further tab would end up in the keys file as another column.
+                let entry = parse(address)
+                    .and_then(|entry| wallet::check_label(label).map(|()| entry))
+                    .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+                entries.push(KeyEntry {
+                    label: (!label.is_empty()).then(|| label.to_string()),
+                    ..entry
+                });
+            }
+        } else {
+            for address in value.split(|c: char| c.is_whitespace() || c == ',') {
+                if !address.is_empty() {
+                    entries.push(parse(address)?);
+                }
+            }
+        }
+
+        // Addresses already in the list, whether owned or watched, are left alone.
+        let mut known: HashSet<AccountId32> = self.account_ids.iter().cloned().collect();
+        let given = entries.len();
+        entries.retain(|entry| known.insert(entry.account_id()));
+        if given == 0 {
+            return Err("Enter at least one address".to_string());
+        }
+        if entries.is_empty() {
+            return Err("Every address is already in the wallet list".to_string());
+        }
+
+        self.save_wallets(&entries)
+            .map_err(|e| format!("Failed to save watch-only wallets: {}", e))?;
+
+        self.select(self.seeds.len().saturating_sub(1));
+        self.set_status(
+            StatusKind::Success,
+            format!("Watching {} new addresses", entries.len()),
+        );
+
+        Ok(())
+    }
+
+    /// Writes the selected wallet as an encrypted polkadot-js JSON keystore named after its
+    /// address, next to the keys file.
+    fn export_keystore(&mut self, password: &str) -> Result<(), String> {
+        if password.is_empty() {
+            return Err("Password must not be empty".to_string());
+        }
+
+        let (Some(entry), Some(address)) = (
+            self.seeds.get(self.selected),
+            self.wallets
+                .get(self.selected)
+                .map(|wallet| &wallet.address),
+        ) else {
+            return Ok(());
+        };
+
+        let keystore = keystore::export(entry, address, password).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(&keystore).map_err(|e| e.to_string())?;
+
+        let path = Path::new(&self.keys_path).with_file_name(format!("{}.json", address));
+        self.write_export(&path, json)?;
+
+        self.set_status(
+            StatusKind::Success,
+            format!("{} keystore to {}", self.exported(), path.display()),
+        );
+        Ok(())
+    }
+
+    /// Writes every wallet to an archive encrypted with `password`, named after the time
+    /// of the export, next to the keys file.
+    fn export_archive(&mut self, password: &str) -> Result<(), String> {
+        if password.is_empty() {
+            return Err("Password must not be empty".to_string());
+        }
+        let entries = self.exported_entries();
+        if entries.is_empty() {
+            return Err("There are no wallets to export".to_string());
+        }
+
+        let data = archive::export(&entries, password).map_err(|e| e.to_string())?;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_millis();
+        let path =
+            Path::new(&self.keys_path).with_file_name(format!("wallets-{}.archive", timestamp));
+        self.write_export(&path, data)?;
+
+        self.set_status(
+            StatusKind::Success,
+            format!(
+                "{} {} wallets to {}",
+                self.exported(),
+                entries.len(),
+                path.display()
+            ),
+        );
+        Ok(())
+    }
+
+    /// Writes the number, label, network and address of every wallet to a CSV file at
+    /// `path`. Nothing secret goes into it, so it can be shared freely.
+    fn export_csv(&mut self, path: &str) {
+        let path = path.trim();
+        let network = network_name(self.ss58_format);
+        let entries = self.exported_entries();
+        let wallets: Vec<WalletKeys> = entries
+            .iter()
+            .map(|entry| WalletKeys::from_entry(entry, self.ss58_format))
+            .collect();
+        let csv = addresses_csv(&entries, &wallets, &network, false);
+
+        match self.write_export(Path::new(path), csv) {
+            Ok(()) => self.set_status(
+                StatusKind::Success,
+                format!(
+                    "{} {} addresses to {}",
+                    self.exported(),
+                    wallets.len(),
+                    path
+                ),
+            ),
+            Err(e) => self.set_status(StatusKind::Error, e),
+        }
+    }
+
+    /// Sets or, when `label` is blank, removes the selected wallet's label and saves it.
+    fn rename_selected(&mut self, label: &str) -> Result<(), String> {
+        let label = label.trim();
+        wallet::check_label(label)?;
+
+        let mut seeds = self.seeds.clone();
+        let Some(entry) = seeds.get_mut(self.selected) else {
+            return Ok(());
+        };
+        entry.label = (!label.is_empty()).then(|| label.to_string());
+
+        self.write_wallets(seeds)
+            .map_err(|e| format!("Failed to save label: {}", e))?;
+
+        self.set_status(StatusKind::Success, "Label saved");
+        Ok(())
+    }
+
+    /// Signs `message` with the selected wallet and shows the signature, first asking for
+    /// the passphrase of a protected wallet.
+    fn sign_message(&mut self, message: &str) -> Result<(), String> {
+        if message.is_empty() {
+            return Err("Message must not be empty".to_string());
+        }
+
+        let (Some(entry), Some(address)) = (
+            self.seeds.get(self.selected),
+            self.wallets
+                .get(self.selected)
+                .map(|wallet| wallet.address.clone()),
+        ) else {
+            return Ok(());
+        };
+
+        let purpose = PassphraseFor::Sign {
+            message: message.to_string(),
+        };
+        if entry.is_protected() {
+            self.ask_passphrase(self.selected, address, purpose);
+        } else {
+            self.run_with_entry(self.selected, &address, entry.clone(), purpose);
+        }
+        Ok(())
+    }
+
+    /// Decodes the hex signing payload of a transaction as far as it can without the node
+    /// and asks whether to sign it with the selected wallet. Once confirmed, the signature
+    /// is shown as hex and as a QR code to carry to a machine that is online.
+    fn sign_payload(&mut self, payload: &str) -> Result<(), String> {
+        let payload = payload.trim();
+        let payload = hex::decode(payload.strip_prefix("0x").unwrap_or(payload))
+            .map_err(|e| format!("Bad hex: {}", e))?;
+        let summary = payload::decode(&payload)?;
+
+        let Some(address) = self
+            .wallets
+            .get(self.selected)
+            .map(|wallet| wallet.address.clone())
+        else {
+            return Ok(());
+        };
+
+        self.confirm = Some(Confirm {
+            prompt: self.payload_prompt(&summary, &payload, &address),
+            action: ConfirmAction::SignPayload {
+                index: self.selected,
+                address,
+                payload,
+            },
+        });
+        Ok(())
+    }
+
+    /// Asks whether to sign `payload` with `address`, saying what it does as far as
+    /// `summary` tells, or warning that it is opaque.
+    fn payload_prompt(&self, summary: &PayloadSummary, payload: &[u8], address: &str) -> String {
+        let call = format!(
+            "pallet {}, call {}",
+            summary.pallet_index, summary.call_index
+        );
+        let Some(chain) = &summary.chain else {
+            let shown = &payload[..payload.len().min(64)];
+            return format!(
+                "WARNING: the payload could not be decoded, so what it does is unknown.\n\
+                 Call: {}, if it is a call at all\n\
+                 Bytes: 0x{}{} ({} bytes)\n\
+                 Sign it blind with {}?",
+                call,
+                hex::encode(shown),
+                if shown.len() < payload.len() {
+                    "…"
+                } else {
+                    ""
+                },
+                payload.len(),
+                address
+            );
+        };
+
+        let format = chain
+            .known
+            .and_then(|known| NETWORKS.iter().find(|network| network.name == known.name))
+            .map_or(self.ss58_format, Network::ss58_format);
+        let mut lines = vec![format!("Sign this transaction with {}?", address)];
+        lines.push(match summary.call_name {
+            Some((pallet, name)) => format!("Call: {}.{} ({})", pallet, name, call),
+            None => format!(
+                "Call: {}; its arguments need the runtime metadata to decode",
+                call
+            ),
+        });
+        if let Some((dest, amount)) = &summary.transfer {
+            lines.push(format!("To: {}", dest.to_ss58check_with_version(format)));
+            lines.push(format!("Amount: {}", format_balance(*amount, format)));
+        }
+        lines.push(format!(
+            "Chain: {}, spec version {}, transaction version {}, {}",
+            match chain.known {
+                Some(known) => known.name.to_string(),
+                None => format!("genesis 0x{}", hex::encode(chain.genesis_hash)),
+            },
+            chain.spec_version,
+            chain.transaction_version,
+            if chain.immortal { "immortal" } else { "mortal" }
+        ));
+        lines.join("\n")
+    }
+
+    /// Asks for the passphrase of the protected wallet at `index`, which has `address`,
+    /// to then use it for `purpose`.
+    fn ask_passphrase(&mut self, index: usize, address: String, purpose: PassphraseFor) {
+        self.input = Some(Input::new(InputKind::Passphrase {
+            index,
+            address,
+            purpose,
+        }));
+    }
+
+    /// Checks `passphrase` against the protected wallet at `index` and uses the key it
+    /// gives for `purpose`, unless the list has changed since and `address` is no longer
+    /// there.
+    fn use_passphrase(
+        &mut self,
+        index: usize,
+        address: &str,
+        purpose: &PassphraseFor,
+        passphrase: &str,
+    ) -> Result<(), String> {
+        let Some(entry) = self
+            .seeds
+            .get(index)
+            .filter(|_| self.wallets[index].address == address)
+        else {
+            self.set_status(
+                StatusKind::Error,
+                "The wallet list changed meanwhile; nothing was done",
+            );
+            return Ok(());
+        };
+
+        let entry = entry.unprotect(passphrase).map_err(|e| e.to_string())?;
+        info!(address, "Passphrase accepted");
+        self.run_with_entry(index, address, entry, purpose.clone());
+        Ok(())
+    }
+
+    /// Signs or starts a transfer with `entry`, the key of the wallet at `index` that has
+    /// `address`.
+    fn run_with_entry(
+        &mut self,
+        index: usize,
+        address: &str,
+        entry: KeyEntry,
+        purpose: PassphraseFor,
+    ) {
+        match purpose {
+            PassphraseFor::Sign { message } => self.show_signature(&entry, address, &message),
+            PassphraseFor::SignPayload { payload } => {
+                self.show_payload_signature(&entry, address, &payload)
+            }
+            PassphraseFor::Transfer {
+                destination,
+                amount,
+            } => {
+                self.connecting = Some(Connecting::start(
+                    &self.rpc_url,
+                    NodeTask::Transfer {
+                        index,
+                        from: address.to_string(),
+                        destination,
+                        amount,
+                        entry: Box::new(entry),
+                    },
+                ))
+            }
+        }
+    }
+
+    /// Signs `message` with the key of `entry`, whose address is `address`, and shows the
+    /// signature.
+    fn show_signature(&mut self, entry: &KeyEntry, address: &str, message: &str) {
+        let Some(pair) = entry.pair() else {
+            self.set_status(StatusKind::Error, "Watch-only wallets cannot sign messages");
+            return;
+        };
+        let signature = format!("0x{}", hex::encode(pair.sign(message.as_bytes())));
+
+        self.popup = Some(Popup {
+            title: "Signature".to_string(),
+            lines: vec![
+                Line::from(vec![
+                    Span::styled("Address:   ", Style::default().fg(self.theme.label)),
+                    Span::raw(address.to_string()),
+                ]),
+                Line::from(vec![
+                    Span::styled("Scheme:    ", Style::default().fg(self.theme.label)),
+                    Span::raw(entry.scheme.name()),
+                ]),
+                Line::from(vec![
+                    Span::styled("Message:   ", Style::default().fg(self.theme.label)),
+                    Span::raw(message.to_string()),
+                ]),
+                Line::from(vec![
+                    Span::styled("Signature: ", Style::default().fg(self.theme.label)),
+                    Span::styled(signature.clone(), Style::default().fg(self.theme.success)),
+                ]),
+            ],
+            copy: Some(signature),
+            hint: None,
+            secret: false,
+        });
+    }
+
+    /// Signs the signing `payload` of a transaction with the key of `entry`, whose address
+    /// is `address`, and shows the signature, SCALE-encoded as a `MultiSignature`, as hex
+    /// and as a QR code.
+    fn show_payload_signature(&mut self, entry: &KeyEntry, address: &str, payload: &[u8]) {
+        let Some(pair) = entry.pair() else {
+            self.set_status(StatusKind::Error, "Watch-only wallets cannot sign");
+            return;
+        };
+        let signature = format!("0x{}", hex::encode(transfer::sign_payload(&pair, payload)));
+
+        match QrView::new("Signature", signature) {
+            Ok(qr_code) => {
+                info!(
+                    address,
+                    bytes = payload.len(),
+                    "Signed a transaction payload offline"
+                );
+                self.qr_code = Some(qr_code);
+            }
+            Err(e) => self.set_status(
+                StatusKind::Error,
+                format!("Failed to encode QR code: {}", e),
+            ),
+        }
+    }
+
+    /// Checks that `address` is a valid SS58 address before asking for the message.
+    fn enter_verify_address(&mut self, address: &str) -> Result<(), String> {
+        let address = address.trim();
+        wallet::parse_address(address).map_err(|e| format!("Bad SS58 address: {}", e))?;
+
+        self.input = Some(Input::new(InputKind::VerifyMessage {
+            address: address.to_string(),
+        }));
+        Ok(())
+    }
+
+    /// Verifies a hex `signature` of `message` by `address` and shows whether it is valid.
+    fn verify_message(
+        &mut self,
+        address: &str,
+        message: &str,
+        signature: &str,
+    ) -> Result<(), String> {
+        let (account_id, _) =
+            wallet::parse_address(address).map_err(|e| format!("Bad SS58 address: {}", e))?;
+
+        let signature = signature.trim();
+        let signature = hex::decode(signature.strip_prefix("0x").unwrap_or(signature))
+            .map_err(|e| format!("Bad hex: {}", e))?;
+        if signature.len() != 64 && signature.len() != 65 {
+            return Err(format!(
+                "Wrong signature length: {} bytes, expected 64 (sr25519, ed25519) or 65 (ecdsa)",
+                signature.len()
+            ));
+        }
+
+        let result = match verify_signature(&account_id, message.as_bytes(), &signature) {
+            Some(scheme) => Line::styled(
+                format!("VALID {} signature", scheme.name()),
+                Style::default()
+                    .fg(self.theme.success)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            None => Line::styled(
+                "INVALID signature",
+                Style::default()
+                    .fg(self.theme.error)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        };
+
+        self.popup = Some(Popup {
+            title: "Verification".to_string(),
+            lines: vec![
+                result.centered(),
+                Line::from(vec![
+                    Span::styled("Address:   ", Style::default().fg(self.theme.label)),
+                    Span::raw(address.to_string()),
+                ]),
+                Line::from(vec![
+                    Span::styled("Message:   ", Style::default().fg(self.theme.label)),
+                    Span::raw(message.to_string()),
+                ]),
+            ],
+            copy: None,
+            hint: None,
+            secret: false,
+        });
+        Ok(())
+    }
+
+    /// Checks that `path` holds a keystore before asking for its password.
+    fn open_keystore(&mut self, path: &str) -> Result<(), String> {
+        let json =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        serde_json::from_str::<keystore::Keystore>(&json)
+            .map_err(|e| format!("Not a JSON keystore: {}", e))?;
+
+        self.input = Some(Input::new(InputKind::ImportKeystorePassword {
+            path: path.to_string(),
+        }));
+        Ok(())
+    }
+
+    /// Checks that `path` holds an archive before asking which keys file to restore it
+    /// into, the open one by default.
+    fn open_archive(&mut self, path: &str) -> Result<(), String> {
+        let path = path.trim();
+        let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if !vault::is_encrypted(&data) {
+            return Err("Not a wallet archive".to_string());
+        }
+
+        let mut input = Input::new(InputKind::ImportArchiveKeysPath {
+            path: path.to_string(),
+        });
+        input.value.push_str(&self.keys_path);
+        self.input = Some(input);
+        Ok(())
+    }
+
+    /// Decrypts the archive at `path` and appends those of its wallets to the keys file at
+    /// `keys_path` that it does not have yet. Only the open keys file may be encrypted,
+    /// since no other password is known.
+    fn import_archive(
+        &mut self,
+        path: &str,
+        keys_path: &str,
+        password: &str,
+    ) -> Result<(), String> {
+        let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let entries = archive::import(&data, password).map_err(|e| e.to_string())?;
+
+        let is_open = keys_path == self.keys_path;
+        // A dry run keeps the changes to the open keys file in memory only.
+        let known: HashSet<[u8; 32]> = if is_open && self.dry_run {
+            self.seeds.iter().map(KeyEntry::fingerprint).collect()
+        } else {
+            let vault = self.vault.as_mut().filter(|_| is_open);
+            wallet::load_wallets_from_file(keys_path, vault)
+                .map_err(|e| format!("Failed to read {}: {}", keys_path, e))?
+                .seeds
+                .iter()
+                .map(KeyEntry::fingerprint)
+                .collect()
+        };
+        let total = entries.len();
+        let new: Vec<KeyEntry> = entries
+            .into_iter()
+            .filter(|entry| !known.contains(&entry.fingerprint()))
+            .collect();
+
+        if is_open && !new.is_empty() {
+            self.save_wallets(&new)
+                .map_err(|e| format!("Failed to save wallets: {}", e))?;
+        } else if !new.is_empty() && !self.dry_run {
+            wallet::save_wallets_to_file(keys_path, &new, None)
+                .map_err(|e| format!("Failed to save wallets: {}", e))?;
+        }
+
+        let mut message = format!(
+            "Restored {} of {} wallets into {}",
+            new.len(),
+            total,
+            keys_path
+        );
+        if new.len() < total {
+            message.push_str(&format!(" ({} already there)", total - new.len()));
+        }
+        self.set_import_status(message, &new);
+        Ok(())
+    }
+
+    /// Merges the wallets of the keys file at `path` into the open one, first asking for
+    /// its password if it is encrypted.
+    fn open_keys_file_to_merge(&mut self, path: &str) -> Result<(), String> {
+        let path = path.trim();
+        if path.is_empty() {
+            return Err("Enter the path of a keys file".to_string());
+        }
+        if Path::new(path) == Path::new(&self.keys_path) {
+            return Err("That is the open keys file".to_string());
+        }
+        let contents =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+        if vault::is_encrypted(&contents) {
+            self.input = Some(Input::new(InputKind::MergeKeysFilePassword {
+                path: path.to_string(),
+            }));
+            return Ok(());
+        }
+        self.merge_keys_file(path, None)
+    }
+
+    /// Appends those wallets of the keys file at `path`, decrypted with `vault` if it is
+    /// encrypted, that the open keys file does not have yet.
+    fn merge_keys_file(&mut self, path: &str, vault: Option<&mut Vault>) -> Result<(), String> {
+        let loaded = wallet::load_wallets_from_file(path, vault).map_err(|e| match e.kind() {
+            std::io::ErrorKind::PermissionDenied => "Wrong password".to_string(),
+            _ => format!("Failed to read {}: {}", path, e),
+        })?;
+
+        let total = loaded.seeds.len();
+        if total == 0 {
+            return Err(format!("{} has no wallets", path));
+        }
+        let known: HashSet<[u8; 32]> = self.seeds.iter().map(KeyEntry::fingerprint).collect();
+        let new: Vec<KeyEntry> = loaded
+            .seeds
+            .into_iter()
+            .filter(|entry| !known.contains(&entry.fingerprint()))
+            .map(KeyEntry::stamped)
+            .collect();
+
+        if !new.is_empty() {
+            self.save_wallets(&new)
+                .map_err(|e| format!("Failed to save wallets: {}", e))?;
+        }
+
+        let mut skipped = Vec::new();
+        if new.len() < total {
+            skipped.push(format!("{} already here", total - new.len()));
+        }
+        if !loaded.skipped.is_empty() {
+            skipped.push(format!("{} invalid lines", loaded.skipped.len()));
+        }
+        let mut message = format!(
+            "Added {} new wallet{} from {}",
+            new.len(),
+            if new.len() == 1 { "" } else { "s" },
+            path
+        );
+        if !skipped.is_empty() {
+            message.push_str(&format!(", skipped {}", skipped.join(" and ")));
+        }
+        self.set_import_status(message, &new);
+        Ok(())
+    }
+
+    /// Decrypts the keystore at `path` and appends the recovered wallet to the keys file.
+    fn import_keystore(&mut self, path: &str, password: &str) -> Result<(), String> {
+        let json =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let entry = keystore::import(&json, password)
+            .map_err(|e| e.to_string())?
+            .stamped();
+
+        self.save_wallets(std::slice::from_ref(&entry))
+            .map_err(|e| format!("Failed to save wallet: {}", e))?;
+
+        self.select(self.seeds.len().saturating_sub(1));
+        self.set_import_status(
+            format!("Imported {}", entry.address(self.ss58_format)),
+            std::slice::from_ref(&entry),
+        );
+
+        Ok(())
+    }
+
+    /// Reports that `imported` were added with `message`, warning instead if any of their
+    /// secrets does not look random. They are kept either way.
+    fn set_import_status(&mut self, message: String, imported: &[KeyEntry]) {
+        let weak: Vec<&str> = imported.iter().filter_map(KeyEntry::weak_secret).collect();
+        match weak.as_slice() {
+            [] => self.set_status(StatusKind::Success, message),
+            [reason] if imported.len() == 1 => self.set_status(
+                StatusKind::Warning,
+                format!(
+                    "{}, but its secret does not look random: {}",
+                    message, reason
+                ),
+            ),
+            _ => self.set_status(
+                StatusKind::Warning,
+                format!(
+                    "{}, but {} of them have secrets that do not look random",
+                    message,
+                    weak.len()
+                ),
+            ),
+        }
+    }
+
+    /// Indices into `seeds` of the wallets shown in the list, in order.
+    fn visible_indices(&self) -> Vec<usize> {
+        let mut visible: Vec<usize> = (0..self.seeds.len())
+            .filter(|&i| {
+                self.filter.as_ref().is_none_or(|filter| {
+                    self.wallets[i]
+                        .address
+                        .to_lowercase()
+                        .contains(filter.as_str())
+                })
+            })
+            .collect();
+
+        // Stable sorts, so that ties stay in file order.
+        match self.sort {
+            SortOrder::File => {}
+            SortOrder::Address => {
+                visible.sort_by(|&a, &b| self.wallets[a].address.cmp(&self.wallets[b].address))
+            }
+            SortOrder::Label => visible.sort_by_cached_key(|&i| {
+                let label = self.seeds[i].label.as_deref();
+                (label.is_none(), label.map(str::to_lowercase))
+            }),
+            SortOrder::Created => visible.sort_by_key(|&i| self.seeds[i].created),
+        }
+
+        visible
+    }
+
+    /// Whether the selected wallet exists and is shown, so per-wallet actions may apply.
+    fn has_selection(&self) -> bool {
+        self.visible_indices().contains(&self.selected)
+    }
+
+    /// Narrows the list to wallets whose address contains `query`, ignoring case. An
+    /// empty query shows every wallet again.
+    fn set_filter(&mut self, query: &str) {
+        let query = query.trim().to_lowercase();
+        self.filter = (!query.is_empty()).then_some(query);
+        *self.list_state.offset_mut() = 0;
+        self.move_selection(0);
+    }
+
+    /// Moves the selection `delta` wallets through the visible list, stopping at its ends.
+    /// A selection hidden by the filter snaps to the first visible wallet.
+    fn move_selection(&mut self, delta: isize) {
+        let visible = self.visible_indices();
+        let Some(position) = visible.iter().position(|&i| i == self.selected) else {
+            if let Some(&first) = visible.first() {
+                self.selected = first;
+            }
+            return;
+        };
+
+        let target = position.saturating_add_signed(delta).min(visible.len() - 1);
+        if target != position {
+            self.selected = visible[target];
+        }
+    }
+
+    /// Selects the wallet at `index`, clearing the filter if it would hide it.
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        if !self.has_selection() {
+            self.filter = None;
+        }
+    }
+
+    /// Switches new wallets to the next longer mnemonic, from a raw seed to the shortest
+    /// and from the longest back to a raw seed, and remembers the choice in the config
+    /// file.
+    fn cycle_mnemonic_words(&mut self) {
+        self.mnemonic_words = match self.mnemonic_words {
+            None => Some(MNEMONIC_WORDS[0]),
+            Some(words) => MNEMONIC_WORDS
+                .iter()
+                .copied()
+                .find(|&longer| longer > words),
+        };
+
+        let Some(path) = self.config_path.as_deref().filter(|_| !self.dry_run) else {
+            return;
+        };
+        let value = match self.mnemonic_words {
+            Some(words) => toml_edit::value(words as i64),
+            None => toml_edit::Item::None,
+        };
+        if let Err(e) = config::save_setting(path, "mnemonic_words", value) {
+            self.set_status(StatusKind::Warning, e);
+        }
+    }
+
+    /// Describes the kind of secret newly generated wallets get.
+    fn secret_kind(&self) -> String {
+        match self.mnemonic_words {
+            Some(words) => format!("a {}-word mnemonic", words),
+            None => "a raw seed".to_string(),
+        }
+    }
+
+    fn quit(&mut self) {
+        self.remember_selection();
+        self.running = false;
+        self.watcher = None;
+    }
+
+    /// Selects the wallet last selected in the keys file in an earlier run, if it is still
+    /// there, or else the first one.
+    fn restore_selection(&mut self) {
+        let Some(address) = self
+            .state_path
+            .as_deref()
+            .and_then(|path| state::last_selected(path, &self.keys_path))
+        else {
+            return;
+        };
+        let account = wallet::parse_address(&address)
+            .ok()
+            .map(|(account, _)| account);
+        self.selected = account
+            .and_then(|account| self.account_ids.iter().position(|id| *id == account))
+            .unwrap_or(0);
+        self.move_selection(0);
+    }
+
+    /// Remembers the selected wallet of the open keys file for the next run. Dry runs
+    /// leave the state file alone, like every other file.
+    fn remember_selection(&mut self) {
+        let (Some(path), Some(wallet)) = (&self.state_path, self.wallets.get(self.selected)) else {
+            return;
+        };
+        if self.mode == AppMode::Locked || self.dry_run {
+            return;
+        }
+        if let Err(e) = state::remember_selected(path, &self.keys_path, &wallet.address) {
+            warn!(
+                "Selected wallet not remembered in {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    fn press_button(&mut self) {
+        let entry = match wallet::generate_random_wallet(
+            self.scheme,
+            self.mnemonic_words,
+            self.ss58_format,
+        ) {
+            Ok((_, entry)) => entry,
+            Err(e) => {
+                self.set_status(StatusKind::Error, format!("No wallet generated: {}", e));
+                return;
+            }
+        };
+        self.button_pressed = true;
+
+        match self.save_wallets(std::slice::from_ref(&entry)) {
+            Ok(()) => {
+                info!(
+                    address = %entry.address(self.ss58_format),
+                    scheme = self.scheme.name(),
+                    "Generated a wallet"
+                );
+                self.push_undo(Undo::Generated {
+                    fingerprints: vec![entry.fingerprint()],
+                });
+            }
+            Err(e) => self.set_status(StatusKind::Error, format!("Failed to save wallet: {}", e)),
+        }
+    }
+
+    /// Generates a wallet with a mnemonic phrase of the configured length, or 12 words
+    /// when raw seeds are configured, protects it with `passphrase` and appends it to the
+    /// keys file.
+    fn generate_protected(&mut self, passphrase: &str) -> Result<(), String> {
+        let (_, entry) = Wallet::new(self.scheme)
+            .with_mnemonic_words(Some(self.mnemonic_words.unwrap_or(12)))
+            .generate()
+            .map_err(|e| format!("No wallet generated: {}", e))?;
+        let entry = entry.protect(passphrase).map_err(|e| e.to_string())?;
+
+        self.save_wallets(std::slice::from_ref(&entry))
+            .map_err(|e| format!("Failed to save wallet: {}", e))?;
+        info!(
+            address = %entry.address(self.ss58_format),
+            scheme = self.scheme.name(),
+            "Generated a passphrase-protected wallet"
+        );
+        self.push_undo(Undo::Generated {
+            fingerprints: vec![entry.fingerprint()],
+        });
+        self.select(self.seeds.len().saturating_sub(1));
+        self.set_status(
+            StatusKind::Success,
+            "Generated a wallet that takes the passphrase to sign; back up its phrase and passphrase",
+        );
+        Ok(())
+    }
+
+    /// Generates `count` wallets with the current scheme and secret kind and appends them
+    /// to the keys file together.
+    fn generate_batch(&mut self, count: &str) -> Result<(), String> {
+        let count: usize = count
+            .trim()
+            .parse()
+            .ok()
+            .filter(|count| (1..=MAX_BATCH_SIZE).contains(count))
+            .ok_or_else(|| format!("Enter a number from 1 to {}", MAX_BATCH_SIZE))?;
+
+        self.confirm = Some(Confirm {
+            prompt: format!("Generate {} new {} wallets?", count, self.scheme.name()),
+            action: ConfirmAction::GenerateBatch(count),
+        });
+        Ok(())
+    }
+
+    /// Collects the wallets of the batch being generated, and appends them all to the keys
+    /// file at once when the last worker is done.
+    fn check_batch(&mut self) {
+        let Some(batch) = &mut self.batch else {
+            return;
+        };
+        batch.generated.sample(Instant::now());
+
+        loop {
+            match batch.results.try_recv() {
+                Ok(Ok(entries)) => {
+                    batch.entries.extend(entries);
+                    batch.pending -= 1;
+                    if batch.pending == 0 {
+                        break;
+                    }
+                }
+                Ok(Err(e)) => {
+                    self.batch = None;
+                    self.set_status(StatusKind::Error, format!("No wallets generated: {}", e));
+                    return;
+                }
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.batch = None;
+                    self.set_status(
+                        StatusKind::Error,
+                        "No wallets generated: a worker stopped unexpectedly",
+                    );
+                    return;
+                }
+            }
+        }
+
+        let Some(mut batch) = self.batch.take() else {
+            return;
+        };
+        let entries = std::mem::take(&mut batch.entries);
+        match self.save_wallets(&entries) {
+            Ok(()) => {
+                self.push_undo(Undo::Generated {
+                    fingerprints: entries.iter().map(KeyEntry::fingerprint).collect(),
+                });
+                self.set_status(
+                    StatusKind::Success,
+                    format!("Generated {} {} wallets", batch.count, batch.scheme.name()),
+                )
+            }
+            Err(e) => self.set_status(StatusKind::Error, format!("Failed to save wallets: {}", e)),
+        }
+    }
+
+    /// Saves and selects the wallet a vanity search found, once it has found one.
+    fn check_vanity(&mut self) {
+        let Some(search) = &mut self.vanity else {
+            return;
+        };
+        search.attempts.sample(Instant::now());
+        let Ok(found) = search.found.try_recv() else {
+            return;
+        };
+        let attempts = search.attempts.count();
+        self.vanity = None;
+
+        let (address, entry) = match found {
+            Ok(found) => found,
+            Err(e) => {
+                self.set_status(StatusKind::Error, format!("Vanity search failed: {}", e));
+                return;
+            }
+        };
+
+        let result = self.save_wallets(std::slice::from_ref(&entry));
+
+        match result {
+            Ok(()) => {
+                self.select(self.seeds.len().saturating_sub(1));
+                self.push_undo(Undo::Generated {
+                    fingerprints: vec![entry.fingerprint()],
+                });
+                self.set_status(
+                    StatusKind::Success,
+                    format!("Found {} after {} attempts", address, attempts),
+                );
+            }
+            Err(e) => self.set_status(StatusKind::Error, format!("Failed to save wallet: {}", e)),
+        }
+    }
+
+    /// Adds the next hard-derived account `//<n>` of the selected wallet's secret, where
+    /// `n` is one past the highest index already in the keys file.
+    fn derive_next(&mut self) {
+        let Some(selected) = self.seeds.get(self.selected) else {
+            return;
+        };
+
+        let next = self
+            .seeds
+            .iter()
+            .filter(|entry| entry.scheme == selected.scheme && entry.secret == selected.secret)
+            .filter_map(|entry| {
+                entry
+                    .path
+                    .as_deref()?
+                    .strip_prefix("//")?
+                    .parse::<u64>()
+                    .ok()
+            })
+            .max()
+            .map_or(0, |index| index + 1);
+
+        let result = selected
+            .with_path(Some(&format!("//{}", next)))
+            .and_then(|entry| self.save_wallets(&[entry.stamped()]));
+
+        match result {
+            Ok(()) => {
+                self.select(self.seeds.len().saturating_sub(1));
+                self.set_status(StatusKind::Success, format!("Derived account //{}", next));
+            }
+            Err(e) => self.set_status(StatusKind::Error, format!("Failed to derive: {}", e)),
+        }
+    }
+
+    /// The account of `entry`'s secret at `path`, which must not be empty.
+    fn derive_path(entry: &KeyEntry, path: &str) -> Result<KeyEntry, String> {
+        let path = path.trim();
+        if path.is_empty() {
+            return Err("Enter a path such as //0".to_string());
+        }
+
+        entry.with_path(Some(path)).map_err(|e| e.to_string())
+    }
+
+    /// Adds the account of the selected wallet's secret at `path` to the keys file.
+    fn add_derived(&mut self, path: &str) -> Result<(), String> {
+        let Some(selected) = self.seeds.get(self.selected) else {
+            return Ok(());
+        };
+        let entry = Self::derive_path(selected, path)?.stamped();
+
+        self.save_wallets(std::slice::from_ref(&entry))
+            .map_err(|e| format!("Failed to save wallet: {}", e))?;
+
+        self.select(self.seeds.len().saturating_sub(1));
+        self.set_status(
+            StatusKind::Success,
+            format!("Derived {}", entry.address(self.ss58_format)),
+        );
+        Ok(())
+    }
+
+    /// Fetches the free balance of every wallet from the node at `url`, once it answers.
+    fn fetch_balances(&mut self, url: &str) -> Result<(), String> {
+        let url = url.trim();
+        if url.is_empty() {
+            return Err("Enter the URL of a node, e.g. http://127.0.0.1:9944".to_string());
+        }
+        self.rpc_url = url.to_string();
+        self.connecting = Some(Connecting::start(url, NodeTask::Balances));
+        Ok(())
+    }
+
+    /// Runs the task that waited for the node once it has answered, or keeps the reason
+    /// it could not be reached on screen.
+    fn check_connection(&mut self) {
+        let Some(connecting) = &mut self.connecting else {
+            return;
+        };
+        let result = match connecting.result.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            // Already failed, and waiting to be retried or dismissed.
+            Err(mpsc::TryRecvError::Disconnected) if connecting.error.is_some() => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Err("The connection attempt stopped".to_string())
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                let task = connecting.task.clone();
+                info!(url = %connecting.url, elapsed = ?connecting.started.elapsed(), "Connected to the node");
+                self.connecting = None;
+                match task {
+                    NodeTask::Balances => self.spawn_balance_fetch(),
+                    NodeTask::Transfer {
+                        index,
+                        from,
+                        destination,
+                        amount,
+                        entry,
+                    } => self.spawn_transfer(index, &from, &destination, amount, *entry),
+                }
+            }
+            Err(e) => {
+                let message = format!("Cannot connect to {}: {}", connecting.url, e);
+                connecting.error = Some(e);
+                self.set_status(StatusKind::Error, message);
+            }
+        }
+    }
+
+    /// Starts fetching the free balance of every wallet from the node at `rpc_url` in the
+    /// background. Wallets show "…" until their balance arrives.
+    fn spawn_balance_fetch(&mut self) {
+        let mut accounts = self.account_ids.clone();
+        accounts.sort();
+        accounts.dedup();
+
+        self.balances = accounts
+            .iter()
+            .map(|account| (account.clone(), Balance::Loading))
+            .collect();
+
+        let client = RpcClient::new(&self.rpc_url);
+        let (sender, updates) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut accounts = accounts.into_iter();
+            while let Some(account) = accounts.next() {
+                match client.free_balance(&account) {
+                    Ok(free) => {
+                        if sender.send((account, Ok(free))).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        // The node is unlikely to answer for the remaining accounts either,
+                        // so don't wait for each of them to time out.
+                        let e = e.to_string();
+                        for account in std::iter::once(account).chain(accounts) {
+                            let _ = sender.send((account, Err(e.clone())));
+                        }
+                        return;
+                    }
+                }
+            }
+        });
+
+        // Replacing the receiver makes any earlier fetch stop at its next result.
+        self.balance_updates = Some(updates);
+    }
+
+    /// Applies the balances the background fetch has delivered since the last call.
+    fn check_balances(&mut self) {
+        let Some(updates) = &self.balance_updates else {
+            return;
+        };
+
+        let mut error = None;
+        let mut finished = false;
+        loop {
+            match updates.try_recv() {
+                Ok((account, Ok(free))) => {
+                    self.balances.insert(account, Balance::Free(free));
+                }
+                Ok((account, Err(e))) => {
+                    self.balances.insert(account, Balance::Unavailable);
+                    error.get_or_insert(e);
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+
+        if let Some(e) = error {
+            self.set_status(StatusKind::Error, format!("Failed to fetch balance: {}", e));
+        }
+        if finished {
+            self.balance_updates = None;
+        }
+    }
+
+    /// Checks that `destination` is a valid SS58 address before asking for the amount.
+    fn enter_transfer_destination(&mut self, destination: &str) -> Result<(), String> {
+        let destination = destination.trim();
+        let (account, _) =
+            wallet::parse_address(destination).map_err(|e| format!("Bad SS58 address: {}", e))?;
+        if self.account_ids.get(self.selected) == Some(&account) {
+            return Err("Destination is the selected wallet itself".to_string());
+        }
+
+        self.input = Some(Input::new(InputKind::TransferAmount {
+            destination: destination.to_string(),
+        }));
+        Ok(())
+    }
+
+    /// Shows the address book, reading it first if it has not been yet. `picking` is what
+    /// had been typed as the transfer destination when the book is opened to pick one.
+    fn open_address_book(&mut self, picking: Option<String>) {
+        if self.address_book.is_none() {
+            let Some(path) = self.address_book_path.clone() else {
+                self.set_status(
+                    StatusKind::Error,
+                    "Nowhere to keep the address book; set address_book in the config file",
+                );
+                return;
+            };
+            match AddressBook::load(&path) {
+                Ok(book) => self.address_book = Some(book),
+                Err(e) => {
+                    self.set_status(
+                        StatusKind::Error,
+                        format!("Failed to read the address book: {}", e),
+                    );
+                    return;
+                }
+            }
+        }
+
+        if picking.is_some() {
+            self.input = None;
+        }
+        self.book_view = Some(BookView {
+            selected: 0,
+            picking,
+        });
+    }
+
+    /// Closes the address book, going on with a transfer to `destination` if one was
+    /// picked. A book opened from the transfer destination input goes back to it either way.
+    fn close_address_book(&mut self, destination: Option<String>) {
+        let Some(view) = self.book_view.take() else {
+            return;
+        };
+        let Some(destination) = destination.or(view.picking) else {
+            return;
+        };
+
+        let mut input = Input::new(InputKind::TransferDestination);
+        input.value.push_str(&destination);
+        self.input = Some(input);
+        self.check_address_input();
+    }
+
+    /// Handles keys while the address book is shown.
+    fn on_book_key_event(&mut self, key: KeyEvent) {
+        let (Some(view), Some(book)) = (&mut self.book_view, &self.address_book) else {
+            return;
+        };
+        let count = book.contacts().len();
+        let selected = view.selected.min(count.saturating_sub(1));
+
+        match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
+            (_, KeyCode::Esc) => self.close_address_book(None),
+            (_, KeyCode::Up) => view.selected = selected.saturating_sub(1),
+            (_, KeyCode::Down) => view.selected = (selected + 1).min(count.saturating_sub(1)),
+            (_, KeyCode::Char('a')) => {
+                self.input = Some(Input::new(InputKind::ContactName { index: None }))
+            }
+            (_, KeyCode::Char('e')) if selected < count => {
+                let mut input = Input::new(InputKind::ContactName {
+                    index: Some(selected),
+                });
+                input.value.push_str(&book.contacts()[selected].name);
+                self.input = Some(input);
+            }
+            (_, KeyCode::Char('d')) if selected < count => self.remove_contact(selected),
+            (_, KeyCode::Enter) if selected < count => {
+                let address = book.contacts()[selected].address.clone();
+                // Opened from the wallet list, the book starts a transfer from the selected
+                // wallet, which needs to have a key.
+                if view.picking.is_none()
+                    && (!self.has_selection() || self.seeds[self.selected].is_watch_only())
+                {
+                    self.set_status(
+                        StatusKind::Error,
+                        "Select a wallet with a key to transfer from",
+                    );
+                    return;
+                }
+                self.close_address_book(Some(address));
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves on to the address of a contact once its name is accepted.
+    fn enter_contact_name(&mut self, index: Option<usize>, name: &str) -> Result<(), String> {
+        let book = self
+            .address_book
+            .as_ref()
+            .ok_or("The address book is not open")?;
+        book.check_name(index, name)?;
+
+        let mut input = Input::new(InputKind::ContactAddress {
+            index,
+            name: name.trim().to_string(),
+        });
+        if let Some(contact) = index.and_then(|index| book.contacts().get(index)) {
+            input.value.push_str(&contact.address);
+        }
+        self.input = Some(input);
+        self.check_address_input();
+        Ok(())
+    }
+
+    /// Adds the contact, or replaces the one at `index`, and saves the address book.
+    fn save_contact(
+        &mut self,
+        index: Option<usize>,
+        name: &str,
+        address: &str,
+    ) -> Result<(), String> {
+        let book = self
+            .address_book
+            .as_mut()
+            .ok_or("The address book is not open")?;
+        let position = book.set(index, name, address)?;
+        if let Some(view) = &mut self.book_view {
+            view.selected = position;
+        }
+        let done = match index {
+            Some(_) => format!("Changed contact {}", name),
+            None => format!("Added contact {}", name),
+        };
+        self.save_address_book(done);
+        Ok(())
+    }
+
+    fn remove_contact(&mut self, index: usize) {
+        let Some(contact) = self
+            .address_book
+            .as_mut()
+            .and_then(|book| book.remove(index))
+        else {
+            return;
+        };
+        if let (Some(view), Some(book)) = (&mut self.book_view, &self.address_book) {
+            view.selected = view.selected.min(book.contacts().len().saturating_sub(1));
+        }
+        self.save_address_book(format!("Removed contact {}", contact.name));
+    }
+
+    /// Writes the address book to its file, unless dry-running, and reports `done` once it
+    /// is saved.
+    fn save_address_book(&mut self, done: String) {
+        let result = match &self.address_book {
+            Some(book) if !self.dry_run => book
+                .save()
+                .map_err(|e| format!("Failed to save {}: {}", book.path().display(), e)),
+            _ => Ok(()),
+        };
+        match result {
+            Ok(()) => self.set_status(StatusKind::Success, done),
+            Err(e) => self.set_status(StatusKind::Error, e),
+        }
+    }
+
+    /// Checks a transfer of `amount` tokens from the selected wallet to `destination`, and
+    /// starts it once the node answers.
+    fn start_transfer(&mut self, destination: &str, amount: &str) -> Result<(), String> {
+        let Some(account) = self.account_ids.get(self.selected) else {
+            return Err("No wallet selected".to_string());
+        };
+        wallet::parse_address(destination).map_err(|e| format!("Bad SS58 address: {}", e))?;
+
+        let planck = parse_balance(amount, self.ss58_format)?;
+        if planck == 0 {
+            return Err("Enter an amount above zero".to_string());
+        }
+        // The worker checks again against the balance at the time of sending.
+        if let Some(Balance::Free(free)) = self.balances.get(account) {
+            if planck > *free {
+                return Err(format!(
+                    "Amount exceeds the free balance of {}",
+                    format_balance(*free, self.ss58_format)
+                ));
+            }
+        }
+
+        let (index, from) = (self.selected, self.wallets[self.selected].address.clone());
+        let purpose = PassphraseFor::Transfer {
+            destination: destination.to_string(),
+            amount: planck,
+        };
+        if self.seeds[index].is_protected() {
+            self.ask_passphrase(index, from, purpose);
+        } else {
+            self.run_with_entry(index, &from, self.seeds[index].clone(), purpose);
+        }
+        Ok(())
+    }
+
+    /// Starts signing and submitting a transfer of `planck` from the wallet at `index`,
+    /// with the key of `entry`, to `destination` in the background, and shows its
+    /// progress. Nothing is sent if the list has changed since and `from` is no longer at
+    /// `index`.
+    fn spawn_transfer(
+        &mut self,
+        index: usize,
+        from: &str,
+        destination: &str,
+        planck: u128,
+        entry: KeyEntry,
+    ) {
+        if self
+            .wallets
+            .get(index)
+            .is_none_or(|wallet| wallet.address != from)
+        {
+            self.set_status(
+                StatusKind::Error,
+                "The wallet list changed meanwhile; nothing was sent",
+            );
+            return;
+        }
+        let Ok((dest, _)) = wallet::parse_address(destination) else {
+            return;
+        };
+
+        let client = RpcClient::new(&self.rpc_url);
+        let (sender, events) = mpsc::channel();
+        let (confirm, confirmation) = mpsc::channel();
+        std::thread::spawn(move || {
+            transfer::run(client, entry, dest, planck, sender, confirmation)
+        });
+
+        // Replacing an earlier transfer stops tracking it at its next event.
+        let transfer = Transfer {
+            from: from.to_string(),
+            to: destination.to_string(),
+            amount: planck,
+            ss58_format: self.ss58_format,
+            fee: None,
+            confirm: Some(confirm),
+            awaiting_confirmation: false,
+            hash: None,
+            state: Line::raw(format!("Estimating the fee with {}…", self.rpc_url)),
+            done: false,
+            events,
+        };
+        self.popup = Some(transfer.popup(&self.theme));
+        self.transfer = Some(transfer);
+    }
+
+    /// Applies the progress the background transfer has reported since the last call,
+    /// refreshing its popup if it is still shown.
+    fn check_transfer(&mut self) {
+        let Some(transfer) = &mut self.transfer else {
+            return;
+        };
+
+        let mut status = None;
+        let mut show_popup = false;
+        loop {
+            let event = match transfer.events.try_recv() {
+                Ok(event) => event,
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    transfer.done = true;
+                    break;
+                }
+            };
+
+            let (state, kind) = match event {
+                TransferEvent::Prepared { fee, free } => {
+                    transfer.fee = Some(fee);
+                    if transfer.amount.saturating_add(fee) > free {
+                        // Dropping the sender stops the worker without submitting.
+                        transfer.confirm = None;
+                        transfer.done = true;
+                        show_popup = true;
+                        (
+                            format!(
+                                "Not submitted: amount plus fee exceeds the free balance of {}",
+                                format_balance(free, transfer.ss58_format)
+                            ),
+                            StatusKind::Error,
+                        )
+                    } else {
+                        transfer.awaiting_confirmation = true;
+                        // Show the confirmation even if the popup was closed meanwhile.
+                        show_popup = true;
+                        ("Submit this transfer?".to_string(), StatusKind::Success)
+                    }
+                }
+                TransferEvent::Submitted(hash) => {
+                    transfer.hash = Some(hash);
+                    (
+                        "Submitted, waiting for inclusion…".to_string(),
+                        StatusKind::Success,
+                    )
+                }
+                TransferEvent::InBlock { number, hash } => (
+                    format!(
+                        "In block #{} (0x{}), waiting for finalization…",
+                        number,
+                        hex::encode(hash)
+                    ),
+                    StatusKind::Success,
+                ),
+                TransferEvent::Finalized { number } => {
+                    transfer.done = true;
+                    (
+                        format!("Finalized in block #{}", number),
+                        StatusKind::Success,
+                    )
+                }
+                TransferEvent::Failed(e) => {
+                    transfer.done = true;
+                    (format!("Failed: {}", e), StatusKind::Error)
+                }
+            };
+            transfer.state =
+                Line::styled(state.clone(), Style::default().fg(kind.color(&self.theme)));
+            status = Some((kind, format!("Transfer: {}", state)));
+        }
+
+        let popup_open = self.popup.as_ref().is_some_and(|p| p.title == "Transfer");
+        if show_popup || status.is_some() && popup_open {
+            self.popup = Some(transfer.popup(&self.theme));
+        }
+        if let Some((kind, message)) = status {
+            self.set_status(kind, message);
+        }
+    }
+
+    /// Runs an action the user has just confirmed.
+    fn run_confirmed(&mut self, action: ConfirmAction) {
+        match action {
+            ConfirmAction::Generate => self.press_button(),
+            ConfirmAction::GenerateBatch(count) => {
+                self.batch = Some(BatchGeneration::start(
+                    count,
+                    self.scheme,
+                    self.mnemonic_words,
+                    self.ss58_format,
+                ))
+            }
+            ConfirmAction::Delete { index, address } => self.delete_wallet(index, &address),
+            ConfirmAction::DeleteMarked(fingerprints) => self.delete_marked(&fingerprints),
+            // Whoever is at the keyboard has to know the password of an encrypted keys
+            // file, not just find the wallet unlocked.
+            ConfirmAction::RevealSecret {
+                index,
+                address,
+                phrase,
+            } if self.vault.is_some() => {
+                self.input = Some(Input::new(InputKind::RevealPassword {
+                    index,
+                    address,
+                    phrase,
+                    copy: false,
+                }))
+            }
+            ConfirmAction::RevealSecret {
+                index,
+                address,
+                phrase,
+            } => self.reveal_secret(index, &address, phrase),
+            ConfirmAction::CopySecret { index, address } => {
+                self.input = Some(Input::new(InputKind::RevealPassword {
+                    index,
+                    address,
+                    phrase: false,
+                    copy: true,
+                }))
+            }
+            ConfirmAction::Inspect { index, address } if self.vault.is_some() => {
+                self.input = Some(Input::new(InputKind::InspectPassword { index, address }))
+            }
+            ConfirmAction::Inspect { index, address } => self.inspect(index, &address),
+            ConfirmAction::Rotate { index, address } => self.rotate_wallet(index, &address),
+            ConfirmAction::SignPayload {
+                index,
+                address,
+                payload,
+            } => {
+                let Some(entry) = self
+                    .seeds
+                    .get(index)
+                    .filter(|_| self.wallets[index].address == address)
+                else {
+                    self.set_status(
+                        StatusKind::Error,
+                        "The wallet list changed meanwhile; nothing was signed",
+                    );
+                    return;
+                };
+                let purpose = PassphraseFor::SignPayload { payload };
+                if entry.is_protected() {
+                    self.ask_passphrase(index, address, purpose);
+                } else {
+                    self.run_with_entry(index, &address, entry.clone(), purpose);
+                }
+            }
+            ConfirmAction::RestrictPermissions => self.restrict_keys_permissions(),
+            ConfirmAction::Undo => self.undo_last(),
+        }
+    }
+
+    /// The secret of the wallet at `index`, or its mnemonic phrase if `phrase`, unless the
+    /// list has changed since and `address` is no longer there, in which case `nothing`
+    /// says what did not happen.
+    fn secret_of(
+        &self,
+        index: usize,
+        address: &str,
+        phrase: bool,
+        nothing: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        if self
+            .wallets
+            .get(index)
+            .map(|wallet| wallet.address.as_str())
+            != Some(address)
+        {
+            return Err(format!("The wallet list changed meanwhile; {}", nothing));
+        }
+
+        if phrase {
+            return self.seeds[index]
+                .phrase()
+                .map(|phrase| Zeroizing::new(phrase.to_string()))
+                .ok_or_else(|| "The wallet was not made from a mnemonic phrase".to_string());
+        }
+        self.wallets[index]
+            .private_key()
+            .map(|secret| Zeroizing::new(secret.to_string()))
+            .ok_or_else(|| "Watch-only wallets have no secret".to_string())
+    }
+
+    /// Shows the secret of the wallet at `index`, or its mnemonic phrase if `phrase`, unless
+    /// the list has changed since and `address` is no longer there.
+    fn reveal_secret(&mut self, index: usize, address: &str, phrase: bool) {
+        match self.secret_of(index, address, phrase, "nothing was revealed") {
+            Ok(secret) => {
+                self.revealed = Some(RevealedSecret {
+                    address: address.to_string(),
+                    secret,
+                    phrase,
+                    shown_at: Instant::now(),
+                })
+            }
+            Err(e) => self.set_status(StatusKind::Error, e),
+        }
+    }
+
+    /// Copies the secret of the wallet at `index` to the clipboard, to be cleared again
+    /// once the clipboard timeout has passed.
+    fn copy_secret(&mut self, index: usize, address: &str) {
+        let secret = match self.secret_of(index, address, false, "nothing was copied") {
+            Ok(secret) => secret,
+            Err(e) => {
+                self.set_status(StatusKind::Error, e);
+                return;
+            }
+        };
+        if let Err(e) = self.copy_to_clipboard(secret.to_string()) {
+            self.set_status(StatusKind::Error, format!("Failed to copy: {}", e));
+            return;
+        }
+
+        warn!(address, "Secret seed copied to the clipboard");
+        self.copied_secret = Some((secret, Instant::now()));
+        let until = match self.clipboard_clear {
+            Some(timeout) => format!("for the next {} seconds", timeout.as_secs()),
+            None => "until something else is copied".to_string(),
+        };
+        self.set_status(
+            StatusKind::Warning,
+            format!("Secret seed copied. The clipboard holds a secret {}", until),
+        );
+    }
+
+    /// Shows what `subkey inspect` prints for the wallet at `index`, unless the list has
+    /// changed since and `address` is no longer there.
+    fn inspect(&mut self, index: usize, address: &str) {
+        if self
+            .wallets
+            .get(index)
+            .map(|wallet| wallet.address.as_str())
+            != Some(address)
+        {
+            self.set_status(
+                StatusKind::Error,
+                "The wallet list changed meanwhile; nothing was shown",
+            );
+            return;
+        }
+        let entry = &self.seeds[index];
+        let output = match entry.inspect(entry.ss58_format().unwrap_or(self.ss58_format)) {
+            Ok(output) => output,
+            Err(e) => {
+                self.set_status(StatusKind::Error, e);
+                return;
+            }
+        };
+
+        warn!(address, "Secret seed shown as subkey inspect output");
+        self.popup = Some(Popup {
+            title: "subkey inspect".to_string(),
+            lines: output
+                .lines()
+                .map(|line| Line::raw(line.to_string()))
+                .collect(),
+            copy: Some(output.to_string()),
+            hint: None,
+            secret: true,
+        });
+    }
+
+    /// Empties the clipboard if it still holds the secret copied last. Anything copied
+    /// since is left alone.
+    fn clear_copied_secret(&mut self) {
+        let (Some((secret, _)), Some(clipboard)) = (self.copied_secret.take(), &mut self.clipboard)
+        else {
+            return;
+        };
+        let held = clipboard.0.get_text().map(Zeroizing::new);
+        if held.is_ok_and(|text| text == secret) {
+            match clipboard.0.clear() {
+                Ok(()) => {
+                    self.set_status(StatusKind::Success, "Cleared the secret from the clipboard")
+                }
+                Err(e) => self.set_status(
+                    StatusKind::Error,
+                    format!("Failed to clear the secret from the clipboard: {}", e),
+                ),
+            }
+        }
+    }
+
+    /// Removes the wallet at `index` from the keys file and the in-memory list, unless the
+    /// list has changed since and `address` is no longer there.
+    fn delete_wallet(&mut self, index: usize, address: &str) {
+        if self
+            .wallets
+            .get(index)
+            .map(|wallet| wallet.address.as_str())
+            != Some(address)
+        {
+            self.set_status(
+                StatusKind::Error,
+                "The wallet list changed meanwhile; nothing was deleted",
+            );
+            return;
+        }
+
+        let mut seeds = self.seeds.clone();
+        let entry = seeds.remove(index);
+
+        if let Err(e) = self.write_wallets(seeds) {
+            self.set_status(StatusKind::Error, format!("Failed to delete wallet: {}", e));
+            return;
+        }
+
+        self.push_undo(Undo::Deleted { index, entry });
+        self.set_status(StatusKind::Success, "Wallet deleted");
+    }
+
+    /// Replaces the wallet at `index` with a newly generated one of the same scheme, label
+    /// and derivation path, unless the list has changed since and `address` is no longer
+    /// there. The old wallet is appended to the retired keys file first, encrypted like
+    /// the keys file, so that it is never lost.
+    fn rotate_wallet(&mut self, index: usize, address: &str) {
+        if self
+            .wallets
+            .get(index)
+            .map(|wallet| wallet.address.as_str())
+            != Some(address)
+        {
+            self.set_status(
+                StatusKind::Error,
+                "The wallet list changed meanwhile; nothing was replaced",
+            );
+            return;
+        }
+
+        let old = self.seeds[index].clone();
+        let new = wallet::generate_random_wallet(old.scheme, self.mnemonic_words, self.ss58_format)
+            .and_then(|(_, entry)| entry.with_path(old.path.as_deref()))
+            .map(|entry| KeyEntry {
+                label: old.label.clone(),
+                ..entry.stamped()
+            });
+        let new = match new {
+            Ok(new) => new,
+            Err(e) => {
+                self.set_status(StatusKind::Error, format!("No wallet generated: {}", e));
+                return;
+            }
+        };
+
+        let retired_path = wallet::retired_keys_path(&self.keys_path);
+        if !self.dry_run {
+            if let Err(e) = wallet::save_wallet_to_file(&retired_path, &old, self.vault.as_mut()) {
+                self.set_status(
+                    StatusKind::Error,
+                    format!("Failed to keep the old key in {}: {}", retired_path, e),
+                );
+                return;
+            }
+        }
+
+        let new_address = new.address(self.ss58_format);
+        let mut seeds = self.seeds.clone();
+        seeds[index] = new;
+        if let Err(e) = self.write_wallets(seeds) {
+            self.set_status(
+                StatusKind::Error,
+                format!(
+                    "Failed to save the new key: {}. The old one is still in place",
+                    e
+                ),
+            );
+            return;
+        }
+
+        warn!(
+            old_address = address,
+            new_address,
+            retired = retired_path,
+            "Replaced the key of a wallet"
+        );
+        self.set_status(
+            StatusKind::Success,
+            format!(
+                "Wallet {} is now {}. The old key is in {}",
+                index + 1,
+                new_address,
+                retired_path
+            ),
+        );
+    }
+
+    /// Removes the wallets with `fingerprints` from the keys file in a single rewrite, and
+    /// unmarks them.
+    fn delete_marked(&mut self, fingerprints: &[[u8; 32]]) {
+        let (mut kept, mut deleted) = (Vec::new(), Vec::new());
+        for (index, (entry, fingerprint)) in self.seeds.iter().zip(&self.fingerprints).enumerate() {
+            match fingerprints.contains(fingerprint) {
+                true => deleted.push((index, entry.clone())),
+                false => kept.push(entry.clone()),
+            }
+        }
+        if deleted.is_empty() {
+            self.set_status(
+                StatusKind::Error,
+                "The marked wallets are no longer in the keys file",
+            );
+            return;
+        }
+
+        if let Err(e) = self.write_wallets(kept) {
+            self.set_status(
+                StatusKind::Error,
+                format!("Failed to delete wallets: {}", e),
+            );
+            return;
+        }
+
+        let count = deleted.len();
+        self.marked.clear();
+        self.push_undo(Undo::DeletedMarked { entries: deleted });
+        self.set_status(
+            StatusKind::Success,
+            format!(
+                "{} wallet{} deleted",
+                count,
+                if count == 1 { "" } else { "s" }
+            ),
+        );
+    }
+
+    /// Records a change to the keys file for [`Action::Undo`], forgetting the oldest one
+    /// beyond [`UNDO_LEVELS`].
+    fn push_undo(&mut self, undo: Undo) {
+        self.undo.push(undo);
+        if self.undo.len() > UNDO_LEVELS {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Asks whether to undo the most recent change to the keys file, if there is one.
+    fn ask_undo(&mut self) {
+        let prompt = match self.undo.last() {
+            Some(Undo::Generated { fingerprints }) => format!(
+                "Undo: delete the {} wallet{} just generated?",
+                fingerprints.len(),
+                if fingerprints.len() == 1 { "" } else { "s" }
+            ),
+            Some(Undo::Deleted { entry, .. }) => format!(
+                "Undo: restore the deleted wallet {}?",
+                entry.address(entry.ss58_format().unwrap_or(self.ss58_format))
+            ),
+            Some(Undo::DeletedMarked { entries }) => format!(
+                "Undo: restore the {} deleted wallet{}?",
+                entries.len(),
+                if entries.len() == 1 { "" } else { "s" }
+            ),
+            None => {
+                self.set_status(StatusKind::Warning, "Nothing to undo");
+                return;
+            }
+        };
+        self.confirm = Some(Confirm {
+            prompt,
+            action: ConfirmAction::Undo,
+        });
+    }
+
+    /// Reverses the most recent change to the keys file. It stays on the undo stack when
+    /// the keys file cannot be written.
+    fn undo_last(&mut self) {
+        let Some(undo) = self.undo.pop() else {
+            return;
+        };
+
+        let mut seeds = self.seeds.clone();
+        let (result, done) = match &undo {
+            Undo::Generated { fingerprints } => {
+                seeds.retain(|entry| !fingerprints.contains(&entry.fingerprint()));
+                let removed = self.seeds.len() - seeds.len();
+                if removed == 0 {
+                    self.set_status(
+                        StatusKind::Error,
+                        "The generated wallets are no longer in the keys file",
+                    );
+                    return;
+                }
+                (
+                    self.write_wallets(seeds),
+                    format!(
+                        "Deleted {} generated wallet{}",
+                        removed,
+                        if removed == 1 { "" } else { "s" }
+                    ),
+                )
+            }
+            Undo::Deleted { index, entry } => {
+                if seeds
+                    .iter()
+                    .any(|seed| seed.fingerprint() == entry.fingerprint())
+                {
+                    self.set_status(StatusKind::Error, "The wallet is back already");
+                    return;
+                }
+                let index = (*index).min(seeds.len());
+                seeds.insert(index, entry.clone());
+                let result = self.write_wallets(seeds);
+                self.select(index);
+                (result, "Wallet restored".to_string())
+            }
+            Undo::DeletedMarked { entries } => {
+                let present: HashSet<[u8; 32]> = seeds.iter().map(KeyEntry::fingerprint).collect();
+                let mut restored = 0;
+                // Ascending indices put every wallet back where it was.
+                for (index, entry) in entries {
+                    if !present.contains(&entry.fingerprint()) {
+                        seeds.insert((*index).min(seeds.len()), entry.clone());
+                        restored += 1;
+                    }
+                }
+                if restored == 0 {
+                    self.set_status(StatusKind::Error, "The wallets are back already");
+                    return;
+                }
+                (
+                    self.write_wallets(seeds),
+                    format!(
+                        "Restored {} wallet{}",
+                        restored,
+                        if restored == 1 { "" } else { "s" }
+                    ),
+                )
+            }
+        };
+
+        match result {
+            Ok(()) => self.set_status(StatusKind::Success, done),
+            Err(e) => {
+                self.undo.push(undo);
+                self.set_status(StatusKind::Error, format!("Failed to undo: {}", e));
+            }
+        }
+    }
+
+    /// Shows the selected wallet's SS58 address as a QR code.
+    fn show_qr_code(&mut self) {
+        let Some(address) = self
+            .wallets
+            .get(self.selected)
+            .map(|wallet| wallet.address.clone())
+        else {
+            return;
+        };
+
+        match QrView::new("Address", address) {
+            Ok(qr_code) => self.qr_code = Some(qr_code),
+            Err(e) => self.set_status(
+                StatusKind::Error,
+                format!("Failed to encode QR code: {}", e),
+            ),
+        }
+    }
+
+    /// Copies the selected wallet's SS58 address to the OS clipboard.
+    fn copy_selected_address(&mut self) {
+        let Some(address) = self
+            .wallets
+            .get(self.selected)
+            .map(|wallet| wallet.address.clone())
+        else {
+            return;
+        };
+
+        match self.copy_to_clipboard(address) {
+            Ok(()) => self.set_status(StatusKind::Success, "Address copied"),
+            Err(e) => self.set_status(StatusKind::Error, format!("Failed to copy address: {}", e)),
+        }
+    }
+
+    /// Copies the addresses of the wallets shown in the list, one per line and in the order
+    /// shown.
+    fn copy_visible_addresses(&mut self) {
+        let addresses = self.visible_addresses();
+        if addresses.is_empty() {
+            self.set_status(StatusKind::Warning, "No addresses to copy");
+            return;
+        }
+
+        let count = addresses.len();
+        match self.copy_to_clipboard(addresses.join("\n")) {
+            Ok(()) => self.set_status(
+                StatusKind::Success,
+                format!(
+                    "Copied {} address{}",
+                    count,
+                    if count == 1 { "" } else { "es" }
+                ),
+            ),
+            Err(e) => self.set_status(
+                StatusKind::Error,
+                format!("Failed to copy addresses: {}", e),
+            ),
+        }
+    }
+
+    /// Addresses of the wallets shown in the list, after filtering and sorting.
+    fn visible_addresses(&self) -> Vec<String> {
+        self.visible_indices()
+            .into_iter()
+            .map(|i| self.wallets[i].address.clone())
+            .collect()
+    }
+
+    /// Copies the selected wallet's address as a `<scheme>:<address>` URI, with the scheme
+    /// configured for the address's network, or as it is when there is none.
+    fn copy_selected_uri(&mut self) {
+        let Some(uri) = self.selected_uri() else {
+            return;
+        };
+
+        let copied = if uri.contains(':') {
+            "URI copied"
+        } else {
+            "Address copied; no URI scheme is configured for its network"
+        };
+        match self.copy_to_clipboard(uri) {
+            Ok(()) => self.set_status(StatusKind::Success, copied),
+            Err(e) => self.set_status(StatusKind::Error, format!("Failed to copy URI: {}", e)),
+        }
+    }
+
+    /// The URI of the selected wallet's address, which is the bare address when no URI
+    /// scheme is configured for its network.
+    fn selected_uri(&self) -> Option<String> {
+        let address = &self.wallets.get(self.selected)?.address;
+        let scheme = AccountId32::from_ss58check_with_version(address)
+            .ok()
+            .and_then(|(_, format)| self.uri_schemes.get(&format.prefix()));
+
+        Some(match scheme {
+            Some(scheme) => format!("{}:{}", scheme, address),
+            None => address.clone(),
+        })
+    }
+
+    /// Shows `message` in the status line until [`STATUS_TIMEOUT`] passes or another
+    /// message replaces it.
+    fn set_status(&mut self, kind: StatusKind, message: impl Into<String>) {
+        let message = message.into();
+        match kind {
+            StatusKind::Success => info!("{}", message),
+            StatusKind::Warning => warn!("{}", message),
+            StatusKind::Error => error!("{}", message),
+        }
+        self.status = Some((message, kind));
+        self.status_set_at = Some(Instant::now());
+    }
+
+    fn copy_to_clipboard(&mut self, text: String) -> Result<(), arboard::Error> {
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self
+                .clipboard
+                .insert(ClipboardHandle(arboard::Clipboard::new()?)),
+        };
+
+        clipboard.0.set_text(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deleted_keys_file_clears_the_list_until_it_reappears() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let seeds = vec![KeyEntry::from_seed(KeyScheme::Sr25519, [7u8; 32])];
+        wallet::write_wallets_to_file(path.to_str().unwrap(), &seeds, None).unwrap();
+
+        let mut app = App::new(path.to_str().unwrap());
+        app.reload_debounce = Duration::ZERO;
+        app.load_seeds().unwrap();
+        assert_eq!(app.seeds, seeds);
+
+        std::fs::remove_file(&path).unwrap();
+        app.check_for_updates();
+        assert!(app.seeds.is_empty());
+        assert!(app.wallets.is_empty());
+        assert!(matches!(app.status, Some((_, StatusKind::Warning))));
+
+        wallet::write_wallets_to_file(path.to_str().unwrap(), &seeds, None).unwrap();
+        app.check_for_updates();
+        assert_eq!(app.seeds, seeds);
+        assert!(matches!(app.status, Some((_, StatusKind::Success))));
+    }
+
+    #[test]
+    fn reload_waits_until_the_keys_file_settles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let seeds = vec![KeyEntry::from_seed(KeyScheme::Sr25519, [8u8; 32])];
+        wallet::write_wallets_to_file(path.to_str().unwrap(), &seeds, None).unwrap();
+
+        let mut app = App::new(path.to_str().unwrap());
+        app.reload_debounce = Duration::from_secs(3600);
+        app.load_seeds().unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+        app.check_for_updates();
+        let due = app.reload_due.expect("a reload is pending");
+        app.check_for_updates();
+        assert_eq!(app.reload_due, Some(due));
+        assert_eq!(app.seeds, seeds);
+
+        app.reload_due = Some(Instant::now());
+        app.check_for_updates();
+        assert!(app.seeds.is_empty());
+        assert!(app.reload_due.is_none());
+    }
+
+    #[test]
+    fn reload_reads_the_keys_file_right_away() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let mut seeds = vec![KeyEntry::from_seed(KeyScheme::Sr25519, [12u8; 32])];
+        wallet::write_wallets_to_file(path.to_str().unwrap(), &seeds, None).unwrap();
+
+        let mut app = App::new(path.to_str().unwrap());
+        app.reload_debounce = Duration::from_secs(3600);
+        app.load_seeds().unwrap();
+
+        seeds.push(KeyEntry::from_seed(KeyScheme::Ed25519, [13u8; 32]));
+        wallet::write_wallets_to_file(path.to_str().unwrap(), &seeds, None).unwrap();
+        app.reload_due = Some(Instant::now() + app.reload_debounce);
+        app.reload();
+        assert_eq!(app.seeds, seeds);
+        assert!(app.reload_due.is_none());
+        assert_eq!(
+            app.status,
+            Some(("Reloaded 2 wallets".to_string(), StatusKind::Success))
+        );
+    }
+
+    #[test]
+    fn clicks_select_the_wallet_under_the_pointer() {
+        let click = |column, row| MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+
+        let mut app = App::new("unused-keys.txt");
+        app.set_seeds(
+            (1..=4)
+                .map(|byte| KeyEntry::from_seed(KeyScheme::Sr25519, [byte; 32]))
+                .collect(),
+        );
+        app.mode = AppMode::Unlocked;
+        app.list_area = Rect::new(0, 10, 80, 4);
+        // Scrolled by one, so the first row inside the border shows the second wallet.
+        app.list_wallets = vec![1, 2, 3];
+
+        app.on_mouse_event(click(5, 11));
+        assert_eq!(app.selected, 1);
+        app.on_mouse_event(click(5, 12));
+        assert_eq!(app.selected, 2);
+
+        // Borders, rows below the last wallet and anything outside the list do nothing.
+        app.last_click = None;
+        for (column, row) in [(5, 10), (0, 11), (79, 11), (5, 13), (5, 2)] {
+            app.on_mouse_event(click(column, row));
+            assert_eq!(app.selected, 2);
+        }
+        app.list_wallets.truncate(1);
+        app.on_mouse_event(click(5, 12));
+        assert_eq!(app.selected, 2);
+        assert!(app.last_click.is_none());
+    }
+
+    #[test]
+    fn ctrl_c_closes_a_dialog_before_quitting() {
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        let mut app = App::new("unused-keys.txt");
+        app.mode = AppMode::Unlocked;
+        app.running = true;
+
+        app.on_key_event(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
+        for c in "abandon".chars() {
+            app.on_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        app.on_key_event(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert_eq!(app.input.as_ref().unwrap().value.as_str(), "abandonq");
+
+        app.on_key_event(ctrl_c);
+        assert!(app.input.is_none());
+        assert!(app.running);
+        assert!(matches!(app.status, Some((_, StatusKind::Warning))));
+
+        app.on_key_event(ctrl_c);
+        assert!(!app.running);
+    }
+
+    #[test]
+    fn archives_restore_only_missing_wallets() {
+        let dir = tempfile::tempdir().unwrap();
+        let (keys, other) = (dir.path().join("keys.txt"), dir.path().join("other.txt"));
+        let seeds = vec![
+            // Random-looking seeds, so that the import does not warn about them.
+            KeyEntry::from_seed(KeyScheme::Sr25519, sp_core::blake2_256(&[14])),
+            KeyEntry::from_seed(KeyScheme::Ed25519, sp_core::blake2_256(&[15])),
+        ];
+        wallet::write_wallets_to_file(keys.to_str().unwrap(), &seeds, None).unwrap();
+        wallet::write_wallets_to_file(other.to_str().unwrap(), &seeds[1..], None).unwrap();
+
+        let mut app = App::new(keys.to_str().unwrap());
+        app.load_seeds().unwrap();
+        app.export_archive("secret").unwrap();
+        let archive = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "archive"))
+            .expect("an archive was written");
+        let archive = archive.to_str().unwrap();
+
+        let other = other.to_str().unwrap();
+        assert!(app.import_archive(archive, other, "wrong").is_err());
+        app.import_archive(archive, other, "secret").unwrap();
+        assert_eq!(
+            wallet::load_wallets_from_file(other, None).unwrap().seeds,
+            [seeds[1].clone(), seeds[0].clone()]
+        );
+        assert_eq!(
+            app.status.as_ref().unwrap().0,
+            format!("Restored 1 of 2 wallets into {} (1 already there)", other)
+        );
+    }
+
+    #[test]
+    fn dry_runs_change_the_list_but_not_the_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = dir.path().join("keys.txt");
+        let seeds = vec![KeyEntry::from_seed(KeyScheme::Sr25519, [18u8; 32])];
+        wallet::write_wallets_to_file(keys.to_str().unwrap(), &seeds, None).unwrap();
+        let written = std::fs::read(&keys).unwrap();
+
+        let mut app = App::new(keys.to_str().unwrap()).with_dry_run(true);
+        app.load_seeds().unwrap();
+        app.add_derived("//1").unwrap();
+        app.rename_selected("Derived").unwrap();
+        app.export_archive("secret").unwrap();
+        assert_eq!(app.seeds.len(), 2);
+        assert_eq!(app.seeds[1].label.as_deref(), Some("Derived"));
+        assert!(app.add_derived("//1").is_err());
+
+        let address = app.wallets[0].address.clone();
+        app.delete_wallet(0, &address);
+        assert_eq!(app.seeds.len(), 1);
+        assert_eq!(std::fs::read(&keys).unwrap(), written);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn rates_follow_the_most_recent_work() {
+        let mut throughput = Throughput::new();
+        let start = Instant::now();
+        let counter = throughput.counter();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| counter.fetch_add(500, Ordering::Relaxed));
+            }
+        });
+        throughput.sample(start);
+        assert_eq!(throughput.count(), 2000);
+
+        // 100 over the two seconds of the window, then nothing once it has passed.
+        throughput.sample(start + Duration::from_secs(1));
+        counter.fetch_add(100, Ordering::Relaxed);
+        throughput.sample(start + Duration::from_secs(2));
+        assert_eq!(throughput.per_second(), 50.0);
+        throughput.sample(start + RATE_WINDOW * 3);
+        throughput.sample(start + RATE_WINDOW * 4);
+        assert_eq!(throughput.per_second(), 0.0);
+    }
+
+    #[test]
+    fn batches_are_generated_in_the_background() {
+        let mut app = App::new("unused-keys.txt").with_dry_run(true);
+        app.run_confirmed(ConfirmAction::GenerateBatch(9));
+        assert!(app.batch.is_some());
+
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while app.batch.is_some() && Instant::now() < deadline {
+            app.check_batch();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(app.seeds.len(), 9);
+        assert_eq!(
+            app.status.as_ref().unwrap().0,
+            "Generated 9 sr25519 wallets"
+        );
+    }
+
+    #[test]
+    fn uris_use_the_scheme_of_the_address_network() {
+        let config = Config {
+            uri_schemes: BTreeMap::from([("polkadot".to_string(), "polkadot".to_string())]),
+            ..Config::default()
+        };
+        let mut app = App::new("unused-keys.txt").with_config(&config);
+        app.set_seeds(vec![
+            KeyEntry::from_seed(KeyScheme::Sr25519, [19u8; 32]),
+            KeyEntry::watch_only(AccountId32::new([20u8; 32]), NETWORKS[1].ss58_format()),
+        ]);
+
+        assert_eq!(app.selected_uri(), Some(app.wallets[0].address.clone()));
+        app.selected = 1;
+        assert_eq!(
+            app.selected_uri(),
+            Some(format!("polkadot:{}", app.wallets[1].address))
+        );
+    }
+
+    #[test]
+    fn copied_addresses_follow_the_filter_and_sort_order() {
+        let mut app = App::new("unused-keys.txt");
+        app.set_seeds(
+            (30u8..36)
+                .map(|byte| KeyEntry::from_seed(KeyScheme::Sr25519, [byte; 32]))
+                .collect(),
+        );
+        assert_eq!(app.visible_addresses().len(), 6);
+
+        let query = app.wallets[4].address[1..3].to_string();
+        app.set_filter(&query);
+        app.sort = SortOrder::Address;
+        let mut expected: Vec<String> = app
+            .wallets
+            .iter()
+            .map(|wallet| wallet.address.clone())
+            .filter(|address| address.to_lowercase().contains(&query.to_lowercase()))
+            .collect();
+        expected.sort();
+        assert_eq!(app.visible_addresses(), expected);
+    }
+
+    #[test]
+    fn generating_and_deleting_can_be_undone() {
+        let mut app = App::new("unused-keys.txt").with_dry_run(true);
+        app.set_seeds(vec![
+            KeyEntry::from_seed(KeyScheme::Sr25519, [21u8; 32]),
+            KeyEntry::from_seed(KeyScheme::Ed25519, [22u8; 32]),
+        ]);
+        let original = app.seeds.clone();
+
+        app.ask_undo();
+        assert!(app.confirm.is_none());
+        app.run_confirmed(ConfirmAction::Generate);
+        let address = app.wallets[0].address.clone();
+        app.delete_wallet(0, &address);
+        assert_eq!(app.seeds.len(), 2);
+
+        app.ask_undo();
+        assert_eq!(app.confirm.take().unwrap().action, ConfirmAction::Undo);
+        app.undo_last();
+        assert_eq!(app.seeds.len(), 3);
+        assert_eq!(app.seeds[0], original[0]);
+        app.undo_last();
+        assert_eq!(app.seeds, original);
+        assert!(app.undo.is_empty());
+    }
+
+    #[test]
+    fn marked_wallets_are_deleted_together() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let dir = tempfile::tempdir().unwrap();
+        let keys = dir.path().join("keys.txt");
+        let seeds: Vec<KeyEntry> = (41..45)
+            .map(|i| KeyEntry::from_seed(KeyScheme::Sr25519, sp_core::blake2_256(&[i])))
+            .collect();
+        wallet::write_wallets_to_file(keys.to_str().unwrap(), &seeds, None).unwrap();
+
+        let mut app = App::new(keys.to_str().unwrap());
+        app.mode = AppMode::Unlocked;
+        app.load_seeds().unwrap();
+        // Space marks and moves on, so this marks the first and the third wallet.
+        app.on_key_event(press(KeyCode::Char(' ')));
+        app.on_key_event(press(KeyCode::Down));
+        app.on_key_event(press(KeyCode::Char(' ')));
+        assert_eq!(app.marked.len(), 2);
+        app.on_key_event(press(KeyCode::Char('d')));
+        app.on_key_event(press(KeyCode::Char('y')));
+
+        let on_disk = |keys: &std::path::Path| {
+            wallet::load_wallets_from_file(keys.to_str().unwrap(), None)
+                .unwrap()
+                .seeds
+        };
+        assert_eq!(on_disk(&keys), vec![seeds[1].clone(), seeds[3].clone()]);
+        assert!(app.marked.is_empty());
+
+        app.undo_last();
+        assert_eq!(on_disk(&keys), seeds);
+
+        app.on_key_event(press(KeyCode::Char('*')));
+        assert_eq!(app.marked.len(), 4);
+        app.on_key_event(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL));
+        assert!(app.marked.is_empty());
+    }
+
+    #[test]
+    fn ongoing_progress_sweeps_back_and_forth() {
+        let at = |steps| Progress::sweep(10, Progress::SWEEP_STEP * steps);
+        assert_eq!(at(0), (0, 2));
+        assert_eq!(at(3), (3, 5));
+        assert_eq!(at(8), (8, 10));
+        assert_eq!(at(11), (5, 7));
+        assert_eq!(at(16), (0, 2));
+        assert_eq!(Progress::sweep(3, Progress::SWEEP_STEP * 3), (1, 2));
+        assert_eq!(Progress::sweep(0, Duration::from_secs(9)), (0, 0));
+    }
+
+    #[test]
+    fn rotated_wallets_keep_their_label_and_place() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let dir = tempfile::tempdir().unwrap();
+        let keys = dir.path().join("keys.txt");
+        let mut seeds = vec![
+            KeyEntry::from_seed(KeyScheme::Sr25519, sp_core::blake2_256(&[31])),
+            KeyEntry::from_seed(KeyScheme::Ed25519, sp_core::blake2_256(&[32])),
+            KeyEntry::from_seed(KeyScheme::Sr25519, sp_core::blake2_256(&[33])),
+        ];
+        seeds[1].label = Some("Savings".to_string());
+        let mut vault = Vault::new("secret");
+        wallet::write_wallets_to_file(keys.to_str().unwrap(), &seeds, Some(&mut vault)).unwrap();
+
+        let mut app = App::new(keys.to_str().unwrap());
+        app.vault = Some(vault);
+        app.mode = AppMode::Unlocked;
+        app.load_seeds().unwrap();
+        app.select(1);
+        app.on_key_event(press(KeyCode::Char('O')));
+        app.on_key_event(press(KeyCode::Char('y')));
+
+        let loaded =
+            wallet::load_wallets_from_file(keys.to_str().unwrap(), Some(&mut Vault::new("secret")))
+                .unwrap()
+                .seeds;
+        assert_eq!(loaded.len(), 3);
+        assert_eq!((&loaded[0], &loaded[2]), (&seeds[0], &seeds[2]));
+        assert_ne!(loaded[1].account_id(), seeds[1].account_id());
+        assert_eq!(loaded[1].scheme, KeyScheme::Ed25519);
+        assert_eq!(loaded[1].label.as_deref(), Some("Savings"));
+
+        let retired = wallet::retired_keys_path(keys.to_str().unwrap());
+        assert_eq!(
+            wallet::load_wallets_from_file(&retired, Some(&mut Vault::new("secret")))
+                .unwrap()
+                .seeds,
+            vec![seeds[1].clone()]
+        );
+    }
+
+    #[test]
+    fn other_keys_files_are_merged_without_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (keys, other) = (dir.path().join("keys.txt"), dir.path().join("other.txt"));
+        let seeds = vec![
+            KeyEntry::from_seed(KeyScheme::Sr25519, sp_core::blake2_256(&[23])),
+            KeyEntry::from_seed(KeyScheme::Ed25519, sp_core::blake2_256(&[24])),
+        ];
+        wallet::write_wallets_to_file(keys.to_str().unwrap(), &seeds[..1], None).unwrap();
+        let mut vault = Vault::new("secret");
+        wallet::write_wallets_to_file(other.to_str().unwrap(), &seeds, Some(&mut vault)).unwrap();
+
+        let mut app = App::new(keys.to_str().unwrap());
+        app.load_seeds().unwrap();
+        let other = other.to_str().unwrap();
+        app.open_keys_file_to_merge(other).unwrap();
+        assert!(matches!(
+            app.input.take().unwrap().kind,
+            InputKind::MergeKeysFilePassword { .. }
+        ));
+        assert_eq!(
+            app.merge_keys_file(other, Some(&mut Vault::new("wrong"))),
+            Err("Wrong password".to_string())
+        );
+        app.merge_keys_file(other, Some(&mut Vault::new("secret")))
+            .unwrap();
+
+        assert_eq!(
+            wallet::load_wallets_from_file(keys.to_str().unwrap(), None)
+                .unwrap()
+                .seeds
+                .len(),
+            2
+        );
+        assert_eq!(
+            app.status.as_ref().unwrap().0,
+            format!("Added 1 new wallet from {}, skipped 1 already here", other)
+        );
+    }
+
+    #[test]
+    fn contacts_are_picked_as_transfer_destinations() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = App::new(dir.path().join("keys.txt").to_str().unwrap());
+        app.address_book_path = Some(dir.path().join("book.txt"));
+        app.mode = AppMode::Unlocked;
+        app.set_seeds(vec![KeyEntry::from_seed(KeyScheme::Sr25519, [25u8; 32])]);
+        let bob = KeyEntry::from_seed(KeyScheme::Sr25519, [26u8; 32]).address(app.ss58_format);
+
+        app.on_key_event(press(KeyCode::Char('K')));
+        app.on_key_event(press(KeyCode::Char('a')));
+        for c in "Bob".chars() {
+            app.on_key_event(press(KeyCode::Char(c)));
+        }
+        app.on_key_event(press(KeyCode::Enter));
+        for c in "5Grwva".chars() {
+            app.on_key_event(press(KeyCode::Char(c)));
+        }
+        app.on_key_event(press(KeyCode::Enter));
+        assert!(app.input.as_ref().unwrap().error.is_some());
+        app.input.as_mut().unwrap().value = Zeroizing::new(bob.clone());
+        app.on_key_event(press(KeyCode::Enter));
+        assert!(app.input.is_none());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("book.txt")).unwrap(),
+            format!("{}\tBob\n", bob)
+        );
+        app.on_key_event(press(KeyCode::Esc));
+        assert!(app.book_view.is_none());
+
+        app.on_key_event(press(KeyCode::Char('t')));
+        app.on_key_event(press(KeyCode::Char('5')));
+        app.on_key_event(press(KeyCode::Tab));
+        assert!(app.input.is_none());
+        app.on_key_event(press(KeyCode::Esc));
+        assert_eq!(app.input.as_ref().unwrap().value.as_str(), "5");
+
+        app.on_key_event(press(KeyCode::Tab));
+        app.on_key_event(press(KeyCode::Enter));
+        let input = app.input.as_ref().unwrap();
+        assert_eq!(input.kind, InputKind::TransferDestination);
+        assert_eq!(input.value.as_str(), bob);
+        assert!(input.preview.is_some());
+    }
+
+    #[test]
+    fn exported_csv_has_secrets_only_on_request() {
+        let entries = vec![
+            KeyEntry {
+                label: Some("Savings, old".to_string()),
+                ..KeyEntry::from_seed(KeyScheme::Sr25519, [28u8; 32])
+            },
+            KeyEntry::watch_only(AccountId32::new([29u8; 32]), NETWORKS[1].ss58_format()),
+        ];
+        let format = NETWORKS[0].ss58_format();
+        let wallets: Vec<WalletKeys> = entries
+            .iter()
+            .map(|entry| WalletKeys::from_entry(entry, format))
+            .collect();
+
+        let csv = addresses_csv(&entries, &wallets, "Substrate", false);
+        assert_eq!(
+            csv,
+            format!(
+                "index,label,network,address\n1,\"Savings, old\",Substrate,{}\n2,,Substrate,{}\n",
+                wallets[0].address, wallets[1].address
+            )
+        );
+        let secret = wallets[0].private_key().unwrap();
+        assert!(!csv.contains(secret));
+
+        let csv = addresses_csv(&entries, &wallets, "Substrate", true);
+        assert!(csv.starts_with("index,label,network,address,secret\n"));
+        assert!(csv.contains(&format!("{},{}\n", wallets[0].address, secret)));
+        assert!(csv.ends_with(&format!("{},\n", wallets[1].address)));
+    }
+
+    #[test]
+    fn unreachable_nodes_can_be_retried() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut app = App::new("unused-keys.txt");
+        app.mode = AppMode::Unlocked;
+        app.set_seeds(vec![KeyEntry::from_seed(KeyScheme::Sr25519, [30u8; 32])]);
+
+        // Nothing listens on port 1, so connecting fails right away.
+        app.fetch_balances("http://127.0.0.1:1").unwrap();
+        assert!(app.list_is_covered());
+        let wait_for_error = |app: &mut App| {
+            let deadline = Instant::now() + rpc::CONNECT_TIMEOUT * 2;
+            while app.connecting.as_ref().unwrap().error.is_none() {
+                assert!(Instant::now() < deadline, "connecting did not fail");
+                std::thread::sleep(Duration::from_millis(10));
+                app.check_connection();
+            }
+        };
+        wait_for_error(&mut app);
+        assert!(app.balance_updates.is_none());
+        assert_eq!(app.status.as_ref().unwrap().1, StatusKind::Error);
+
+        app.on_key_event(press(KeyCode::Char('r')));
+        assert!(app.connecting.as_ref().unwrap().error.is_none());
+        wait_for_error(&mut app);
+        app.on_key_event(press(KeyCode::Esc));
+        assert!(app.connecting.is_none());
+
+        assert_eq!(spinner(Duration::ZERO), SPINNER_FRAMES[0]);
+        assert_eq!(spinner(SPINNER_STEP * 11), SPINNER_FRAMES[1]);
+    }
+
+    #[test]
+    fn the_last_selected_wallet_is_selected_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = dir.path().join("keys.txt");
+        let seeds: Vec<KeyEntry> = (32u8..35)
+            .map(|byte| KeyEntry::from_seed(KeyScheme::Sr25519, [byte; 32]))
+            .collect();
+        wallet::write_wallets_to_file(keys.to_str().unwrap(), &seeds, None).unwrap();
+        let state = dir.path().join("state.txt");
+        let open = || {
+            let mut app = App::new(keys.to_str().unwrap()).with_state_file(Some(state.clone()));
+            app.submit_password();
+            assert_eq!(app.mode, AppMode::Unlocked);
+            app
+        };
+
+        let mut app = open();
+        assert_eq!(app.selected, 0);
+        app.selected = 2;
+        app.quit();
+        assert_eq!(open().selected, 2);
+
+        // Once that wallet is gone, the first one is selected instead.
+        wallet::write_wallets_to_file(keys.to_str().unwrap(), &seeds[..2], None).unwrap();
+        assert_eq!(open().selected, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exposed_keys_files_can_be_made_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let dir = tempfile::tempdir().unwrap();
+        let keys = dir.path().join("keys.txt");
+        let seeds = [KeyEntry::from_seed(KeyScheme::Sr25519, [36u8; 32])];
+        wallet::write_wallets_to_file(keys.to_str().unwrap(), &seeds, None).unwrap();
+        let mode = || std::fs::metadata(&keys).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(), 0o600);
+
+        let mut app = App::new(keys.to_str().unwrap());
+        app.submit_password();
+        assert!(app.confirm.is_none());
+
+        std::fs::set_permissions(&keys, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let mut app = App::new(keys.to_str().unwrap());
+        app.submit_password();
+        assert_eq!(
+            app.confirm.as_ref().map(|confirm| &confirm.action),
+            Some(&ConfirmAction::RestrictPermissions)
+        );
+        assert!(matches!(app.status, Some((_, StatusKind::Warning))));
+        app.on_key_event(press(KeyCode::Char('y')));
+        assert_eq!(mode(), 0o600);
+        assert!(matches!(app.status, Some((_, StatusKind::Success))));
+    }
+
+    #[test]
+    fn timestamps_are_shown_as_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00");
+        assert_eq!(format_timestamp(1_792_030_390), "2026-10-15 02:13");
+        assert_eq!(format_timestamp(4_107_542_399), "2100-02-28 23:59");
+    }
+
+    #[test]
+    fn long_labels_are_cut_short_to_keep_the_columns_aligned() {
+        assert_eq!(column("Savings", 10), "Savings   ");
+        assert_eq!(column("Cold storage", 12), "Cold storage");
+        assert_eq!(column("Cold storage vault", 12), "Cold storag…");
+        assert_eq!(column("Épargne à long terme", 8).chars().count(), 8);
+    }
+
+    #[test]
+    fn enter_opens_the_details_of_the_selected_wallet() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut app = App::new("unused-keys.txt");
+        app.mode = AppMode::Unlocked;
+        app.set_seeds(vec![
+            KeyEntry::from_seed(KeyScheme::Sr25519, [16u8; 32]),
+            KeyEntry::from_seed(KeyScheme::Ed25519, [17u8; 32]),
+        ]);
+
+        app.on_key_event(press(KeyCode::Enter));
+        assert_eq!(app.mode, AppMode::Details);
+
+        // Only actions that make sense for the one wallet work from there.
+        app.on_key_event(press(KeyCode::Char('/')));
+        assert!(app.input.is_none());
+        app.on_key_event(press(KeyCode::Down));
+        assert_eq!(app.selected, 1);
+
+        app.on_key_event(press(KeyCode::Esc));
+        assert_eq!(app.mode, AppMode::Unlocked);
+        assert_eq!(app.selected, 1);
+    }
+
+    #[test]
+    fn copying_a_secret_takes_confirmation_and_the_password() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut app = App::new("unused-keys.txt");
+        app.mode = AppMode::Unlocked;
+        app.set_seeds(vec![KeyEntry::from_seed(KeyScheme::Sr25519, [27u8; 32])]);
+
+        // Without a password there is nothing to enter again.
+        app.on_key_event(press(KeyCode::Char('C')));
+        assert!(app.confirm.is_none());
+        assert_eq!(app.status.as_ref().unwrap().1, StatusKind::Error);
+
+        app.vault = Some(Vault::new("secret"));
+        app.on_key_event(press(KeyCode::Char('C')));
+        app.on_key_event(press(KeyCode::Char('y')));
+        assert!(matches!(
+            app.input.as_ref().unwrap().kind,
+            InputKind::RevealPassword { copy: true, .. }
+        ));
+        for c in "wrong".chars() {
+            app.on_key_event(press(KeyCode::Char(c)));
+        }
+        app.on_key_event(press(KeyCode::Enter));
+        assert_eq!(
+            app.input.as_ref().unwrap().error.as_deref(),
+            Some("Wrong password")
+        );
+        assert!(app.copied_secret.is_none());
+
+        let address = app.wallets[0].address.clone();
+        assert_eq!(
+            app.secret_of(0, &address, false, "nothing was copied")
+                .unwrap()
+                .as_str(),
+            app.wallets[0].private_key().unwrap()
+        );
+        assert!(app
+            .secret_of(1, &address, false, "nothing was copied")
+            .is_err());
+    }
+
+    #[test]
+    fn watched_labels_with_tabs_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("addresses.tsv");
+        let address = |seed| {
+            KeyEntry::from_seed(KeyScheme::Sr25519, [seed; 32])
+                .account_id()
+                .to_ss58check()
+        };
+        let mut app = App::new("unused-keys.txt").with_dry_run(true);
+        app.mode = AppMode::Unlocked;
+
+        std::fs::write(
+            &path,
+            format!("{}\tSpending\n{}\tSavings\tnote\n", address(1), address(2)),
+        )
+        .unwrap();
+        let error = app.watch_addresses(path.to_str().unwrap()).unwrap_err();
+        assert!(error.starts_with("Line 2: "), "{}", error);
+        assert!(app.seeds.is_empty());
+
+        std::fs::write(&path, format!("{}\tSavings\n", address(2))).unwrap();
+        app.watch_addresses(path.to_str().unwrap()).unwrap();
+        assert_eq!(app.seeds[0].label.as_deref(), Some("Savings"));
+    }
+
+    #[test]
+    fn key_hints_follow_the_keymap() {
+        let overrides =
+            BTreeMap::from([("generate".to_string(), keymap::Keys::One("g".to_string()))]);
+        let mut app = App::new("unused-keys.txt");
+        app.key_bindings = keymap::key_bindings(None, &overrides).unwrap();
+        app.key_bindings
+            .retain(|binding| binding.action != Action::Help);
+
+        assert_eq!(app.key_label(Action::Generate, true), "'g'");
+        assert_eq!(app.key_label(Action::Generate, false), "g");
+        assert_eq!(app.key_label(Action::Help, true), "no key");
+    }
+
+    #[test]
+    fn revealing_a_phrase_takes_confirmation_and_the_password() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let phrase = ["abandon"; 11].join(" ") + " about";
+        let mut app = App::new("unused-keys.txt");
+        app.mode = AppMode::Unlocked;
+        app.set_seeds(vec![
+            KeyEntry::from_phrase(KeyScheme::Sr25519, &phrase).unwrap()
+        ]);
+        app.vault = Some(Vault::new("secret"));
+
+        app.on_key_event(press(KeyCode::Char('w')));
+        assert!(app.revealed.is_none());
+        app.on_key_event(press(KeyCode::Char('y')));
+        assert!(matches!(
+            app.input.as_ref().unwrap().kind,
+            InputKind::RevealPassword {
+                phrase: true,
+                copy: false,
+                ..
+            }
+        ));
+        for c in "secret".chars() {
+            app.on_key_event(press(KeyCode::Char(c)));
+        }
+        app.on_key_event(press(KeyCode::Enter));
+        let revealed = app.revealed.as_ref().unwrap();
+        assert!(revealed.phrase);
+        assert_eq!(revealed.secret.as_str(), phrase);
+
+        // Any key hides it again.
+        app.on_key_event(press(KeyCode::Char('j')));
+        assert!(app.revealed.is_none());
+    }
+
+    #[test]
+    fn inspecting_a_wallet_takes_confirmation_and_the_password() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut app = App::new("unused-keys.txt");
+        app.mode = AppMode::Unlocked;
+        app.set_seeds(vec![KeyEntry::from_seed(KeyScheme::Ed25519, [27u8; 32])]);
+        app.vault = Some(Vault::new("secret"));
+
+        app.on_key_event(press(KeyCode::Char('X')));
+        assert!(app.popup.is_none());
+        app.on_key_event(press(KeyCode::Char('y')));
+        for c in "secret".chars() {
+            app.on_key_event(press(KeyCode::Char(c)));
+        }
+        app.on_key_event(press(KeyCode::Enter));
+
+        let popup = app.popup.as_ref().unwrap();
+        assert!(popup.secret);
+        assert_eq!(
+            popup.copy.as_deref(),
+            Some(app.seeds[0].inspect(app.ss58_format).unwrap().as_str())
+        );
+        assert_eq!(popup.lines.len(), 7);
+    }
+
+    #[test]
+    fn payloads_are_signed_offline_and_shown_as_a_qr_code() {
+        use sp_core::{crypto::Pair as _, sr25519};
+
+        let mut app = App::new("unused-keys.txt");
+        app.mode = AppMode::Unlocked;
+        app.set_seeds(vec![KeyEntry::from_seed(KeyScheme::Sr25519, [29u8; 32])]);
+        let public = sr25519::Pair::from_seed(&[29u8; 32]).public();
+
+        assert_eq!(
+            app.sign_payload("0xzz"),
+            Err("Bad hex: Invalid character 'z' at position 0".to_string())
+        );
+        assert!(app.sign_payload("").is_err());
+
+        for payload in [vec![4u8; 40], vec![5u8; 300]] {
+            app.sign_payload(&format!("0x{}", hex::encode(&payload)))
+                .unwrap();
+            // Nothing can be made of these bytes, so signing them is signing blind.
+            let confirm = app.confirm.take().unwrap();
+            assert!(confirm.prompt.starts_with("WARNING"));
+            app.run_confirmed(confirm.action);
+            let qr_code = app.qr_code.take().unwrap();
+            assert_eq!(qr_code.title, "Signature");
+            let signature = hex::decode(qr_code.text.strip_prefix("0x").unwrap()).unwrap();
+            assert_eq!(signature[0], 1);
+            let signature = sr25519::Signature::try_from(&signature[1..]).unwrap();
+            let signed = match payload.len() > 256 {
+                true => sp_core::blake2_256(&payload).to_vec(),
+                false => payload,
+            };
+            assert!(sr25519::Pair::verify(&signature, &signed, &public));
+        }
+        assert!(app.connecting.is_none());
+    }
+
+    #[test]
+    fn locking_wipes_the_wallets_until_the_password_is_entered() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let dir = tempfile::tempdir().unwrap();
+        let keys = dir.path().join("keys.txt");
+        let seeds = vec![
+            KeyEntry::from_seed(KeyScheme::Sr25519, [37u8; 32]),
+            KeyEntry::from_seed(KeyScheme::Ed25519, [38u8; 32]),
+        ];
+        let mut vault = Vault::new("secret");
+        wallet::write_wallets_to_file(keys.to_str().unwrap(), &seeds, Some(&mut vault)).unwrap();
+
+        let mut app = App::new(keys.to_str().unwrap());
+        let unlock = |app: &mut App| {
+            for c in "secret".chars() {
+                app.on_key_event(press(KeyCode::Char(c)));
+            }
+            app.on_key_event(press(KeyCode::Enter));
+        };
+        unlock(&mut app);
+        assert_eq!(app.mode, AppMode::Unlocked);
+        assert_eq!(app.seeds.len(), 2);
+
+        app.on_key_event(KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL));
+        assert_eq!(app.mode, AppMode::Locked);
+        assert!(app.seeds.is_empty() && app.wallets.is_empty());
+        assert!(app.vault.is_none());
+
+        unlock(&mut app);
+        assert_eq!(app.mode, AppMode::Unlocked);
+        assert_eq!(app.seeds, seeds);
+    }
+
+    #[test]
+    fn idle_keys_files_lock_themselves() {
+        let config = Config {
+            auto_lock_secs: Some(60),
+            ..Config::default()
+        };
+        let mut app = App::new("unused-keys.txt").with_config(&config);
+        app.mode = AppMode::Unlocked;
+        app.set_seeds(vec![KeyEntry::from_seed(KeyScheme::Sr25519, [39u8; 32])]);
+        app.last_input = Instant::now() - Duration::from_secs(61);
+
+        // Without a password, there is nothing to unlock it with again.
+        app.check_idle();
+        assert_eq!(app.mode, AppMode::Unlocked);
+
+        app.vault = Some(Vault::new("secret"));
+        app.last_input = Instant::now();
+        app.check_idle();
+        assert_eq!(app.mode, AppMode::Unlocked);
+
+        app.last_input = Instant::now() - Duration::from_secs(61);
+        app.check_idle();
+        assert_eq!(app.mode, AppMode::Locked);
+        assert!(app.seeds.is_empty());
+    }
+
+    #[test]
+    fn protected_wallets_ask_for_their_passphrase_to_sign() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let type_in = |app: &mut App, text: &str| {
+            for c in text.chars() {
+                app.on_key_event(press(KeyCode::Char(c)));
+            }
+            app.on_key_event(press(KeyCode::Enter));
+        };
+        let mut app = App::new("unused-keys.txt").with_dry_run(true);
+        app.mode = AppMode::Unlocked;
+
+        app.on_key_event(press(KeyCode::Char('G')));
+        type_in(&mut app, "hunter2");
+        type_in(&mut app, "hunter3");
+        let input = app.input.as_ref().unwrap();
+        assert_eq!(input.kind, InputKind::NewPassphrase);
+        assert_eq!(input.error.as_deref(), Some("Passphrases do not match"));
+        type_in(&mut app, "hunter2");
+        type_in(&mut app, "hunter2");
+        assert!(app.input.is_none());
+        assert!(app.seeds[0].is_protected());
+
+        app.on_key_event(press(KeyCode::Char('s')));
+        assert!(app.confirm.is_none());
+        assert_eq!(app.status.as_ref().unwrap().1, StatusKind::Error);
+
+        app.on_key_event(press(KeyCode::Char('S')));
+        type_in(&mut app, "hello");
+        assert!(matches!(
+            app.input.as_ref().unwrap().kind,
+            InputKind::Passphrase { .. }
+        ));
+        type_in(&mut app, "wrong");
+        assert_eq!(
+            app.input.as_ref().unwrap().error.as_deref(),
+            Some("Wrong passphrase")
+        );
+        assert!(app.popup.is_none());
+        while app
+            .input
+            .as_ref()
+            .is_some_and(|input| !input.value.is_empty())
+        {
+            app.on_key_event(press(KeyCode::Backspace));
+        }
+        type_in(&mut app, "hunter2");
+        assert!(app.input.is_none());
+        let signature = app.popup.as_ref().unwrap().copy.clone().unwrap();
+
+        let (account, _) = wallet::parse_address(&app.wallets[0].address).unwrap();
+        let signature = hex::decode(signature.trim_start_matches("0x")).unwrap();
+        assert!(wallet::verify_signature(&account, b"hello", &signature).is_some());
+    }
+
+    #[test]
+    fn switching_profiles_loads_the_other_keys_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (first, second) = (dir.path().join("first.txt"), dir.path().join("second.txt"));
+        let first_seeds = vec![KeyEntry::from_seed(KeyScheme::Sr25519, [9u8; 32])];
+        let second_seeds = vec![
+            KeyEntry::from_seed(KeyScheme::Ed25519, [10u8; 32]),
+            KeyEntry::from_seed(KeyScheme::Ecdsa, [11u8; 32]),
+        ];
+        wallet::write_wallets_to_file(first.to_str().unwrap(), &first_seeds, None).unwrap();
+        wallet::write_wallets_to_file(second.to_str().unwrap(), &second_seeds, None).unwrap();
+
+        let mut app = App::new(first.to_str().unwrap());
+        app.set_profiles(&BTreeMap::from([(
+            "second".to_string(),
+            second.to_str().unwrap().to_string(),
+        )]));
+        let names: Vec<&str> = app.profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["default", "second"]);
+        app.load_seeds().unwrap();
+
+        app.cycle_profile();
+        assert_eq!(app.keys_path, second.to_str().unwrap());
+        assert_eq!(app.seeds, second_seeds);
+        assert_eq!(app.mode, AppMode::Unlocked);
+        assert!(app.watcher.is_some());
+
+        app.cycle_profile();
+        assert_eq!(app.seeds, first_seeds);
+        assert_eq!(app.profile, 0);
+    }
+
+    #[test]
+    fn every_key_is_bound_once() {
+        let bindings = keymap::DEFAULT_KEY_BINDINGS;
+        for (i, binding) in bindings.iter().enumerate() {
+            assert!(
+                !bindings[..i]
+                    .iter()
+                    .any(|other| other.key == binding.key && other.ctrl == binding.ctrl),
+                "{} is bound twice",
+                binding.label()
+            );
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Password-protected archives of a whole set of wallets, for backups and for moving
+//! wallets between machines.
+//!
+//! An archive is a JSON document listing every wallet, sealed the same way as an
+//! encrypted keys file (Argon2id and XChaCha20-Poly1305, see [`crate::vault`]):
+//!
+//! ```json
+//! {"version": 1, "wallets": [{"entry": "sr25519:0x…//0", "label": "Savings"}]}
+//! ```
+//!
+//! where each `entry` is the wallet's keys file line without its label.
+
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::vault::{self, Vault};
+use crate::wallet::{check_label, KeyEntry};
+
+const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    version: u32,
+    wallets: Vec<ArchivedWallet>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedWallet {
+    entry: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+impl Drop for ArchivedWallet {
+    fn drop(&mut self) {
+        self.entry.zeroize();
+    }
+}
+
+/// Seals all of `entries`, labels included, into an archive encrypted with `password`.
+pub fn export(entries: &[KeyEntry], password: &str) -> Result<Vec<u8>, Error> {
+    let archive = Archive {
+        version: VERSION,
+        wallets: entries
+            .iter()
+            .map(|entry| ArchivedWallet {
+                entry: KeyEntry {
+                    label: None,
+                    ..entry.clone()
+                }
+                .to_line(),
+                label: entry.label.clone(),
+            })
+            .collect(),
+    };
+    let json = Zeroizing::new(serde_json::to_vec(&archive).map_err(Error::other)?);
+
+    Vault::new(password).seal(&json)
+}
+
+/// Opens an archive made by [`export`], returning its wallets in the order they were
+/// exported.
+pub fn import(data: &[u8], password: &str) -> Result<Vec<KeyEntry>, Error> {
+    if !vault::is_encrypted(data) {
+        return Err(Error::new(ErrorKind::InvalidData, "Not a wallet archive"));
+    }
+    let json = Vault::new(password)
+        .open(data)
+        .map_err(|e| match e.kind() {
+            ErrorKind::PermissionDenied => Error::new(
+                ErrorKind::PermissionDenied,
+                "Wrong password or corrupted archive",
+            ),
+            _ => e,
+        })?;
+
+    let archive: Archive = serde_json::from_slice(&json)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Invalid archive: {}", e)))?;
+    if archive.version != VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unsupported archive version: {}", archive.version),
+        ));
+    }
+
+    archive
+        .wallets
+        .iter()
+        .enumerate()
+        .map(|(i, wallet)| {
+            let invalid = |e: &dyn std::fmt::Display| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Wallet {} of the archive is invalid: {}", i + 1, e),
+                )
+            };
+            // A label spanning lines would break the keys file it is restored into.
+            if let Some(label) = &wallet.label {
+                check_label(label).map_err(|e| invalid(&e))?;
+            }
+            let entry = KeyEntry::parse(&wallet.entry).map_err(|e| invalid(&e))?;
+            Ok(KeyEntry {
+                label: wallet.label.clone(),
+                ..entry
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::NETWORKS;
+    use crate::wallet::KeyScheme;
+    use sp_core::crypto::AccountId32;
+
+    #[test]
+    fn archives_round_trip_every_kind_of_wallet() {
+        let entries = vec![
+            KeyEntry::from_seed(KeyScheme::Sr25519, [1u8; 32]),
+            KeyEntry {
+                label: Some("Savings".to_string()),
+                ..KeyEntry::from_seed(KeyScheme::Ed25519, [2u8; 32])
+                    .with_path(Some("//0"))
+                    .unwrap()
+            },
+            KeyEntry::from_phrase(
+                KeyScheme::Ecdsa,
+                "bottom drive obey lake curtain smoke basket hold race lonely fit walk",
+            )
+            .unwrap(),
+            KeyEntry {
+                label: Some("Exchange".to_string()),
+                ..KeyEntry::watch_only(AccountId32::new([3u8; 32]), NETWORKS[1].ss58_format())
+            },
+        ];
+
+        let data = export(&entries, "secret").unwrap();
+        assert!(!data.windows(7).any(|window| window == b"sr25519"));
+        assert_eq!(import(&data, "secret").unwrap(), entries);
+        assert_eq!(
+            import(&data, "wrong").unwrap_err().kind(),
+            ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            import(b"sr25519:0x00", "secret").unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,445 @@
+//! Optional settings file overriding the built-in defaults.
+//!
+//! The file is TOML, read from `$XDG_CONFIG_HOME/substrate-wallet/config.toml` (or
+//! `~/.config/substrate-wallet/config.toml`) unless another path is given:
+//!
+//! ```toml
+//! keys_path = "/home/me/wallets/keys.txt"
+//! network = "polkadot"
+//! # Or, for a network without a preset, its address prefix instead:
+//! # ss58_prefix = 7
+//! rpc_url = "wss://rpc.polkadot.io"
+//! poll_interval_ms = 250
+//! reload_debounce_ms = 300
+//! theme = "light"
+//! log_file = "/tmp/wallet.log"
+//! address_book = "/home/me/wallets/contacts.txt"
+//! keymap = "vim"
+//! mnemonic_words = 12
+//! clipboard_clear_secs = 30
+//! auto_lock_secs = 300
+//!
+//! [colors]
+//! error = "magenta"
+//! address = "#00afff"
+//!
+//! [keys]
+//! generate = "g"
+//! quit = ["q", "ctrl+q"]
+//!
+//! [profiles]
+//! personal = "/home/me/wallets/keys.txt"
+//! work = "/home/me/work/keys.txt"
+//!
+//! [uri_schemes]
+//! polkadot = "polkadot"
+//! substrate = "substrate"
+//! ```
+//!
+//! See [`crate::keymap`] for the action names and keys `[keys]` accepts.
+//!
+//! Every setting is optional. A file that cannot be read or parsed is reported and
+//! ignored as a whole, so a typo never keeps the wallet from starting.
+//!
+//! The wallet writes `mnemonic_words` itself, to remember the mnemonic length last chosen;
+//! see [`save_setting`].
+
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use sp_core::crypto::Ss58AddressFormat;
+
+use crate::app::{Network, NETWORKS, POLL_INTERVAL_RANGE_MS};
+use crate::keymap::{self, Keys};
+use crate::theme::Theme;
+use crate::wallet::MNEMONIC_WORDS;
+
+/// Largest SS58 prefix, as addresses have 14 bits for it.
+const MAX_SS58_PREFIX: u16 = 16_383;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Keys file to manage, unless one is given on the command line
+    pub keys_path: Option<String>,
+    /// Name of the network preset whose address format is selected at startup
+    pub network: Option<String>,
+    /// SS58 prefix of the address format selected at startup, instead of a preset's; see
+    /// [`Config::ss58_format`]
+    pub ss58_prefix: Option<i64>,
+    /// Node RPC endpoint balances and transfers use until another one is entered
+    pub rpc_url: Option<String>,
+    /// How long the UI waits for input before checking on background work, from 10 to
+    /// 1000 milliseconds
+    pub poll_interval_ms: Option<u64>,
+    /// How long the keys file has to stay unchanged before a change to it is reloaded
+    pub reload_debounce_ms: Option<u64>,
+    /// Name of the color theme preset, see [`Theme::PRESETS`]
+    pub theme: Option<String>,
+    /// Colors replacing those of the theme
+    pub colors: ColorOverrides,
+    /// File the diagnostic log is written to, unless one is given on the command line
+    pub log_file: Option<PathBuf>,
+    /// File the address book is kept in, instead of [`crate::address_book::default_path`]
+    pub address_book: Option<PathBuf>,
+    /// Name of the keymap preset, see [`keymap::PRESETS`]
+    pub keymap: Option<String>,
+    /// Keys replacing those the keymap binds to an action, by action name
+    pub keys: BTreeMap<String, Keys>,
+    /// Keys files that can be switched between while running, by profile name
+    pub profiles: BTreeMap<String, String>,
+    /// Scheme of the URIs addresses are copied as, by network preset name
+    pub uri_schemes: BTreeMap<String, String>,
+    /// Number of words of the mnemonic phrases new wallets get, or none for raw seeds
+    pub mnemonic_words: Option<usize>,
+    /// How many seconds a copied secret seed stays on the clipboard before it is cleared,
+    /// or 0 to leave it there
+    pub clipboard_clear_secs: Option<u64>,
+    /// How many seconds without a key press or click lock an encrypted keys file again,
+    /// or 0 to never lock it by itself
+    pub auto_lock_secs: Option<u64>,
+    /// Where the settings were read from, and where changed ones are saved, when that
+    /// file exists and is valid or is to be created at the default path
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+/// Colors of single roles of a [`Theme`], by name (`"red"`, `"lightblue"`), hex
+/// (`"#ff8700"`) or 256-color index (`"208"`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorOverrides {
+    pub label: Option<Color>,
+    pub address: Option<Color>,
+    pub highlight: Option<Color>,
+    pub detail: Option<Color>,
+    pub accent: Option<Color>,
+    pub success: Option<Color>,
+    pub warning: Option<Color>,
+    pub error: Option<Color>,
+    pub hint: Option<Color>,
+}
+
+impl ColorOverrides {
+    pub fn apply(&self, theme: &mut Theme) {
+        let overrides = [
+            (self.label, &mut theme.label),
+            (self.address, &mut theme.address),
+            (self.highlight, &mut theme.highlight),
+            (self.detail, &mut theme.detail),
+            (self.accent, &mut theme.accent),
+            (self.success, &mut theme.success),
+            (self.warning, &mut theme.warning),
+            (self.error, &mut theme.error),
+            (self.hint, &mut theme.hint),
+        ];
+        for (color, role) in overrides {
+            if let Some(color) = color {
+                *role = color;
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file at `path`, or at [`default_path`] when none is given.
+    ///
+    /// Returns the defaults when the file does not exist, and also, together with a
+    /// warning, when it is malformed. Only a missing file at the default path goes
+    /// unmentioned.
+    pub fn load(path: Option<&Path>) -> (Self, Option<String>) {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return (Self::default(), None),
+            },
+        };
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !explicit => {
+                let config = Self {
+                    path: Some(path),
+                    ..Self::default()
+                };
+                return (config, None);
+            }
+            Err(e) => {
+                let warning = format!("Ignoring config file {}: {}", path.display(), e);
+                return (Self::default(), Some(warning));
+            }
+        };
+
+        match Self::parse(&contents) {
+            Ok(config) => (
+                Self {
+                    path: Some(path),
+                    ..config
+                },
+                None,
+            ),
+            Err(e) => {
+                let warning = format!("Ignoring config file {}: {}", path.display(), e);
+                (Self::default(), Some(warning))
+            }
+        }
+    }
+
+    /// The address format the config file selects, by network name or by prefix, if
+    /// either is set.
+    pub fn ss58_format(&self) -> Option<Ss58AddressFormat> {
+        if let Some(prefix) = self.ss58_prefix {
+            return u16::try_from(prefix).ok().map(Ss58AddressFormat::custom);
+        }
+        let name = self.network.as_deref()?;
+        NETWORKS
+            .iter()
+            .find(|network| network.name.eq_ignore_ascii_case(name))
+            .map(Network::ss58_format)
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(contents).map_err(|e| e.message().to_string())?;
+
+        if let Some(network) = &config.network {
+            if !NETWORKS
+                .iter()
+                .any(|preset| preset.name.eq_ignore_ascii_case(network))
+            {
+                let names: Vec<&str> = NETWORKS.iter().map(|preset| preset.name).collect();
+                return Err(format!(
+                    "Unknown network \"{}\", expected one of {}",
+                    network,
+                    names.join(", ")
+                ));
+            }
+        }
+
+        if let Some(prefix) = config.ss58_prefix {
+            if config.network.is_some() {
+                return Err("Set either network or ss58_prefix, not both".to_string());
+            }
+            // Addresses encode 14 bits of prefix; larger ones would silently wrap around.
+            let prefix = u16::try_from(prefix)
+                .ok()
+                .filter(|&prefix| prefix <= MAX_SS58_PREFIX)
+                .ok_or_else(|| {
+                    format!(
+                        "ss58_prefix must be from 0 to {}, not {}",
+                        MAX_SS58_PREFIX, prefix
+                    )
+                })?;
+            if Ss58AddressFormat::custom(prefix).is_reserved() {
+                return Err(format!("ss58_prefix {} is reserved", prefix));
+            }
+        }
+
+        if let Some(theme) = &config.theme {
+            if Theme::by_name(theme).is_none() {
+                let names: Vec<&str> = Theme::PRESETS.iter().map(|(name, _)| *name).collect();
+                return Err(format!(
+                    "Unknown theme \"{}\", expected one of {}",
+                    theme,
+                    names.join(", ")
+                ));
+            }
+        }
+
+        if let Some(interval) = config.poll_interval_ms {
+            if !POLL_INTERVAL_RANGE_MS.contains(&interval) {
+                return Err(format!(
+                    "poll_interval_ms must be from {} to {}, not {}",
+                    POLL_INTERVAL_RANGE_MS.start(),
+                    POLL_INTERVAL_RANGE_MS.end(),
+                    interval
+                ));
+            }
+        }
+
+        for (network, scheme) in &config.uri_schemes {
+            if !NETWORKS
+                .iter()
+                .any(|preset| preset.name.eq_ignore_ascii_case(network))
+            {
+                return Err(format!("Unknown network \"{}\" in [uri_schemes]", network));
+            }
+            // RFC 3986: a letter, then letters, digits, `+`, `-` and `.`.
+            let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+            if !valid {
+                return Err(format!("Invalid URI scheme \"{}\" for {}", scheme, network));
+            }
+        }
+
+        if let Some(words) = config.mnemonic_words {
+            if !MNEMONIC_WORDS.contains(&words) {
+                return Err(format!(
+                    "mnemonic_words must be one of {:?}, not {}",
+                    MNEMONIC_WORDS, words
+                ));
+            }
+        }
+
+        keymap::key_bindings(config.keymap.as_deref(), &config.keys)?;
+
+        Ok(config)
+    }
+}
+
+/// Sets the top-level setting `key` of the config file at `path` to `value`, or removes
+/// it when `value` is [`toml_edit::Item::None`]. Everything else in the file, comments
+/// included, stays as it is. The file is created when it does not exist.
+///
+/// Nothing is written if the file, before or after the change, is not a valid config.
+pub fn save_setting(path: &Path, key: &str, value: toml_edit::Item) -> Result<(), String> {
+    let fail = |e: &dyn std::fmt::Display| format!("Not saved to {}: {}", path.display(), e);
+
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(fail(&e)),
+    };
+    Config::parse(&contents).map_err(|e| fail(&e))?;
+
+    let mut document: toml_edit::DocumentMut = contents.parse().map_err(|e| fail(&e))?;
+    if value.is_none() {
+        document.remove(key);
+    } else {
+        document.insert(key, value);
+    }
+    let contents = document.to_string();
+    Config::parse(&contents).map_err(|e| fail(&e))?;
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| fail(&e))?;
+    }
+    std::fs::write(path, contents).map_err(|e| fail(&e))
+}
+
+/// Where the config file is looked for when no path is given.
+pub fn default_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(config_dir.join("substrate-wallet").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_optional_and_validated() {
+        let config = Config::parse("network = \"polkadot\"\npoll_interval_ms = 250\n").unwrap();
+        assert_eq!(config.network.as_deref(), Some("polkadot"));
+        assert_eq!(config.poll_interval_ms, Some(250));
+        assert!(config.keys_path.is_none());
+        assert!(config.log_file.is_none());
+        let config = Config::parse("log_file = \"/tmp/wallet.log\"").unwrap();
+        assert_eq!(config.log_file, Some(PathBuf::from("/tmp/wallet.log")));
+
+        assert!(Config::parse("").is_ok());
+        assert!(Config::parse("network = \"mars\"").is_err());
+        assert!(Config::parse("poll_interval_ms = 5").is_err());
+        assert!(Config::parse("poll_interval_ms = 5000").is_err());
+        assert!(Config::parse("keys = \"typo.txt\"").is_err());
+        assert!(Config::parse("mnemonic_words = 13").is_err());
+    }
+
+    #[test]
+    fn ss58_prefixes_must_be_encodable() {
+        let config = Config::parse("ss58_prefix = 7").unwrap();
+        assert_eq!(config.ss58_format(), Some(Ss58AddressFormat::custom(7)));
+        let config = Config::parse("network = \"kusama\"").unwrap();
+        assert_eq!(config.ss58_format(), Some(Ss58AddressFormat::custom(2)));
+        assert_eq!(Config::parse("").unwrap().ss58_format(), None);
+
+        assert!(Config::parse("ss58_prefix = 16383").is_ok());
+        for invalid in ["-1", "16384", "70000", "46"] {
+            assert!(Config::parse(&format!("ss58_prefix = {}", invalid)).is_err());
+        }
+        assert!(Config::parse("network = \"kusama\"\nss58_prefix = 2").is_err());
+    }
+
+    #[test]
+    fn saved_settings_keep_the_rest_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        save_setting(&path, "mnemonic_words", toml_edit::value(12)).unwrap();
+        assert_eq!(Config::load(Some(&path)).0.mnemonic_words, Some(12));
+
+        let contents = "# Mine\nnetwork = \"polkadot\"\n\n[keys]\nquit = \"q\"\n";
+        std::fs::write(&path, contents).unwrap();
+        save_setting(&path, "mnemonic_words", toml_edit::value(24)).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            saved.starts_with("# Mine\nnetwork = \"polkadot\"\n"),
+            "{}",
+            saved
+        );
+        let config = Config::parse(&saved).unwrap();
+        assert_eq!(config.mnemonic_words, Some(24));
+        assert_eq!(config.keys.len(), 1);
+
+        save_setting(&path, "mnemonic_words", toml_edit::Item::None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+
+        std::fs::write(&path, "network = \"mars\"").unwrap();
+        assert!(save_setting(&path, "mnemonic_words", toml_edit::value(12)).is_err());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "network = \"mars\""
+        );
+    }
+
+    #[test]
+    fn colors_override_the_theme() {
+        let config =
+            Config::parse("theme = \"light\"\n[colors]\nerror = \"magenta\"\nhint = \"#123456\"\n")
+                .unwrap();
+        let mut theme = Theme::by_name(config.theme.as_deref().unwrap()).unwrap();
+        config.colors.apply(&mut theme);
+
+        assert_eq!(theme.error, Color::Magenta);
+        assert_eq!(theme.hint, Color::Rgb(0x12, 0x34, 0x56));
+        assert_eq!(theme.success, Theme::LIGHT.success);
+        assert!(Config::parse("theme = \"neon\"").is_err());
+    }
+
+    #[test]
+    fn profiles_map_names_to_keys_files() {
+        let config =
+            Config::parse("[profiles]\nwork = \"work.txt\"\nhome = \"home.txt\"\n").unwrap();
+        assert_eq!(config.profiles["work"], "work.txt");
+        assert_eq!(config.profiles.len(), 2);
+        assert!(Config::parse("[profiles]\nwork = 1").is_err());
+    }
+
+    #[test]
+    fn uri_schemes_are_checked() {
+        let config = Config::parse("[uri_schemes]\nPolkadot = \"polkadot\"\n").unwrap();
+        assert_eq!(config.uri_schemes["Polkadot"], "polkadot");
+        assert!(Config::parse("[uri_schemes]\nmars = \"mars\"").is_err());
+        assert!(Config::parse("[uri_schemes]\nkusama = \"ksm:\"").is_err());
+        assert!(Config::parse("[uri_schemes]\nkusama = \"\"").is_err());
+    }
+
+    #[test]
+    fn conflicting_keys_reject_the_file() {
+        let config =
+            Config::parse("keymap = \"vim\"\n[keys]\nquit = [\"q\", \"ctrl+q\"]\n").unwrap();
+        assert_eq!(config.keymap.as_deref(), Some("vim"));
+        assert!(matches!(&config.keys["quit"], Keys::Many(keys) if keys.len() == 2));
+
+        let error = Config::parse("[keys]\ncopy = \"q\"\n").unwrap_err();
+        assert_eq!(error, "q is bound to both quit and copy");
+    }
+}
@@ -0,0 +1,604 @@
+//! Keys of the wallet list and the actions they run.
+//!
+//! The defaults can be changed in the config file: `keymap` picks a preset, and the
+//! `[keys]` table maps action names to a key or a list of keys, replacing all default
+//! keys of those actions. An empty list leaves an action without a key.
+//!
+//! ```toml
+//! keymap = "vim"
+//!
+//! [keys]
+//! generate = "g"
+//! quit = ["q", "ctrl+q"]
+//! ```
+//!
+//! A key is a single character, `ctrl+` and a character, or one of `up`, `down`, `left`,
+//! `right`, `pageup`, `pagedown`, `home`, `end`, `esc`, `tab`, `enter`, `space` and
+//! `f1` to `f12`.
+
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Something that can be done from the wallet list by pressing a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    /// Wipe the wallets from memory until the password is entered again
+    Lock,
+    /// Clear the filter, or quit when there is none
+    Back,
+    Help,
+    ShowDetails,
+    Generate,
+    GenerateBatch,
+    GenerateProtected,
+    VanitySearch,
+    ImportPhrase,
+    ImportKeystore,
+    ImportArchive,
+    MergeKeysFile,
+    WatchAddresses,
+    ExportKeystore,
+    ExportCsv,
+    ExportArchive,
+    Delete,
+    /// Mark or unmark the selected wallet for batch deletes and exports
+    Mark,
+    MarkAll,
+    ClearMarks,
+    Undo,
+    CopyAddress,
+    CopyUri,
+    CopyAllAddresses,
+    Rename,
+    Sign,
+    SignOffline,
+    Verify,
+    Transfer,
+    AddressBook,
+    ShowQrCode,
+    RevealSecret,
+    CopySecret,
+    Inspect,
+    Rotate,
+    DeriveNext,
+    DerivationPath,
+    FetchBalances,
+    Filter,
+    CycleScheme,
+    CycleMnemonicWords,
+    CycleNetwork,
+    CycleSortOrder,
+    CycleProfile,
+    Reload,
+    RevealPhrase,
+    TogglePublicKeys,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+}
+
+/// Every action with its name in the config file and its description, in the order the
+/// help overlay lists them.
+const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Generate, "generate", "Generate a wallet"),
+    (
+        Action::GenerateBatch,
+        "generate_batch",
+        "Generate several wallets",
+    ),
+    (
+        Action::GenerateProtected,
+        "generate_protected",
+        "Generate a wallet whose key also takes a passphrase",
+    ),
+    (
+        Action::VanitySearch,
+        "vanity",
+        "Search for a vanity address",
+    ),
+    (
+        Action::ImportPhrase,
+        "import_phrase",
+        "Import a mnemonic phrase or secret URI",
+    ),
+    (
+        Action::ImportKeystore,
+        "import_keystore",
+        "Import a JSON keystore",
+    ),
+    (
+        Action::ImportArchive,
+        "import_all",
+        "Import all wallets of an archive",
+    ),
+    (
+        Action::MergeKeysFile,
+        "merge",
+        "Merge in the wallets of another keys file",
+    ),
+    (
+        Action::WatchAddresses,
+        "watch",
+        "Watch addresses you don't own",
+    ),
+    (
+        Action::ExportKeystore,
+        "export_keystore",
+        "Export as JSON keystore",
+    ),
+    (
+        Action::ExportCsv,
+        "export_csv",
+        "Export all or the marked addresses to CSV",
+    ),
+    (
+        Action::ExportArchive,
+        "export_all",
+        "Export all or the marked wallets to an archive",
+    ),
+    (
+        Action::Delete,
+        "delete",
+        "Delete, or delete the marked wallets",
+    ),
+    (
+        Action::Mark,
+        "mark",
+        "Mark for deleting or exporting together",
+    ),
+    (Action::MarkAll, "mark_all", "Mark every wallet shown"),
+    (Action::ClearMarks, "clear_marks", "Unmark all wallets"),
+    (Action::Undo, "undo", "Undo the last generate or delete"),
+    (
+        Action::ShowDetails,
+        "details",
+        "Show the selected wallet in full",
+    ),
+    (Action::CopyAddress, "copy", "Copy the address"),
+    (
+        Action::CopyUri,
+        "copy_uri",
+        "Copy the address as a payment URI",
+    ),
+    (
+        Action::CopyAllAddresses,
+        "copy_all",
+        "Copy the addresses of every wallet shown",
+    ),
+    (Action::Rename, "rename", "Rename"),
+    (Action::ShowQrCode, "qr_code", "Show the address QR code"),
+    (
+        Action::RevealSecret,
+        "reveal_secret",
+        "Reveal the secret seed",
+    ),
+    (Action::CopySecret, "copy_secret", "Copy the secret seed"),
+    (
+        Action::Inspect,
+        "inspect",
+        "Show the secret seed and keys as subkey inspect does",
+    ),
+    (
+        Action::Rotate,
+        "rotate",
+        "Replace the key with a new one, keeping the label",
+    ),
+    (Action::Sign, "sign", "Sign a message"),
+    (
+        Action::SignOffline,
+        "sign_offline",
+        "Sign a transaction payload offline",
+    ),
+    (Action::Verify, "verify", "Verify a signature"),
+    (Action::Transfer, "transfer", "Transfer tokens"),
+    (Action::AddressBook, "address_book", "Open the address book"),
+    (Action::DeriveNext, "derive_next", "Derive the next account"),
+    (Action::DerivationPath, "derive_path", "Derive along a path"),
+    (
+        Action::FetchBalances,
+        "balances",
+        "Fetch balances from a node",
+    ),
+    (Action::Filter, "filter", "Filter by address"),
+    (
+        Action::CycleScheme,
+        "cycle_scheme",
+        "Switch the new wallet scheme",
+    ),
+    (
+        Action::CycleMnemonicWords,
+        "cycle_mnemonic",
+        "Switch the mnemonic length",
+    ),
+    (Action::CycleNetwork, "cycle_network", "Switch the network"),
+    (
+        Action::CycleSortOrder,
+        "cycle_sort",
+        "Sort by address, label, age or file order",
+    ),
+    (
+        Action::CycleProfile,
+        "cycle_profile",
+        "Switch to the next keys file profile",
+    ),
+    (Action::Reload, "reload", "Reload the keys file now"),
+    (
+        Action::RevealPhrase,
+        "toggle_phrase",
+        "Show the phrase for a moment, once confirmed",
+    ),
+    (
+        Action::TogglePublicKeys,
+        "toggle_public_keys",
+        "Show or hide public keys",
+    ),
+    (Action::Up, "up", "Select the previous wallet"),
+    (Action::Down, "down", "Select the next wallet"),
+    (Action::PageUp, "page_up", "Scroll up a page"),
+    (Action::PageDown, "page_down", "Scroll down a page"),
+    (Action::Help, "help", "Show this help"),
+    (Action::Back, "back", "Clear the filter, or quit"),
+    (
+        Action::Lock,
+        "lock",
+        "Lock the keys file until the password is entered again",
+    ),
+    (Action::Quit, "quit", "Quit"),
+];
+
+impl Action {
+    /// Name of the action in the `[keys]` table of the config file.
+    pub fn name(self) -> &'static str {
+        Self::describe(self).1
+    }
+
+    pub fn description(self) -> &'static str {
+        Self::describe(self).2
+    }
+
+    /// Every action, in the order the help overlay lists them.
+    pub fn all() -> impl Iterator<Item = Action> {
+        ACTIONS.iter().map(|(action, _, _)| *action)
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, action_name, _)| *action_name == name)
+            .map(|(action, _, _)| *action)
+    }
+
+    fn describe(self) -> &'static (Action, &'static str, &'static str) {
+        ACTIONS
+            .iter()
+            .find(|(action, _, _)| *action == self)
+            .expect("every action is listed")
+    }
+
+    /// Whether the action applies to the selected wallet, and so does nothing without one.
+    pub fn needs_selection(self) -> bool {
+        matches!(
+            self,
+            Action::ShowDetails
+                | Action::ExportKeystore
+                | Action::Delete
+                | Action::Mark
+                | Action::CopyAddress
+                | Action::CopyUri
+                | Action::Rename
+                | Action::Sign
+                | Action::SignOffline
+                | Action::Transfer
+                | Action::ShowQrCode
+                | Action::RevealSecret
+                | Action::RevealPhrase
+                | Action::CopySecret
+                | Action::Inspect
+                | Action::Rotate
+                | Action::DeriveNext
+                | Action::DerivationPath
+        )
+    }
+
+    /// Whether the action can be used from the detail view of a wallet: those applying to
+    /// it, moving on to the previous or next one, and leaving.
+    pub fn works_in_details(self) -> bool {
+        self.needs_selection()
+            || matches!(
+                self,
+                Action::Quit
+                    | Action::Lock
+                    | Action::Back
+                    | Action::Help
+                    | Action::TogglePublicKeys
+                    | Action::Up
+                    | Action::Down
+            )
+    }
+
+    /// Whether the action needs the selected wallet's secret, which watch-only wallets
+    /// don't have.
+    pub fn needs_secret(self) -> bool {
+        matches!(
+            self,
+            Action::ExportKeystore
+                | Action::Sign
+                | Action::SignOffline
+                | Action::Transfer
+                | Action::RevealSecret
+                | Action::RevealPhrase
+                | Action::CopySecret
+                | Action::Inspect
+                | Action::Rotate
+                | Action::DeriveNext
+                | Action::DerivationPath
+        )
+    }
+}
+
+/// A key of the wallet list and the action it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub key: KeyCode,
+    /// Whether Ctrl has to be held. Letters are then always lowercase.
+    pub ctrl: bool,
+    pub action: Action,
+}
+
+impl KeyBinding {
+    /// How the key is shown in the help overlay and the footer.
+    pub fn label(&self) -> String {
+        let key = match self.key {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) if self.ctrl => c.to_ascii_uppercase().to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            key => format!("{:?}", key),
+        };
+
+        match self.ctrl {
+            true => format!("Ctrl+{}", key),
+            false => key,
+        }
+    }
+}
+
+const fn bind(key: KeyCode, action: Action) -> KeyBinding {
+    KeyBinding {
+        key,
+        ctrl: false,
+        action,
+    }
+}
+
+const fn ctrl(c: char, action: Action) -> KeyBinding {
+    KeyBinding {
+        key: KeyCode::Char(c),
+        ctrl: true,
+        action,
+    }
+}
+
+/// The keys the wallet list has unless the config file changes them.
+pub const DEFAULT_KEY_BINDINGS: &[KeyBinding] = &[
+    bind(KeyCode::Char('a'), Action::Generate),
+    bind(KeyCode::Char('A'), Action::GenerateBatch),
+    bind(KeyCode::Char('G'), Action::GenerateProtected),
+    bind(KeyCode::Char('v'), Action::VanitySearch),
+    bind(KeyCode::Char('i'), Action::ImportPhrase),
+    bind(KeyCode::Char('J'), Action::ImportKeystore),
+    bind(KeyCode::Char('I'), Action::ImportArchive),
+    bind(KeyCode::Char('M'), Action::MergeKeysFile),
+    bind(KeyCode::Char('W'), Action::WatchAddresses),
+    bind(KeyCode::Char('e'), Action::ExportKeystore),
+    bind(KeyCode::Char('E'), Action::ExportCsv),
+    bind(KeyCode::Char('B'), Action::ExportArchive),
+    bind(KeyCode::Char('d'), Action::Delete),
+    bind(KeyCode::Enter, Action::ShowDetails),
+    bind(KeyCode::Char('c'), Action::CopyAddress),
+    bind(KeyCode::Char('U'), Action::CopyUri),
+    bind(KeyCode::Char('Y'), Action::CopyAllAddresses),
+    bind(KeyCode::Char('u'), Action::Undo),
+    bind(KeyCode::Char('r'), Action::Rename),
+    bind(KeyCode::Char('Q'), Action::ShowQrCode),
+    bind(KeyCode::Char('s'), Action::RevealSecret),
+    bind(KeyCode::Char('C'), Action::CopySecret),
+    bind(KeyCode::Char('X'), Action::Inspect),
+    bind(KeyCode::Char('O'), Action::Rotate),
+    bind(KeyCode::Char('S'), Action::Sign),
+    bind(KeyCode::Char('T'), Action::SignOffline),
+    bind(KeyCode::Char('V'), Action::Verify),
+    bind(KeyCode::Char('t'), Action::Transfer),
+    bind(KeyCode::Char('K'), Action::AddressBook),
+    bind(KeyCode::Char('+'), Action::DeriveNext),
+    bind(KeyCode::Char('P'), Action::DerivationPath),
+    bind(KeyCode::Char('b'), Action::FetchBalances),
+    bind(KeyCode::Char('/'), Action::Filter),
+    bind(KeyCode::Char('x'), Action::CycleScheme),
+    bind(KeyCode::Char('m'), Action::CycleMnemonicWords),
+    bind(KeyCode::Char('n'), Action::CycleNetwork),
+    bind(KeyCode::Char('o'), Action::CycleSortOrder),
+    bind(KeyCode::Char('f'), Action::CycleProfile),
+    bind(KeyCode::Char('R'), Action::Reload),
+    bind(KeyCode::Char('w'), Action::RevealPhrase),
+    bind(KeyCode::Char('p'), Action::TogglePublicKeys),
+    bind(KeyCode::Up, Action::Up),
+    bind(KeyCode::Down, Action::Down),
+    bind(KeyCode::PageUp, Action::PageUp),
+    bind(KeyCode::PageDown, Action::PageDown),
+    bind(KeyCode::Char('?'), Action::Help),
+    bind(KeyCode::Esc, Action::Back),
+    bind(KeyCode::Char('q'), Action::Quit),
+    ctrl('c', Action::Quit),
+    ctrl('l', Action::Lock),
+    bind(KeyCode::Char(' '), Action::Mark),
+    bind(KeyCode::Char('*'), Action::MarkAll),
+    ctrl('n', Action::ClearMarks),
+];
+
+/// Keys the `vim` keymap adds to the defaults.
+const VIM_KEY_BINDINGS: &[KeyBinding] = &[
+    bind(KeyCode::Char('k'), Action::Up),
+    bind(KeyCode::Char('j'), Action::Down),
+    ctrl('u', Action::PageUp),
+    ctrl('d', Action::PageDown),
+];
+
+/// Names the `keymap` setting accepts.
+pub const PRESETS: [&str; 2] = ["default", "vim"];
+
+/// The key or keys of an action in the `[keys]` table of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Builds the key bindings of the `preset` keymap with the keys of every action in
+/// `overrides` replaced.
+///
+/// Fails on an unknown preset, action or key, and on a key bound to two actions, so that
+/// a mistake is reported instead of silently shadowing a binding.
+pub fn key_bindings(
+    preset: Option<&str>,
+    overrides: &BTreeMap<String, Keys>,
+) -> Result<Vec<KeyBinding>, String> {
+    let mut bindings = DEFAULT_KEY_BINDINGS.to_vec();
+    match preset {
+        None => {}
+        Some(preset) if preset.eq_ignore_ascii_case("default") => {}
+        Some(preset) if preset.eq_ignore_ascii_case("vim") => {
+            bindings.extend_from_slice(VIM_KEY_BINDINGS)
+        }
+        Some(preset) => {
+            return Err(format!(
+                "Unknown keymap \"{}\", expected one of {}",
+                preset,
+                PRESETS.join(", ")
+            ))
+        }
+    }
+
+    for (name, keys) in overrides {
+        let action = Action::from_name(name)
+            .ok_or_else(|| format!("Unknown action \"{}\" in [keys]", name))?;
+        let keys = match keys {
+            Keys::One(key) => std::slice::from_ref(key),
+            Keys::Many(keys) => keys.as_slice(),
+        };
+
+        bindings.retain(|binding| binding.action != action);
+        for key in keys {
+            let (key, ctrl) =
+                parse_key(key).map_err(|e| format!("Invalid key for {}: {}", name, e))?;
+            bindings.push(KeyBinding { key, ctrl, action });
+        }
+    }
+
+    for (i, binding) in bindings.iter().enumerate() {
+        let conflict = bindings[..i]
+            .iter()
+            .find(|other| other.key == binding.key && other.ctrl == binding.ctrl);
+        if let Some(other) = conflict {
+            return Err(format!(
+                "{} is bound to both {} and {}",
+                binding.label(),
+                other.action.name(),
+                binding.action.name()
+            ));
+        }
+    }
+
+    Ok(bindings)
+}
+
+/// Parses a key of the `[keys]` table into its code and whether Ctrl is held.
+fn parse_key(spec: &str) -> Result<(KeyCode, bool), String> {
+    let (ctrl, key) = match spec.get(..5) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("ctrl+") => (true, &spec[5..]),
+        _ => (false, spec),
+    };
+
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match key.to_ascii_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "enter" => KeyCode::Enter,
+            "space" => KeyCode::Char(' '),
+            name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(format!("unknown key \"{}\"", spec)),
+            },
+        },
+    };
+
+    Ok((code, ctrl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_and_vim_keymaps_have_no_conflicts() {
+        assert!(key_bindings(None, &BTreeMap::new()).is_ok());
+        assert!(key_bindings(Some("vim"), &BTreeMap::new()).is_ok());
+    }
+
+    #[test]
+    fn overrides_replace_an_actions_keys() {
+        let overrides = BTreeMap::from([
+            ("generate".to_string(), Keys::One("g".to_string())),
+            (
+                "quit".to_string(),
+                Keys::Many(vec!["q".to_string(), "ctrl+Q".to_string()]),
+            ),
+        ]);
+        let bindings = key_bindings(None, &overrides).unwrap();
+        let keys = |action| {
+            bindings
+                .iter()
+                .filter(|binding| binding.action == action)
+                .map(KeyBinding::label)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(Action::Generate), ["g"]);
+        assert_eq!(keys(Action::Quit), ["q", "Ctrl+Q"]);
+        assert!(!bindings
+            .iter()
+            .any(|binding| binding.key == KeyCode::Char('a')));
+    }
+
+    #[test]
+    fn conflicting_and_unknown_bindings_are_rejected() {
+        let conflict = BTreeMap::from([("generate".to_string(), Keys::One("d".to_string()))]);
+        assert_eq!(
+            key_bindings(None, &conflict).unwrap_err(),
+            "d is bound to both delete and generate"
+        );
+
+        let unknown = BTreeMap::from([("fly".to_string(), Keys::One("f".to_string()))]);
+        assert!(key_bindings(None, &unknown).is_err());
+        let bad_key = BTreeMap::from([("help".to_string(), Keys::One("f13".to_string()))]);
+        assert!(key_bindings(None, &bad_key).is_err());
+        assert!(key_bindings(Some("emacs"), &BTreeMap::new()).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+//! Encrypted JSON keystore files in the format used by polkadot-js and subkey.
+//!
+//! The `encoded` field is the base64 of
+//!
+//! ```text
+//! salt (32) | scrypt N (u32 LE) | scrypt p (u32 LE) | scrypt r (u32 LE) | nonce (24) | secretbox(pkcs8)
+//! ```
+//!
+//! where the secretbox key is the first 32 bytes of the scrypt output and the sealed
+//! PKCS#8-style payload is `PKCS8_HEADER | secret key | PKCS8_DIVIDER | public key`.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crypto_secretbox::{
+    aead::{Aead, KeyInit},
+    XSalsa20Poly1305,
+};
+use rand::{rngs::OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::{AccountId32, Pair, Ss58Codec};
+use std::io::{Error, ErrorKind};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+use crate::wallet::{check_label, KeyEntry, KeyPair, KeyScheme};
+
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+const PKCS8_DIVIDER: [u8; 5] = [161, 35, 3, 33, 0];
+
+/// scrypt cost parameters polkadot-js uses for new keystores.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+
+/// A polkadot-js JSON keystore.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Keystore {
+    pub encoded: String,
+    pub encoding: Encoding,
+    pub address: String,
+    pub meta: Meta,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Encoding {
+    /// `["pkcs8", <scheme>]`
+    pub content: Vec<String>,
+    /// `["scrypt", "xsalsa20-poly1305"]`
+    #[serde(rename = "type")]
+    pub kind: Vec<String>,
+    pub version: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+    #[serde(default)]
+    pub genesis_hash: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub when_created: Option<u64>,
+}
+
+/// Encrypts `entry` with `password` into a keystore recording `address`.
+pub fn export(entry: &KeyEntry, address: &str, password: &str) -> Result<Keystore, Error> {
+    let pair = entry.pair().ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "Watch-only wallets have no key to export",
+        )
+    })?;
+    let (secret_key, public_key) = key_pair_bytes(&pair);
+
+    let mut pkcs8 = Zeroizing::new(Vec::new());
+    pkcs8.extend_from_slice(&PKCS8_HEADER);
+    pkcs8.extend_from_slice(&secret_key);
+    pkcs8.extend_from_slice(&PKCS8_DIVIDER);
+    pkcs8.extend_from_slice(&public_key);
+
+    let mut rng = OsRng;
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.try_fill_bytes(&mut salt).map_err(Error::other)?;
+    rng.try_fill_bytes(&mut nonce).map_err(Error::other)?;
+
+    let key = derive_key(password, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+    let sealed = XSalsa20Poly1305::new(key.as_ref().into())
+        .encrypt(&nonce.into(), pkcs8.as_slice())
+        .map_err(|_| Error::other("Encryption failed"))?;
+
+    let mut encoded = Vec::with_capacity(SALT_LEN + 12 + NONCE_LEN + sealed.len());
+    encoded.extend_from_slice(&salt);
+    encoded.extend_from_slice(&(1u32 << SCRYPT_LOG_N).to_le_bytes());
+    encoded.extend_from_slice(&SCRYPT_P.to_le_bytes());
+    encoded.extend_from_slice(&SCRYPT_R.to_le_bytes());
+    encoded.extend_from_slice(&nonce);
+    encoded.extend_from_slice(&sealed);
+
+    let when_created = match entry.created {
+        Some(created) => created * 1000,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default(),
+    };
+
+    Ok(Keystore {
+        encoded: STANDARD.encode(encoded),
+        encoding: Encoding {
+            content: vec!["pkcs8".to_string(), entry.scheme.name().to_string()],
+            kind: vec!["scrypt".to_string(), "xsalsa20-poly1305".to_string()],
+            version: "3".to_string(),
+        },
+        address: address.to_string(),
+        meta: Meta {
+            genesis_hash: Some(String::new()),
+            name: entry.label.clone(),
+            when_created: Some(when_created),
+        },
+    })
+}
+
+/// Decrypts a keystore with `password` into a wallet entry, checking that the recovered
+/// key matches the keystore's recorded address.
+pub fn import(json: &str, password: &str) -> Result<KeyEntry, Error> {
+    let keystore: Keystore = serde_json::from_str(json)
+        .map_err(|e| invalid_data(format!("Invalid keystore JSON: {}", e)))?;
+
+    let encoding = &keystore.encoding;
+    if encoding.kind != ["scrypt", "xsalsa20-poly1305"]
+        || encoding.content.first().map(String::as_str) != Some("pkcs8")
+    {
+        return Err(invalid_data(format!(
+            "Unsupported keystore encoding: {} / {}",
+            encoding.kind.join("+"),
+            encoding.content.join("+")
+        )));
+    }
+
+    let scheme_name = encoding.content.get(1).map(String::as_str).unwrap_or("");
+    let scheme = KeyScheme::from_name(scheme_name)
+        .ok_or_else(|| invalid_data(format!("Unsupported key type: {}", scheme_name)))?;
+
+    let encoded = STANDARD
+        .decode(&keystore.encoded)
+        .map_err(|e| invalid_data(format!("Invalid base64 in keystore: {}", e)))?;
+    if encoded.len() < SALT_LEN + 12 + NONCE_LEN {
+        return Err(invalid_data("Keystore payload is truncated"));
+    }
+
+    let (salt, rest) = encoded.split_at(SALT_LEN);
+    let read_u32 = |offset: usize| u32::from_le_bytes(rest[offset..offset + 4].try_into().unwrap());
+    let (n, p, r) = (read_u32(0), read_u32(4), read_u32(8));
+    // The parameters come from the file, so anything but the ones polkadot-js writes
+    // could make deriving the key take any amount of time and memory.
+    if (n, r, p) != (1 << SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P) {
+        return Err(invalid_data(format!(
+            "Invalid scrypt params: N={}, r={}, p={}",
+            n, r, p
+        )));
+    }
+    let (nonce, sealed) = rest[12..].split_at(NONCE_LEN);
+
+    let key = derive_key(password, salt, n.trailing_zeros() as u8, r, p)?;
+    let pkcs8 = Zeroizing::new(
+        XSalsa20Poly1305::new(key.as_ref().into())
+            .decrypt(nonce.into(), sealed)
+            .map_err(|_| Error::new(ErrorKind::PermissionDenied, "Wrong keystore password"))?,
+    );
+
+    let secret_len = match scheme {
+        KeyScheme::Ecdsa => 32,
+        KeyScheme::Sr25519 | KeyScheme::Ed25519 => 64,
+    };
+    let divider_at = PKCS8_HEADER.len() + secret_len;
+    if pkcs8.len() <= divider_at + PKCS8_DIVIDER.len()
+        || pkcs8[..PKCS8_HEADER.len()] != PKCS8_HEADER
+        || pkcs8[divider_at..divider_at + PKCS8_DIVIDER.len()] != PKCS8_DIVIDER
+    {
+        return Err(invalid_data("Keystore contains a malformed PKCS#8 key"));
+    }
+    let secret = &pkcs8[PKCS8_HEADER.len()..divider_at];
+    let public = &pkcs8[divider_at + PKCS8_DIVIDER.len()..];
+
+    let entry = match scheme {
+        KeyScheme::Sr25519 => KeyEntry::from_sr25519_key(secret.try_into().unwrap())?,
+        KeyScheme::Ed25519 | KeyScheme::Ecdsa => {
+            KeyEntry::from_seed(scheme, secret[..32].try_into().unwrap())
+        }
+    };
+
+    let pair = entry.pair().expect("decrypted entries have a secret");
+    if pair.public() != public {
+        return Err(invalid_data(
+            "Keystore public key does not match its secret key",
+        ));
+    }
+
+    let (account, _) = AccountId32::from_ss58check_with_version(&keystore.address)
+        .map_err(|e| invalid_data(format!("Invalid keystore address: {:?}", e)))?;
+    if account != pair.account_id() {
+        return Err(invalid_data(
+            "Keystore address does not match the decrypted key",
+        ));
+    }
+
+    if let Some(name) = &keystore.meta.name {
+        check_label(name).map_err(|e| invalid_data(format!("Invalid keystore name: {}", e)))?;
+    }
+
+    Ok(KeyEntry {
+        label: keystore.meta.name,
+        created: keystore.meta.when_created.map(|ms| ms / 1000),
+        ..entry
+    })
+}
+
+/// Returns the secret and public key of `pair` laid out the way polkadot-js stores them.
+///
+/// sr25519 secrets are the 64-byte ed25519-style secret key, ed25519 secrets are the seed
+/// followed by the public key, and ecdsa secrets are the seed itself. Derived accounts
+/// are stored with the secret of the derived pair, as polkadot-js does.
+fn key_pair_bytes(pair: &KeyPair) -> (Zeroizing<Vec<u8>>, Vec<u8>) {
+    let public = pair.public();
+
+    let secret = match pair {
+        KeyPair::Sr25519(pair) => {
+            let raw = Zeroizing::new(pair.to_raw_vec());
+            schnorrkel::SecretKey::from_bytes(&raw)
+                .expect("sr25519 pairs hold a valid secret key")
+                .to_ed25519_bytes()
+                .to_vec()
+        }
+        KeyPair::Ed25519(pair) => [&pair.seed()[..], &public].concat(),
+        KeyPair::Ecdsa(pair) => pair.seed().to_vec(),
+    };
+
+    (Zeroizing::new(secret), public)
+}
+
+fn invalid_data(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, message.into())
+}
+
+/// Derives the secretbox key from `password` the way polkadot-js does: scrypt with a
+/// 64-byte output, of which the first 32 bytes are used.
+fn derive_key(
+    password: &str,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<Zeroizing<[u8; 32]>, Error> {
+    let params = scrypt::Params::new(log_n, r, p, 64)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+
+    let mut output = Zeroizing::new([0u8; 64]);
+    scrypt::scrypt(password.as_bytes(), salt, &params, output.as_mut())
+        .map_err(|e| Error::other(e.to_string()))?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(&output[..32]);
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_core::Pair;
+
+    /// Opens an exported keystore the way polkadot-js' `decodePair` does.
+    fn open(keystore: &Keystore, password: &str) -> Vec<u8> {
+        let encoded = STANDARD.decode(&keystore.encoded).unwrap();
+        let (salt, rest) = encoded.split_at(SALT_LEN);
+        let n = u32::from_le_bytes(rest[0..4].try_into().unwrap());
+        let p = u32::from_le_bytes(rest[4..8].try_into().unwrap());
+        let r = u32::from_le_bytes(rest[8..12].try_into().unwrap());
+        let (nonce, sealed) = rest[12..].split_at(NONCE_LEN);
+
+        let key = derive_key(password, salt, n.trailing_zeros() as u8, r, p).unwrap();
+        XSalsa20Poly1305::new(key.as_ref().into())
+            .decrypt(nonce.into(), sealed)
+            .unwrap()
+    }
+
+    #[test]
+    fn export_matches_polkadot_js_layout() {
+        let entry = KeyEntry::from_seed(KeyScheme::Sr25519, [7u8; 32]);
+        let public = sp_core::sr25519::Pair::from_seed(&[7u8; 32]).public();
+        let address = public.to_ss58check();
+
+        let keystore = export(&entry, &address, "secret").unwrap();
+        let json = serde_json::to_value(&keystore).unwrap();
+        assert_eq!(json["encoding"]["content"][1], "sr25519");
+        assert_eq!(json["encoding"]["type"][0], "scrypt");
+        assert_eq!(json["encoding"]["version"], "3");
+        assert_eq!(json["address"], address.as_str());
+
+        let pkcs8 = open(&keystore, "secret");
+        assert_eq!(
+            pkcs8.len(),
+            PKCS8_HEADER.len() + 64 + PKCS8_DIVIDER.len() + 32
+        );
+        assert_eq!(&pkcs8[..16], &PKCS8_HEADER);
+        assert_eq!(&pkcs8[80..85], &PKCS8_DIVIDER);
+        assert_eq!(&pkcs8[85..], &public.0);
+    }
+
+    #[test]
+    fn import_round_trips_every_scheme() {
+        for scheme in [KeyScheme::Sr25519, KeyScheme::Ed25519, KeyScheme::Ecdsa] {
+            let entry = KeyEntry::from_seed(scheme, [9u8; 32]);
+            let address = entry.account_id().to_ss58check();
+
+            let keystore = export(&entry, &address, "secret").unwrap();
+            let json = serde_json::to_string(&keystore).unwrap();
+
+            let imported = import(&json, "secret").unwrap();
+            assert_eq!(
+                imported.pair().unwrap().public(),
+                entry.pair().unwrap().public()
+            );
+            assert_eq!(
+                import(&json, "wrong").unwrap_err().kind(),
+                ErrorKind::PermissionDenied
+            );
+        }
+    }
+
+    #[test]
+    fn import_rejects_other_scrypt_params() {
+        let entry = KeyEntry::from_seed(KeyScheme::Sr25519, [9u8; 32]);
+        let address = entry.account_id().to_ss58check();
+        let mut keystore = export(&entry, &address, "secret").unwrap();
+
+        // N = 2^30 would need gigabytes of memory to derive the key.
+        let mut encoded = STANDARD.decode(&keystore.encoded).unwrap();
+        encoded[SALT_LEN..SALT_LEN + 4].copy_from_slice(&(1u32 << 30).to_le_bytes());
+        keystore.encoded = STANDARD.encode(&encoded);
+        let json = serde_json::to_string(&keystore).unwrap();
+
+        let error = import(&json, "secret").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("Invalid scrypt params"));
+    }
+
+    #[test]
+    fn import_rejects_names_that_would_break_the_keys_file() {
+        let entry = KeyEntry::from_seed(KeyScheme::Sr25519, [9u8; 32]);
+        let address = entry.account_id().to_ss58check();
+        let mut keystore = export(&entry, &address, "secret").unwrap();
+        keystore.meta.name = Some("a\nb".to_string());
+        let json = serde_json::to_string(&keystore).unwrap();
+
+        let error = import(&json, "secret").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("Invalid keystore name"));
+
+        keystore.meta.name = Some("a b".to_string());
+        let json = serde_json::to_string(&keystore).unwrap();
+        assert_eq!(
+            import(&json, "secret").unwrap().label.as_deref(),
+            Some("a b")
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Diagnostic log written to a file, since the terminal belongs to the UI.
+//!
+//! The log goes to `$XDG_STATE_HOME/substrate-wallet/wallet.log` (or
+//! `~/.local/state/substrate-wallet/wallet.log`) unless another path is given. It
+//! records info and above by default; each `--verbose` lowers the level by one step, and
+//! `RUST_LOG` replaces the level with a filter of its own.
+//!
+//! Keys typed into dialogs are never logged, only the actions keys of the wallet list
+//! run, so neither passwords nor secrets end up in the file.
+
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_subscriber::EnvFilter;
+
+/// Starts logging to the file at `path`, or at [`default_path`] when none is given,
+/// appending to what is already there.
+///
+/// Returns the path logged to. Nothing is logged when the file cannot be opened, and
+/// the error says why.
+pub fn init(path: Option<&Path>, verbosity: u8) -> Result<PathBuf, String> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => {
+            default_path().ok_or("No log file path, as neither XDG_STATE_HOME nor HOME is set")?
+        }
+    };
+    let fail = |e: &dyn std::fmt::Display| format!("Not logging to {}: {}", path.display(), e);
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| fail(&e))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| fail(&e))?;
+
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(filter) if !filter.is_empty() => EnvFilter::try_new(filter).map_err(|e| fail(&e))?,
+        _ => EnvFilter::new(level(verbosity)),
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .try_init()
+        .map_err(|e| fail(&e))?;
+
+    Ok(path)
+}
+
+/// The level logged at with `verbosity` times `--verbose`.
+fn level(verbosity: u8) -> &'static str {
+    match verbosity {
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    }
+}
+
+/// Where the log is written when no path is given.
+pub fn default_path() -> Option<PathBuf> {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("state"))
+        })?;
+
+    Some(state_dir.join("substrate-wallet").join("wallet.log"))
+}
@@ -1,11 +1,565 @@
-pub use app::App;
-
-pub mod app;
-
-fn main() -> color_eyre::Result<()> {
-    color_eyre::install()?;
-    let terminal = ratatui::init();
-    let result = App::new().run(terminal);
-    ratatui::restore();
-    result
-}
+use clap::{Parser, Subcommand, ValueEnum};
+use color_eyre::eyre::eyre;
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use sp_core::crypto::Ss58AddressFormat;
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+pub use app::App;
+use app::NETWORKS;
+use config::Config;
+use wallet::{KeyEntry, KeyScheme, WalletKeys};
+
+pub mod address_book;
+pub mod app;
+mod archive;
+pub mod config;
+pub mod keymap;
+pub mod keystore;
+pub mod logging;
+pub mod payload;
+pub mod rpc;
+pub mod state;
+pub mod theme;
+pub mod transfer;
+pub mod vault;
+pub mod wallet;
+
+/// Terminal manager for Substrate wallets.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Keys file to manage, created on first write if it does not exist
+    ///
+    /// Precedence: this argument wins over the WALLET_KEYS_PATH environment variable,
+    /// which wins over `keys_path` in the config file, which wins over the default
+    /// ./keys.txt.
+    #[arg(long, value_name = "PATH", env = "WALLET_KEYS_PATH")]
+    keys: Option<String>,
+
+    /// Config file to read instead of ~/.config/substrate-wallet/config.toml
+    #[arg(long, value_name = "PATH", env = "WALLET_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// File to write the diagnostic log to instead of
+    /// ~/.local/state/substrate-wallet/wallet.log
+    ///
+    /// Wins over `log_file` in the config file.
+    #[arg(long, value_name = "PATH", env = "WALLET_LOG_FILE")]
+    log_file: Option<PathBuf>,
+
+    /// Milliseconds the UI waits for input before checking on background work, from 10
+    /// to 1000. Longer saves CPU, shorter feels snappier.
+    ///
+    /// Wins over `poll_interval_ms` in the config file.
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(app::POLL_INTERVAL_RANGE_MS))]
+    poll_interval: Option<u64>,
+
+    /// Keep every change in memory: neither the keys file nor exports are written
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// How commands run without the UI print their results: text to read, or json to
+    /// pipe into other tools
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Log more detail: once for debug, twice for trace. RUST_LOG overrides this.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Runs without the terminal UI when given
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate wallets, append them to the keys file and print their addresses
+    Generate {
+        /// Number of wallets to generate
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..=app::MAX_BATCH_SIZE as u64))]
+        count: u64,
+
+        /// Keys file to append to instead of the one the TUI would manage
+        #[arg(long, value_name = "PATH")]
+        out: Option<String>,
+
+        /// Key scheme of the new wallets: sr25519, ed25519 or ecdsa
+        #[arg(long, default_value = "sr25519", value_parser = parse_scheme)]
+        scheme: KeyScheme,
+
+        /// Derive the wallets from a new mnemonic phrase of 12, 15, 18, 21 or 24 words,
+        /// 12 when no number is given, instead of a raw seed
+        #[arg(long, value_name = "COUNT", value_parser = parse_words, num_args = 0..=1, default_missing_value = "12")]
+        words: Option<usize>,
+
+        /// Network whose address format is printed, instead of the one in the config file
+        #[arg(long)]
+        network: Option<String>,
+    },
+    /// Add wallets from a file, or from standard input when the path is `-`, to the keys
+    /// file and print their addresses
+    ///
+    /// Each line is read like a line of the keys file, so a bare hex seed is an sr25519
+    /// wallet. Lines that are not a wallet are reported with their line number and
+    /// skipped, as are wallets already in the keys file.
+    Import {
+        /// File to read the wallets from, or `-` for standard input
+        #[arg(value_name = "PATH")]
+        source: PathBuf,
+
+        /// Keys file to append to instead of the one the TUI would manage
+        #[arg(long, value_name = "PATH")]
+        out: Option<String>,
+
+        /// Network whose address format is printed, instead of the one in the config file
+        #[arg(long)]
+        network: Option<String>,
+    },
+    /// Print the index, address and label of every wallet of the keys file
+    List {
+        /// Network whose address format is printed, instead of the one in the config file
+        #[arg(long)]
+        network: Option<String>,
+
+        /// Include each wallet's secret seed, and mnemonic phrase in JSON, in the output
+        #[arg(long)]
+        secrets: bool,
+    },
+    /// Write the index, label, network and address of every wallet of the keys file to a
+    /// CSV file, or a JSON one with `--format json`
+    Export {
+        /// File to write, or `-` for standard output
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Network whose address format is written, instead of the one in the config file
+        #[arg(long)]
+        network: Option<String>,
+
+        /// Include each wallet's secret seed, and mnemonic phrase in JSON, in the export
+        #[arg(long)]
+        secrets: bool,
+    },
+}
+
+/// How commands run without the UI print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// A wallet as the JSON output of the commands run without the UI lists it.
+#[derive(serde::Serialize)]
+struct WalletJson<'a> {
+    /// Position in the keys file, counting from 1 like the wallet list
+    index: usize,
+    address: &'a str,
+    label: Option<&'a str>,
+    /// Signature scheme, or `watch-only` for wallets without a key
+    scheme: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phrase: Option<&'a str>,
+}
+
+/// The JSON array listing the wallets of `entries`, numbered from `first_index`, with
+/// their addresses in `format`, on lines of its own. Secrets are only included if
+/// `secrets`.
+fn wallets_json(
+    entries: &[KeyEntry],
+    first_index: usize,
+    format: Ss58AddressFormat,
+    secrets: bool,
+) -> color_eyre::Result<Zeroizing<String>> {
+    let wallets: Vec<WalletKeys> = entries
+        .iter()
+        .map(|entry| WalletKeys::from_entry(entry, format))
+        .collect();
+    let json: Vec<WalletJson> = entries
+        .iter()
+        .zip(&wallets)
+        .enumerate()
+        .map(|(i, (entry, keys))| WalletJson {
+            index: first_index + i,
+            address: &keys.address,
+            label: entry.label.as_deref(),
+            scheme: match entry.is_watch_only() {
+                true => "watch-only",
+                false => entry.scheme.name(),
+            },
+            secret: keys.private_key().filter(|_| secrets),
+            phrase: entry.phrase().filter(|_| secrets),
+        })
+        .collect();
+
+    let mut json = Zeroizing::new(serde_json::to_string_pretty(&json)?);
+    json.push('\n');
+    Ok(json)
+}
+
+/// Writes `text` to standard output. A reader that stops early, like `head`, is not an
+/// error.
+fn print_stdout(text: &str) -> color_eyre::Result<()> {
+    match std::io::stdout().lock().write_all(text.as_bytes()) {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// One line per address.
+fn address_lines(addresses: impl IntoIterator<Item = String>) -> String {
+    addresses
+        .into_iter()
+        .map(|address| address + "\n")
+        .collect()
+}
+
+fn parse_scheme(name: &str) -> Result<KeyScheme, String> {
+    KeyScheme::from_name(&name.to_ascii_lowercase())
+        .ok_or_else(|| "expected sr25519, ed25519 or ecdsa".to_string())
+}
+
+fn parse_words(words: &str) -> Result<usize, String> {
+    match words.parse() {
+        Ok(words) if wallet::MNEMONIC_WORDS.contains(&words) => Ok(words),
+        _ => Err("expected 12, 15, 18, 21 or 24".to_string()),
+    }
+}
+
+fn main() -> color_eyre::Result<()> {
+    // Parse before taking over the terminal so that `--help` and usage errors print
+    // normally.
+    let cli = Cli::parse();
+    color_eyre::install()?;
+    let (mut config, warning) = Config::load(cli.config.as_deref());
+    config.poll_interval_ms = cli.poll_interval.or(config.poll_interval_ms);
+    let log_file = cli.log_file.as_deref().or(config.log_file.as_deref());
+    let logging = logging::init(log_file, cli.verbose);
+
+    let keys_path = cli
+        .keys
+        .or_else(|| config.keys_path.clone())
+        .unwrap_or_else(|| app::DEFAULT_KEYS_PATH.to_string());
+
+    if let Some(command) = cli.command {
+        for warning in [warning, logging.err()].into_iter().flatten() {
+            eprintln!("{}", warning);
+        }
+        return match command {
+            Command::Generate {
+                count,
+                out,
+                scheme,
+                words,
+                network,
+            } => generate(
+                &out.unwrap_or(keys_path),
+                count as usize,
+                scheme,
+                words,
+                address_format(network.as_deref(), &config)?,
+                cli.format,
+                cli.dry_run,
+            ),
+            Command::Import {
+                source,
+                out,
+                network,
+            } => import(
+                &source,
+                &out.unwrap_or(keys_path),
+                address_format(network.as_deref(), &config)?,
+                cli.format,
+                cli.dry_run,
+            ),
+            Command::List { network, secrets } => list(
+                &keys_path,
+                address_format(network.as_deref(), &config)?,
+                cli.format,
+                secrets,
+            ),
+            Command::Export {
+                path,
+                network,
+                secrets,
+            } => export(
+                &keys_path,
+                &path,
+                address_format(network.as_deref(), &config)?,
+                cli.format,
+                secrets,
+                cli.dry_run,
+            ),
+        };
+    }
+
+    let mut app = App::new(keys_path)
+        .with_config(&config)
+        .with_dry_run(cli.dry_run)
+        .with_state_file(state::default_path());
+    if let Some(warning) = warning {
+        app = app.with_startup_warning(warning);
+    }
+    if let Err(warning) = logging {
+        app = app.with_startup_warning(warning);
+    }
+
+    let terminal = ratatui::init();
+    // Without mouse capture, clicks are left to the terminal and only the keys work.
+    let mouse_captured = crossterm::execute!(std::io::stdout(), EnableMouseCapture).is_ok();
+    restore_terminal_on_panic(mouse_captured);
+    let result = app.run(terminal);
+    restore_terminal(mouse_captured);
+    result
+}
+
+/// Hands the terminal back to the shell: releases the mouse, leaves the alternate
+/// screen and turns raw mode off.
+fn restore_terminal(mouse_captured: bool) {
+    if mouse_captured {
+        let _ = crossterm::execute!(std::io::stdout(), DisableMouseCapture);
+    }
+    ratatui::restore();
+}
+
+/// Restores the terminal before a panic is reported, so that the report is readable and
+/// the shell is usable afterwards.
+fn restore_terminal_on_panic(mouse_captured: bool) {
+    let report = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal(mouse_captured);
+        report(info);
+    }));
+}
+
+/// Appends `count` new wallets to the keys file at `path` in a single write and prints
+/// their addresses, one per line, or them as JSON. A dry run only prints them.
+fn generate(
+    path: &str,
+    count: usize,
+    scheme: KeyScheme,
+    words: Option<usize>,
+    format: Ss58AddressFormat,
+    output: OutputFormat,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    check_not_encrypted(path, "add wallets")?;
+    let first_index = wallet::load_wallets_from_file(path, None)?.seeds.len() + 1;
+
+    let mut addresses = Vec::with_capacity(count);
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (address, entry) = wallet::Wallet::new(scheme)
+            .with_mnemonic_words(words)
+            .with_ss58_format(format)
+            .generate()?;
+        addresses.push(address);
+        entries.push(entry);
+    }
+    if !dry_run {
+        wallet::save_wallets_to_file(path, &entries, None)?;
+    }
+    tracing::info!(
+        count,
+        path,
+        scheme = scheme.name(),
+        dry_run,
+        "Generated wallets"
+    );
+
+    match output {
+        OutputFormat::Text => print_stdout(&address_lines(addresses)),
+        OutputFormat::Json => print_stdout(&wallets_json(&entries, first_index, format, false)?),
+    }
+}
+
+/// Appends the wallets read from `source`, or from standard input when it is `-`, to the
+/// keys file at `path` in a single write and prints their addresses, one per line, or
+/// them as JSON.
+///
+/// Lines that are not a wallet, and wallets the keys file already holds, are reported on
+/// standard error and skipped. A dry run only prints the addresses.
+fn import(
+    source: &Path,
+    path: &str,
+    format: Ss58AddressFormat,
+    output: OutputFormat,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    check_not_encrypted(path, "add wallets")?;
+
+    let mut contents = Zeroizing::new(Vec::new());
+    if source == Path::new("-") {
+        std::io::stdin().read_to_end(&mut contents)?;
+    } else {
+        std::fs::File::open(source)
+            .and_then(|mut file| file.read_to_end(&mut contents))
+            .map_err(|e| eyre!("Cannot read {}: {}", source.display(), e))?;
+    }
+    let loaded = wallet::parse_wallets(&contents);
+    for (line, error) in &loaded.skipped {
+        eprintln!("Skipped line {}: {}", line, error);
+    }
+
+    let existing = wallet::load_wallets_from_file(path, None)?.seeds;
+    let known: HashSet<[u8; 32]> = existing.iter().map(KeyEntry::fingerprint).collect();
+    let (present, entries): (Vec<KeyEntry>, Vec<KeyEntry>) = loaded
+        .seeds
+        .into_iter()
+        .partition(|entry| known.contains(&entry.fingerprint()));
+    if !present.is_empty() {
+        eprintln!("Skipped {} wallet(s) already in {}", present.len(), path);
+    }
+
+    let entries: Vec<KeyEntry> = entries.into_iter().map(KeyEntry::stamped).collect();
+    for entry in &entries {
+        if let Some(reason) = entry.weak_secret() {
+            eprintln!(
+                "Warning: the secret of {} does not look random: {}",
+                entry.address(format),
+                reason
+            );
+        }
+    }
+    if !entries.is_empty() && !dry_run {
+        wallet::save_wallets_to_file(path, &entries, None)?;
+    }
+    tracing::info!(
+        count = entries.len(),
+        skipped = loaded.skipped.len(),
+        path,
+        dry_run,
+        "Imported wallets"
+    );
+
+    match output {
+        OutputFormat::Text => print_stdout(&address_lines(
+            entries.iter().map(|entry| entry.address(format)),
+        )),
+        OutputFormat::Json => {
+            print_stdout(&wallets_json(&entries, existing.len() + 1, format, false)?)
+        }
+    }
+}
+
+/// Reads the wallets of the plaintext keys file at `path`, reporting the lines that are
+/// not a wallet on standard error.
+fn read_wallets(path: &str, doing: &str) -> color_eyre::Result<Vec<KeyEntry>> {
+    check_not_encrypted(path, doing)?;
+    let loaded = wallet::load_wallets_from_file(path, None)?;
+    for (line, error) in &loaded.skipped {
+        eprintln!("Skipped line {} of {}: {}", line, path, error);
+    }
+    Ok(loaded.seeds)
+}
+
+/// Prints the index, address and label of every wallet of the keys file at `path`,
+/// tab-separated, or them as JSON. Secrets are left out unless `secrets`.
+fn list(
+    path: &str,
+    format: Ss58AddressFormat,
+    output: OutputFormat,
+    secrets: bool,
+) -> color_eyre::Result<()> {
+    let entries = read_wallets(path, "list its wallets")?;
+
+    if output == OutputFormat::Json {
+        return print_stdout(&wallets_json(&entries, 1, format, secrets)?);
+    }
+    let mut lines = Zeroizing::new(String::new());
+    for (i, entry) in entries.iter().enumerate() {
+        let keys = WalletKeys::from_entry(entry, format);
+        lines.push_str(&format!(
+            "{}\t{}\t{}",
+            i + 1,
+            keys.address,
+            entry.label.as_deref().unwrap_or_default()
+        ));
+        if secrets {
+            lines.push_str(&format!("\t{}", keys.private_key().unwrap_or_default()));
+        }
+        lines.push('\n');
+    }
+    print_stdout(&lines)
+}
+
+/// Writes every wallet of the keys file at `path` to `out`, or to standard output when it
+/// is `-`, as CSV or as JSON. Secrets are left out unless `secrets`, in which case the
+/// file is replaced atomically and kept private to its owner. A dry run writes nothing to
+/// a file.
+fn export(
+    path: &str,
+    out: &Path,
+    format: Ss58AddressFormat,
+    output: OutputFormat,
+    secrets: bool,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    let entries = read_wallets(path, "export its wallets")?;
+    let contents = match output {
+        OutputFormat::Text => {
+            let wallets: Vec<WalletKeys> = entries
+                .iter()
+                .map(|entry| WalletKeys::from_entry(entry, format))
+                .collect();
+            let network = app::network_name(format);
+            Zeroizing::new(app::addresses_csv(&entries, &wallets, &network, secrets))
+        }
+        OutputFormat::Json => wallets_json(&entries, 1, format, secrets)?,
+    };
+
+    if out == Path::new("-") {
+        return print_stdout(&contents);
+    }
+    if dry_run {
+        eprintln!(
+            "Dry run: {} wallet(s) not exported to {}",
+            entries.len(),
+            out.display()
+        );
+        return Ok(());
+    }
+    let written = if secrets {
+        wallet::write_private_file(out, contents.as_bytes())
+    } else {
+        std::fs::write(out, contents.as_bytes())
+    };
+    written.map_err(|e| eyre!("Cannot write {}: {}", out.display(), e))?;
+    tracing::info!(count = entries.len(), out = %out.display(), secrets, "Exported wallets");
+    eprintln!("Exported {} wallet(s) to {}", entries.len(), out.display());
+    Ok(())
+}
+
+/// The address format of the network preset called `name`, or when no name is given,
+/// the one the config file selects or else that of the first preset.
+fn address_format(name: Option<&str>, config: &Config) -> color_eyre::Result<Ss58AddressFormat> {
+    match name {
+        Some(name) => NETWORKS
+            .iter()
+            .find(|network| network.name.eq_ignore_ascii_case(name))
+            .map(app::Network::ss58_format)
+            .ok_or_else(|| eyre!("Unknown network \"{}\"", name)),
+        None => Ok(config
+            .ss58_format()
+            .unwrap_or_else(|| NETWORKS[0].ss58_format())),
+    }
+}
+
+/// Fails if the keys file at `path` is encrypted, since there is no prompt for its
+/// password outside the UI. `doing` says what would have been done with it.
+fn check_not_encrypted(path: &str, doing: &str) -> color_eyre::Result<()> {
+    if std::fs::read(path).is_ok_and(|contents| vault::is_encrypted(&contents)) {
+        return Err(eyre!(
+            "{} is encrypted; unlock it in the wallet to {}",
+            path,
+            doing
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,232 @@
+//! What can be told about the signing payload of a transaction without the node.
+//!
+//! A signing payload is the call, then the extra data of the signed extensions (era,
+//! nonce, tip and so on), then the data they only sign over, which for the standard
+//! extensions ends with the spec and transaction versions, the genesis hash and the hash
+//! of the block the era starts at, possibly followed by the metadata hash option. Only
+//! the runtime metadata says where the call ends, and offline there is none, so the call
+//! is named and its arguments decoded only for the transfers of chains known here. The
+//! end of the payload is recognised by its genesis hash: either a known one, or the same
+//! hash twice as immortal transactions have.
+
+use codec::{Compact, Decode};
+use sp_core::crypto::AccountId32;
+
+/// A chain whose calls can be named offline.
+#[derive(Debug, PartialEq, Eq)]
+pub struct KnownChain {
+    pub name: &'static str,
+    pub genesis_hash: [u8; 32],
+    /// Index of the `Balances` pallet in the chain's runtime
+    pub balances_pallet: u8,
+}
+
+/// Chains whose genesis hash is recognised, matching the networks the wallet has presets
+/// for.
+pub const KNOWN_CHAINS: [KnownChain; 2] = [
+    KnownChain {
+        name: "Polkadot",
+        genesis_hash: hex_literal(
+            b"91b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c3",
+        ),
+        balances_pallet: 5,
+    },
+    KnownChain {
+        name: "Kusama",
+        genesis_hash: hex_literal(
+            b"b0a8d493285c2df73290dfb7e61f870f17b41801197a149ca93654499ea3dafe",
+        ),
+        balances_pallet: 4,
+    },
+];
+
+/// `Balances` calls by index, and whether their arguments are a destination and an
+/// amount.
+const BALANCES_CALLS: [(u8, &str, bool); 4] = [
+    (0, "transfer_allow_death", true),
+    (2, "force_transfer", false),
+    (3, "transfer_keep_alive", true),
+    (4, "transfer_all", false),
+];
+
+/// What was made out of a signing payload.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PayloadSummary {
+    pub pallet_index: u8,
+    pub call_index: u8,
+    /// Pallet and call name, for the calls of known chains
+    pub call_name: Option<(&'static str, &'static str)>,
+    /// Destination and amount in planck, for transfers of known chains
+    pub transfer: Option<(AccountId32, u128)>,
+    /// The end of the payload, if it was recognised
+    pub chain: Option<ChainInfo>,
+}
+
+/// The chain a payload is for, as told by the end of it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ChainInfo {
+    pub spec_version: u32,
+    pub transaction_version: u32,
+    pub genesis_hash: [u8; 32],
+    /// Whether the era starts at the genesis block, i.e. the transaction never expires
+    pub immortal: bool,
+    pub known: Option<&'static KnownChain>,
+}
+
+impl PayloadSummary {
+    /// Whether nothing but the call index could be made out: signing it is signing blind.
+    pub fn is_opaque(&self) -> bool {
+        self.chain.is_none()
+    }
+}
+
+/// Makes out what it can of the signing `payload`. Fails only if it is too short to even
+/// hold a call index.
+pub fn decode(payload: &[u8]) -> Result<PayloadSummary, String> {
+    let [pallet_index, call_index, args @ ..] = payload else {
+        return Err("A payload starts with a pallet and a call index".to_string());
+    };
+
+    let chain = decode_chain(payload);
+    let known = chain.as_ref().and_then(|chain| chain.known);
+    let call = known
+        .filter(|known| known.balances_pallet == *pallet_index)
+        .and_then(|_| {
+            BALANCES_CALLS
+                .iter()
+                .find(|(index, ..)| index == call_index)
+        });
+
+    Ok(PayloadSummary {
+        pallet_index: *pallet_index,
+        call_index: *call_index,
+        call_name: call.map(|(_, name, _)| ("Balances", *name)),
+        transfer: call
+            .filter(|(_, _, is_transfer)| *is_transfer)
+            .and_then(|_| decode_transfer(args)),
+        chain,
+    })
+}
+
+/// The `MultiAddress::Id` destination and compact amount a transfer call starts with.
+fn decode_transfer(mut args: &[u8]) -> Option<(AccountId32, u128)> {
+    let (&0, rest) = args.split_first()? else {
+        return None;
+    };
+    args = rest;
+    let dest = AccountId32::decode(&mut args).ok()?;
+    let Compact(amount) = Compact::<u128>::decode(&mut args).ok()?;
+    Some((dest, amount))
+}
+
+/// The versions and genesis hash at the end of `payload`, trying it without the metadata
+/// hash option, with `None` and with `Some` hash after them.
+fn decode_chain(payload: &[u8]) -> Option<ChainInfo> {
+    let tails: [(usize, Option<u8>); 3] = [(0, None), (1, Some(0)), (33, Some(1))];
+    tails.into_iter().find_map(|(tail, option)| {
+        let end = payload.len().checked_sub(tail)?;
+        if option.is_some_and(|option| payload.get(end) != Some(&option)) {
+            return None;
+        }
+        // The call index comes first; the versions and hashes take 72 bytes.
+        let start = end.checked_sub(72).filter(|&start| start >= 2)?;
+        let fields = &payload[start..end];
+
+        let genesis_hash: [u8; 32] = fields[8..40].try_into().ok()?;
+        // A run of one byte value is no hash, whatever repeats it.
+        let immortal = fields[8..40] == fields[40..72]
+            && genesis_hash.iter().any(|&byte| byte != genesis_hash[0]);
+        let known = KNOWN_CHAINS
+            .iter()
+            .find(|chain| chain.genesis_hash == genesis_hash);
+        if !immortal && known.is_none() {
+            return None;
+        }
+
+        Some(ChainInfo {
+            spec_version: u32::from_le_bytes(fields[0..4].try_into().ok()?),
+            transaction_version: u32::from_le_bytes(fields[4..8].try_into().ok()?),
+            genesis_hash,
+            immortal,
+            known,
+        })
+    })
+}
+
+/// The 32 bytes a 64-digit hex string stands for, at compile time.
+const fn hex_literal(hex: &[u8; 64]) -> [u8; 32] {
+    const fn digit(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            _ => panic!("not a lowercase hex digit"),
+        }
+    }
+
+    let mut bytes = [0u8; 32];
+    let mut i = 0;
+    while i < 32 {
+        bytes[i] = digit(hex[2 * i]) << 4 | digit(hex[2 * i + 1]);
+        i += 1;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+
+    #[test]
+    fn transfers_of_known_chains_are_decoded() {
+        let polkadot = &KNOWN_CHAINS[0];
+        let dest = [7u8; 32];
+
+        // transfer_keep_alive, a mortal era, nonce 3 and no tip, then the versions, the
+        // genesis hash, a block hash and the metadata hash option set to `None`
+        let mut payload = vec![5, 3, 0];
+        payload.extend(dest);
+        Compact(15_000_000_000u128).encode_to(&mut payload);
+        payload.extend([0x15, 0x02, 3 << 2, 0, 0]);
+        payload.extend(1_003_000u32.to_le_bytes());
+        payload.extend(26u32.to_le_bytes());
+        payload.extend(polkadot.genesis_hash);
+        payload.extend([9u8; 32]);
+        payload.push(0);
+
+        let summary = decode(&payload).unwrap();
+        assert!(!summary.is_opaque());
+        assert_eq!(summary.call_name, Some(("Balances", "transfer_keep_alive")));
+        assert_eq!(
+            summary.transfer,
+            Some((AccountId32::new(dest), 15_000_000_000))
+        );
+        let chain = summary.chain.unwrap();
+        assert_eq!(
+            (
+                chain.spec_version,
+                chain.transaction_version,
+                chain.immortal
+            ),
+            (1_003_000, 26, false)
+        );
+        assert_eq!(chain.known, Some(polkadot));
+
+        // Immortal transactions of other chains are recognised by their end alone.
+        let mut payload = vec![10, 1, 42, 0, 0, 0];
+        payload.extend([1u32.to_le_bytes(), 2u32.to_le_bytes()].concat());
+        let genesis_hash = sp_core::blake2_256(b"genesis");
+        payload.extend([genesis_hash, genesis_hash].concat());
+        let summary = decode(&payload).unwrap();
+        assert_eq!((summary.call_name, summary.transfer), (None, None));
+        assert!(summary
+            .chain
+            .is_some_and(|chain| chain.immortal && chain.known.is_none()));
+
+        let opaque = decode(&[5, 3, 1, 2, 3]).unwrap();
+        assert!(opaque.is_opaque());
+        assert!(decode(&[4; 80]).unwrap().is_opaque());
+        assert_eq!((opaque.pallet_index, opaque.call_index), (5, 3));
+        assert!(decode(&[5]).is_err());
+    }
+}
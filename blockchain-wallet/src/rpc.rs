@@ -0,0 +1,245 @@
+//! A minimal blocking JSON-RPC client for Substrate nodes.
+//!
+//! Requests are plain JSON-RPC 2.0 calls over HTTP, which every Substrate node serves on
+//! the same port as its websocket endpoint. Calls block, so the app makes them from a
+//! worker thread.
+
+use serde_json::{json, Value};
+use sp_core::{
+    crypto::{AccountId32, Ss58Codec},
+    hashing::{blake2_128, twox_128},
+};
+use std::io::{Error, ErrorKind};
+use std::time::Duration;
+
+/// Endpoint used until another one is entered.
+pub const DEFAULT_URL: &str = "http://127.0.0.1:9944";
+
+/// How long a single call may take before it is given up on.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a node may take to answer [`RpcClient::connect`] before it is considered
+/// unreachable.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl RpcClient {
+    /// Creates a client for `url`. A `ws://` or `wss://` URL is called over the matching
+    /// HTTP scheme.
+    pub fn new(url: &str) -> Self {
+        let url = url.trim();
+        let url = if let Some(rest) = url.strip_prefix("ws://") {
+            format!("http://{}", rest)
+        } else if let Some(rest) = url.strip_prefix("wss://") {
+            format!("https://{}", rest)
+        } else {
+            url.to_string()
+        };
+
+        Self {
+            url,
+            agent: agent(TIMEOUT),
+        }
+    }
+
+    /// Checks that the node answers at all, giving up after [`CONNECT_TIMEOUT`] rather
+    /// than the longer timeout of other calls.
+    pub fn connect(&self) -> Result<(), Error> {
+        let probe = Self {
+            url: self.url.clone(),
+            agent: agent(CONNECT_TIMEOUT),
+        };
+        probe.call("system_health", json!([])).map(drop)
+    }
+
+    /// Calls `method` with `params` and returns its result.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, Error> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let mut response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(&request)
+            .map_err(|e| match e {
+                ureq::Error::Timeout(_) => {
+                    Error::new(ErrorKind::TimedOut, "the node did not answer in time")
+                }
+                e => Error::other(e),
+            })?
+            .body_mut()
+            .read_json()
+            .map_err(Error::other)?;
+
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error");
+            return Err(Error::other(format!("{} failed: {}", method, message)));
+        }
+
+        Ok(response["result"].take())
+    }
+
+    /// Fetches the free balance of `account` in planck. Accounts the chain does not know
+    /// have a balance of zero.
+    pub fn free_balance(&self, account: &AccountId32) -> Result<u128, Error> {
+        let account: &[u8] = account.as_ref();
+        let key = [
+            &twox_128(b"System")[..],
+            &twox_128(b"Account"),
+            &blake2_128(account),
+            account,
+        ]
+        .concat();
+
+        let storage = self.call(
+            "state_getStorage",
+            json!([format!("0x{}", hex::encode(key))]),
+        )?;
+        let Some(storage) = storage.as_str() else {
+            return Ok(0);
+        };
+
+        // `AccountInfo` is four u32 counters followed by `AccountData`, whose first field
+        // is the free balance as a little-endian u128.
+        let data = hex::decode(storage.trim_start_matches("0x"))
+            .map_err(|e| invalid_data(e.to_string()))?;
+        let free = data
+            .get(16..32)
+            .ok_or_else(|| invalid_data("Account info is too short"))?;
+
+        Ok(u128::from_le_bytes(free.try_into().expect("16 bytes")))
+    }
+
+    /// Calls `method` for a `0x`-prefixed hex result and decodes it.
+    fn call_hex(&self, method: &str, params: Value) -> Result<Vec<u8>, Error> {
+        let result = self.call(method, params)?;
+        let hex_result = result
+            .as_str()
+            .ok_or_else(|| invalid_data(format!("{} returned no data", method)))?;
+
+        hex::decode(hex_result.trim_start_matches("0x")).map_err(|e| invalid_data(e.to_string()))
+    }
+
+    /// Calls `method` for a 32-byte hash.
+    fn call_hash(&self, method: &str, params: Value) -> Result<[u8; 32], Error> {
+        self.call_hex(method, params)?
+            .try_into()
+            .map_err(|_| invalid_data(format!("{} did not return a 32-byte hash", method)))
+    }
+
+    pub fn genesis_hash(&self) -> Result<[u8; 32], Error> {
+        self.call_hash("chain_getBlockHash", json!([0]))
+    }
+
+    /// Returns the `specVersion` and `transactionVersion` of the current runtime.
+    pub fn runtime_version(&self) -> Result<(u32, u32), Error> {
+        let version = self.call("state_getRuntimeVersion", json!([]))?;
+        let field = |name: &str| {
+            version[name]
+                .as_u64()
+                .and_then(|value| u32::try_from(value).ok())
+                .ok_or_else(|| invalid_data(format!("Runtime version has no {}", name)))
+        };
+
+        Ok((field("specVersion")?, field("transactionVersion")?))
+    }
+
+    /// Fetches the SCALE-encoded runtime metadata.
+    pub fn metadata(&self) -> Result<Vec<u8>, Error> {
+        self.call_hex("state_getMetadata", json!([]))
+    }
+
+    /// Fetches the nonce the next extrinsic of `account` must use, counting the ones
+    /// still waiting in the transaction pool.
+    pub fn account_nonce(&self, account: &AccountId32) -> Result<u32, Error> {
+        self.call("system_accountNextIndex", json!([account.to_ss58check()]))?
+            .as_u64()
+            .and_then(|nonce| u32::try_from(nonce).ok())
+            .ok_or_else(|| invalid_data("Node returned an invalid nonce"))
+    }
+
+    /// Submits a signed extrinsic to the transaction pool and returns its hash.
+    pub fn submit_extrinsic(&self, extrinsic: &[u8]) -> Result<[u8; 32], Error> {
+        self.call_hash(
+            "author_submitExtrinsic",
+            json!([format!("0x{}", hex::encode(extrinsic))]),
+        )
+    }
+
+    /// Estimates the fee in planck the node would charge for a signed extrinsic.
+    pub fn query_fee(&self, extrinsic: &[u8]) -> Result<u128, Error> {
+        let info = self.call(
+            "payment_queryInfo",
+            json!([format!("0x{}", hex::encode(extrinsic))]),
+        )?;
+
+        // Older nodes return the fee as a number, newer ones as a decimal string.
+        let fee = &info["partialFee"];
+        fee.as_str()
+            .and_then(|fee| fee.parse().ok())
+            .or_else(|| fee.as_u64().map(u128::from))
+            .ok_or_else(|| invalid_data("Node returned no fee estimate"))
+    }
+
+    pub fn best_block_number(&self) -> Result<u64, Error> {
+        let header = self.call("chain_getHeader", json!([]))?;
+        block_number(&header)
+    }
+
+    pub fn finalized_block_number(&self) -> Result<u64, Error> {
+        let hash = self.call("chain_getFinalizedHead", json!([]))?;
+        let header = self.call("chain_getHeader", json!([hash]))?;
+        block_number(&header)
+    }
+
+    /// Returns the hash of the block at `number` on the best chain and its extrinsics as
+    /// hex strings.
+    pub fn block_extrinsics(&self, number: u64) -> Result<([u8; 32], Vec<String>), Error> {
+        let hash = self.call_hash("chain_getBlockHash", json!([number]))?;
+        let block = self.call(
+            "chain_getBlock",
+            json!([format!("0x{}", hex::encode(hash))]),
+        )?;
+
+        let extrinsics = block["block"]["extrinsics"]
+            .as_array()
+            .ok_or_else(|| invalid_data("Block has no extrinsics"))?
+            .iter()
+            .filter_map(|extrinsic| extrinsic.as_str().map(str::to_string))
+            .collect();
+
+        Ok((hash, extrinsics))
+    }
+}
+
+/// An HTTP agent giving up on a call after `timeout`.
+fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .build()
+        .into()
+}
+
+/// Reads the hex-encoded `number` of a block header.
+fn block_number(header: &Value) -> Result<u64, Error> {
+    header["number"]
+        .as_str()
+        .and_then(|number| u64::from_str_radix(number.trim_start_matches("0x"), 16).ok())
+        .ok_or_else(|| invalid_data("Block header has no number"))
+}
+
+fn invalid_data(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, message.into())
+}
@@ -0,0 +1,86 @@
+//! Small bits of UI state remembered between runs, apart from the settings.
+//!
+//! The state is kept in `$XDG_STATE_HOME/substrate-wallet/state.txt` (or
+//! `~/.local/state/substrate-wallet/state.txt`), next to the log. So far it only holds
+//! the address of the wallet last selected in each keys file, one keys file per line: its
+//! path, a tab and the address. Losing the file loses nothing but that.
+
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Address of the wallet last selected in the keys file at `keys_path`, if one was
+/// remembered in the state file at `path`.
+pub fn last_selected(path: &Path, keys_path: &str) -> Option<String> {
+    read(path).ok()?.remove(&key(keys_path))
+}
+
+/// Remembers `address` as the wallet last selected in the keys file at `keys_path`,
+/// keeping what the state file at `path` holds for other keys files.
+pub fn remember_selected(path: &Path, keys_path: &str, address: &str) -> Result<(), Error> {
+    let mut selections = match read(path) {
+        Ok(selections) => selections,
+        Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => return Err(e),
+    };
+    selections.insert(key(keys_path), address.to_string());
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let contents: String = selections
+        .iter()
+        .map(|(keys_path, address)| format!("{}\t{}\n", keys_path, address))
+        .collect();
+    std::fs::write(path, contents)
+}
+
+/// Where the state is kept.
+pub fn default_path() -> Option<PathBuf> {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("state"))
+        })?;
+
+    Some(state_dir.join("substrate-wallet").join("state.txt"))
+}
+
+/// The last selected address of each keys file in the state file at `path`. Lines that
+/// do not make sense are skipped.
+fn read(path: &Path) -> Result<BTreeMap<String, String>, Error> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .filter_map(|line| line.rsplit_once('\t'))
+        .map(|(keys_path, address)| (keys_path.to_string(), address.to_string()))
+        .collect())
+}
+
+/// The same keys file reached by different relative paths is remembered once.
+fn key(keys_path: &str) -> String {
+    std::fs::canonicalize(keys_path)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| keys_path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selections_are_remembered_per_keys_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("state.txt");
+
+        assert_eq!(last_selected(&path, "keys.txt"), None);
+        remember_selected(&path, "keys.txt", "5First").unwrap();
+        remember_selected(&path, "other.txt", "5Other").unwrap();
+        remember_selected(&path, "keys.txt", "5Second").unwrap();
+
+        assert_eq!(last_selected(&path, "keys.txt").as_deref(), Some("5Second"));
+        assert_eq!(last_selected(&path, "other.txt").as_deref(), Some("5Other"));
+        assert_eq!(last_selected(&path, "missing.txt"), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! Colors of the UI, named by what they are used for rather than by hue.
+
+use ratatui::style::{Color, Modifier, Style};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// The three words of the "Substrate Wallet Manager" title
+    pub title: [Color; 3],
+    /// Field names such as "Address:", and the wallet numbers of the list
+    pub label: Color,
+    /// SS58 addresses in the wallet list
+    pub address: Color,
+    /// Wallet labels, key names and other values picked out of the text
+    pub highlight: Color,
+    /// Derivation paths and public keys
+    pub detail: Color,
+    pub eth_address: Color,
+    /// The marker of watch-only wallets
+    pub watch_only: Color,
+    /// Instructions at the top of the screen
+    pub accent: Color,
+    /// Successes, balances and valid signatures
+    pub success: Color,
+    pub warning: Color,
+    /// Failures, and anything secret shown on screen
+    pub error: Color,
+    /// Key hints and other secondary text
+    pub hint: Color,
+    /// The selected row of the wallet list
+    pub selection: Style,
+    /// The footer bar
+    pub footer: Style,
+    /// The network shown at the start of the footer
+    pub badge: Style,
+}
+
+impl Theme {
+    /// Colors for terminals with a dark background, the default.
+    pub const DARK: Theme = Theme {
+        title: [Color::Green, Color::Yellow, Color::Blue],
+        label: Color::Blue,
+        address: Color::Reset,
+        highlight: Color::Yellow,
+        detail: Color::Cyan,
+        eth_address: Color::Magenta,
+        watch_only: Color::LightMagenta,
+        accent: Color::Green,
+        success: Color::Green,
+        warning: Color::Yellow,
+        error: Color::Red,
+        hint: Color::DarkGray,
+        selection: Style::new().add_modifier(Modifier::REVERSED),
+        footer: Style::new().fg(Color::Gray).bg(Color::DarkGray),
+        badge: Style::new().fg(Color::Black).bg(Color::Green),
+    };
+
+    /// Darker hues that stay readable on a light background.
+    pub const LIGHT: Theme = Theme {
+        title: [
+            Color::Rgb(0x1a, 0x7f, 0x37),
+            Color::Rgb(0x9a, 0x67, 0x00),
+            Color::Rgb(0x09, 0x69, 0xda),
+        ],
+        label: Color::Rgb(0x09, 0x69, 0xda),
+        address: Color::Reset,
+        highlight: Color::Rgb(0x9a, 0x67, 0x00),
+        detail: Color::Rgb(0x1b, 0x7c, 0x83),
+        eth_address: Color::Rgb(0x82, 0x50, 0xdf),
+        watch_only: Color::Rgb(0xbf, 0x39, 0x89),
+        accent: Color::Rgb(0x1a, 0x7f, 0x37),
+        success: Color::Rgb(0x1a, 0x7f, 0x37),
+        warning: Color::Rgb(0x9a, 0x67, 0x00),
+        error: Color::Rgb(0xcf, 0x22, 0x2e),
+        hint: Color::Rgb(0x6e, 0x77, 0x81),
+        selection: Style::new()
+            .fg(Color::Black)
+            .bg(Color::Rgb(0xdd, 0xf4, 0xff)),
+        footer: Style::new()
+            .fg(Color::Rgb(0x24, 0x29, 0x2f))
+            .bg(Color::Rgb(0xea, 0xee, 0xf2)),
+        badge: Style::new()
+            .fg(Color::White)
+            .bg(Color::Rgb(0x1a, 0x7f, 0x37)),
+    };
+
+    /// Bright colors and bold text only, for low-vision use and washed-out screens.
+    pub const HIGH_CONTRAST: Theme = Theme {
+        title: [Color::White, Color::White, Color::White],
+        label: Color::LightCyan,
+        address: Color::White,
+        highlight: Color::LightYellow,
+        detail: Color::LightCyan,
+        eth_address: Color::LightMagenta,
+        watch_only: Color::LightMagenta,
+        accent: Color::White,
+        success: Color::LightGreen,
+        warning: Color::LightYellow,
+        error: Color::LightRed,
+        hint: Color::White,
+        selection: Style::new()
+            .fg(Color::Black)
+            .bg(Color::White)
+            .add_modifier(Modifier::BOLD),
+        footer: Style::new().fg(Color::White).bg(Color::Black),
+        badge: Style::new()
+            .fg(Color::Black)
+            .bg(Color::White)
+            .add_modifier(Modifier::BOLD),
+    };
+
+    /// The presets that can be picked by name in the config file.
+    pub const PRESETS: [(&'static str, Theme); 3] = [
+        ("dark", Theme::DARK),
+        ("light", Theme::LIGHT),
+        ("high-contrast", Theme::HIGH_CONTRAST),
+    ];
+
+    /// Looks up a preset by name, ignoring case.
+    pub fn by_name(name: &str) -> Option<Theme> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|(_, theme)| *theme)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DARK
+    }
+}
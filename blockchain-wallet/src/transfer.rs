@@ -0,0 +1,398 @@
+//! Building, submitting and tracking balance transfers.
+//!
+//! The `Balances` call index, the address types and the signed extensions a chain
+//! expects all come from its runtime metadata, so the same code works on any chain with
+//! a `Balances` pallet and the standard signed extensions. Transfers are immortal: they
+//! are signed against the genesis hash rather than a recent block.
+
+use codec::{Compact, Decode, Encode};
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+use scale_info::{PortableRegistry, TypeDef};
+use sp_core::{crypto::AccountId32, hashing::blake2_256};
+use std::io::{Error, ErrorKind};
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use crate::rpc::RpcClient;
+use crate::wallet::{KeyEntry, KeyPair};
+
+/// `Balances` calls a transfer can be made with, in order of preference. `transfer` is
+/// the name older runtimes use for `transfer_allow_death`.
+const TRANSFER_CALLS: [&str; 3] = ["transfer_keep_alive", "transfer_allow_death", "transfer"];
+
+/// How often the node is asked for new blocks while a transfer is tracked.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a transfer is tracked before giving up on seeing it finalized.
+const WATCH_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Progress of a transfer, reported by the thread that submits it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferEvent {
+    /// Signed and waiting for confirmation, with the estimated fee and the free balance
+    /// of the sender in planck
+    Prepared {
+        fee: u128,
+        free: u128,
+    },
+    /// Accepted into the transaction pool under this hash
+    Submitted([u8; 32]),
+    /// Included in the best chain
+    InBlock {
+        number: u64,
+        hash: [u8; 32],
+    },
+    /// The block it was included in has been finalized
+    Finalized {
+        number: u64,
+    },
+    Failed(String),
+}
+
+/// A signed extension's contribution to the extrinsic and to the signed payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SignedExtension {
+    /// Adds nothing to either
+    Empty,
+    SpecVersion,
+    TxVersion,
+    Genesis,
+    Mortality,
+    Nonce,
+    Tip,
+    AssetTip,
+    MetadataHash,
+}
+
+/// What a chain's runtime expects of a signed transfer.
+#[derive(Debug)]
+struct ChainInfo {
+    genesis_hash: [u8; 32],
+    spec_version: u32,
+    transaction_version: u32,
+    call_index: [u8; 2],
+    dest_is_multi_address: bool,
+    signer_is_multi_address: bool,
+    extensions: Vec<SignedExtension>,
+}
+
+impl ChainInfo {
+    fn fetch(client: &RpcClient) -> Result<Self, Error> {
+        let (spec_version, transaction_version) = client.runtime_version()?;
+        let genesis_hash = client.genesis_hash()?;
+        let metadata = client.metadata()?;
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+            .map_err(|e| invalid_data(format!("Undecodable runtime metadata: {}", e)))?;
+
+        type Pallet<'a> = (&'a str, u8, Option<u32>);
+        type Extension<'a> = (&'a str, u32, u32);
+        let (types, pallets, address_ty, extensions): (
+            &PortableRegistry,
+            Vec<Pallet>,
+            u32,
+            Vec<Extension>,
+        ) = match &metadata.1 {
+            RuntimeMetadata::V14(metadata) => (
+                &metadata.types,
+                metadata
+                    .pallets
+                    .iter()
+                    .map(|p| (p.name.as_str(), p.index, p.calls.as_ref().map(|c| c.ty.id)))
+                    .collect(),
+                type_param(&metadata.types, metadata.extrinsic.ty.id, "Address")?,
+                metadata
+                    .extrinsic
+                    .signed_extensions
+                    .iter()
+                    .map(|e| (e.identifier.as_str(), e.ty.id, e.additional_signed.id))
+                    .collect(),
+            ),
+            RuntimeMetadata::V15(metadata) => (
+                &metadata.types,
+                metadata
+                    .pallets
+                    .iter()
+                    .map(|p| (p.name.as_str(), p.index, p.calls.as_ref().map(|c| c.ty.id)))
+                    .collect(),
+                metadata.extrinsic.address_ty.id,
+                metadata
+                    .extrinsic
+                    .signed_extensions
+                    .iter()
+                    .map(|e| (e.identifier.as_str(), e.ty.id, e.additional_signed.id))
+                    .collect(),
+            ),
+            other => {
+                return Err(invalid_data(format!(
+                    "Unsupported runtime metadata version {}",
+                    other.version()
+                )))
+            }
+        };
+
+        let (_, pallet_index, calls_ty) =
+            pallets
+                .iter()
+                .find(|(name, _, _)| *name == "Balances")
+                .ok_or_else(|| invalid_data("Chain has no Balances pallet"))?;
+        let calls = match calls_ty
+            .and_then(|ty| types.resolve(ty))
+            .map(|ty| &ty.type_def)
+        {
+            Some(TypeDef::Variant(calls)) => &calls.variants,
+            _ => return Err(invalid_data("Balances pallet has no calls")),
+        };
+        let call = TRANSFER_CALLS
+            .iter()
+            .find_map(|name| calls.iter().find(|call| call.name == *name))
+            .ok_or_else(|| invalid_data("Balances pallet has no transfer call"))?;
+        let dest_ty = call
+            .fields
+            .first()
+            .ok_or_else(|| invalid_data("Transfer call has no destination"))?
+            .ty
+            .id;
+
+        let extensions = extensions
+            .into_iter()
+            .map(|(identifier, ty, additional)| {
+                Ok(match identifier {
+                    "CheckSpecVersion" => SignedExtension::SpecVersion,
+                    "CheckTxVersion" => SignedExtension::TxVersion,
+                    "CheckGenesis" => SignedExtension::Genesis,
+                    "CheckMortality" | "CheckEra" => SignedExtension::Mortality,
+                    "CheckNonce" => SignedExtension::Nonce,
+                    "ChargeTransactionPayment" => SignedExtension::Tip,
+                    "ChargeAssetTxPayment" => SignedExtension::AssetTip,
+                    "CheckMetadataHash" => SignedExtension::MetadataHash,
+                    _ if is_empty(types, ty) && is_empty(types, additional) => {
+                        SignedExtension::Empty
+                    }
+                    _ => {
+                        return Err(invalid_data(format!(
+                            "Unsupported signed extension {}",
+                            identifier
+                        )))
+                    }
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self {
+            genesis_hash,
+            spec_version,
+            transaction_version,
+            call_index: [*pallet_index, call.index],
+            dest_is_multi_address: is_multi_address(types, dest_ty),
+            signer_is_multi_address: is_multi_address(types, address_ty),
+            extensions,
+        })
+    }
+
+    /// Builds the SCALE-encoded signed extrinsic transferring `amount` planck from `pair`
+    /// to `dest`.
+    fn sign_transfer(
+        &self,
+        pair: &KeyPair,
+        nonce: u32,
+        dest: &AccountId32,
+        amount: u128,
+    ) -> Vec<u8> {
+        let mut call = self.call_index.to_vec();
+        if self.dest_is_multi_address {
+            // `MultiAddress::Id`
+            call.push(0);
+        }
+        call.extend_from_slice(dest.as_ref());
+        Compact(amount).encode_to(&mut call);
+
+        let mut extra = Vec::new();
+        let mut additional = Vec::new();
+        for extension in &self.extensions {
+            match extension {
+                SignedExtension::Empty => {}
+                SignedExtension::SpecVersion => self.spec_version.encode_to(&mut additional),
+                SignedExtension::TxVersion => self.transaction_version.encode_to(&mut additional),
+                SignedExtension::Genesis => additional.extend_from_slice(&self.genesis_hash),
+                SignedExtension::Mortality => {
+                    // `Era::Immortal`, checked against the genesis hash
+                    extra.push(0);
+                    additional.extend_from_slice(&self.genesis_hash);
+                }
+                SignedExtension::Nonce => Compact(nonce).encode_to(&mut extra),
+                SignedExtension::Tip => Compact(0u128).encode_to(&mut extra),
+                SignedExtension::AssetTip => {
+                    // No tip, paid in the native asset
+                    Compact(0u128).encode_to(&mut extra);
+                    extra.push(0);
+                }
+                SignedExtension::MetadataHash => {
+                    // `Mode::Disabled`, so no metadata hash is signed
+                    extra.push(0);
+                    additional.push(0);
+                }
+            }
+        }
+
+        let payload = [&call[..], &extra, &additional].concat();
+
+        // Version 4, signed
+        let mut body = vec![0x84];
+        if self.signer_is_multi_address {
+            body.push(0);
+        }
+        body.extend_from_slice(pair.account_id().as_ref());
+        body.extend(sign_payload(pair, &payload));
+        body.extend(extra);
+        body.extend(call);
+
+        let mut extrinsic = Compact(body.len() as u32).encode();
+        extrinsic.extend(body);
+        extrinsic
+    }
+}
+
+/// Signs the signing payload of an extrinsic with `pair` and SCALE-encodes the signature
+/// as a `MultiSignature`: the index of the scheme, then the signature itself. Payloads
+/// longer than 256 bytes are signed by their hash, as the runtime checks them.
+pub fn sign_payload(pair: &KeyPair, payload: &[u8]) -> Vec<u8> {
+    let signature = if payload.len() > 256 {
+        pair.sign(&blake2_256(payload))
+    } else {
+        pair.sign(payload)
+    };
+
+    let mut encoded = vec![match pair {
+        KeyPair::Ed25519(_) => 0,
+        KeyPair::Sr25519(_) => 1,
+        KeyPair::Ecdsa(_) => 2,
+    }];
+    encoded.extend(signature);
+    encoded
+}
+
+/// Signs a transfer of `amount` planck from `entry` to `dest` and reports its estimated
+/// fee, then submits it once `confirm` receives a message. Its progress is reported on
+/// `events` until it is finalized or fails; dropping the sender of `confirm` instead
+/// cancels the transfer.
+pub fn run(
+    client: RpcClient,
+    entry: KeyEntry,
+    dest: AccountId32,
+    amount: u128,
+    events: Sender<TransferEvent>,
+    confirm: Receiver<()>,
+) {
+    let result = (|| {
+        let pair = entry
+            .pair()
+            .ok_or_else(|| Error::other("Watch-only wallets cannot send transfers"))?;
+        let account = pair.account_id();
+        let free = client.free_balance(&account)?;
+
+        let chain = ChainInfo::fetch(&client)?;
+        let nonce = client.account_nonce(&account)?;
+        let extrinsic = chain.sign_transfer(&pair, nonce, &dest, amount);
+
+        let fee = client.query_fee(&extrinsic)?;
+        if events.send(TransferEvent::Prepared { fee, free }).is_err() || confirm.recv().is_err() {
+            return Ok(());
+        }
+
+        let first_block = client.best_block_number()?;
+        let hash = client.submit_extrinsic(&extrinsic)?;
+        if events.send(TransferEvent::Submitted(hash)).is_err() {
+            return Ok(());
+        }
+
+        watch(&client, &extrinsic, first_block, &events)
+    })();
+
+    if let Err(e) = result {
+        let _ = events.send(TransferEvent::Failed(e.to_string()));
+    }
+}
+
+/// Polls the node until `extrinsic` shows up in a block from `first_block` on and that
+/// block is finalized.
+fn watch(
+    client: &RpcClient,
+    extrinsic: &[u8],
+    first_block: u64,
+    events: &Sender<TransferEvent>,
+) -> Result<(), Error> {
+    let extrinsic = format!("0x{}", hex::encode(extrinsic));
+    let started = Instant::now();
+    let mut next_block = first_block;
+    let mut included = None;
+
+    while started.elapsed() < WATCH_TIMEOUT {
+        std::thread::sleep(POLL_INTERVAL);
+
+        match included {
+            None => {
+                let best = client.best_block_number()?;
+                while next_block <= best {
+                    let (hash, extrinsics) = client.block_extrinsics(next_block)?;
+                    if extrinsics.contains(&extrinsic) {
+                        included = Some(next_block);
+                        let event = TransferEvent::InBlock {
+                            number: next_block,
+                            hash,
+                        };
+                        if events.send(event).is_err() {
+                            return Ok(());
+                        }
+                        break;
+                    }
+                    next_block += 1;
+                }
+            }
+            Some(number) => {
+                if client.finalized_block_number()? >= number {
+                    let _ = events.send(TransferEvent::Finalized { number });
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    Err(Error::other(format!(
+        "Transfer was not finalized within {} seconds",
+        WATCH_TIMEOUT.as_secs()
+    )))
+}
+
+/// Returns the type of the generic parameter `name` of the type `id`.
+fn type_param(types: &PortableRegistry, id: u32, name: &str) -> Result<u32, Error> {
+    types
+        .resolve(id)
+        .and_then(|ty| ty.type_params.iter().find(|param| param.name == name))
+        .and_then(|param| param.ty)
+        .map(|ty| ty.id)
+        .ok_or_else(|| invalid_data(format!("Extrinsic type has no {} parameter", name)))
+}
+
+fn is_multi_address(types: &PortableRegistry, id: u32) -> bool {
+    types
+        .resolve(id)
+        .and_then(|ty| ty.path.segments.last())
+        .is_some_and(|name| name == "MultiAddress")
+}
+
+/// Whether values of the type `id` encode to no bytes at all.
+fn is_empty(types: &PortableRegistry, id: u32) -> bool {
+    match types.resolve(id).map(|ty| &ty.type_def) {
+        Some(TypeDef::Composite(composite)) => composite
+            .fields
+            .iter()
+            .all(|field| is_empty(types, field.ty.id)),
+        Some(TypeDef::Tuple(tuple)) => tuple.fields.iter().all(|field| is_empty(types, field.id)),
+        Some(TypeDef::Array(array)) => array.len == 0 || is_empty(types, array.type_param.id),
+        _ => false,
+    }
+}
+
+fn invalid_data(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, message.into())
+}
@@ -0,0 +1,217 @@
+//! Password-based encryption of the keys file.
+//!
+//! An encrypted keys file is a small binary header followed by the XChaCha20-Poly1305
+//! sealed plaintext of the regular line-based format:
+//!
+//! ```text
+//! magic (4) | version (1) | m_cost (4) | t_cost (4) | p_cost (4) | salt (16) | nonce (24) | ciphertext
+//! ```
+//!
+//! The key is derived from the password with Argon2id using the parameters recorded in
+//! the header, and the header itself is authenticated as associated data.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use rand::{rngs::OsRng, TryRngCore};
+use std::io::{Error, ErrorKind};
+use zeroize::Zeroizing;
+
+const MAGIC: &[u8; 4] = b"SWKF";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+/// How many times the default Argon2 costs a header may ask for. The header is read before
+/// anything is authenticated, so without a cap a tampered file could make opening it take
+/// any amount of memory and time.
+const MAX_COST_FACTOR: u32 = 16;
+
+/// Returns whether `data` looks like an encrypted keys file.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Holds the password for an encrypted keys file and the key derived from it.
+///
+/// Deriving the key is deliberately slow, so the key for the most recently seen salt is
+/// cached and reused for every reload and rewrite of the same file.
+pub struct Vault {
+    password: Zeroizing<String>,
+    cached: Option<CachedKey>,
+}
+
+struct CachedKey {
+    params: [u32; 3],
+    salt: [u8; SALT_LEN],
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl std::fmt::Debug for Vault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Vault")
+    }
+}
+
+impl Vault {
+    pub fn new(password: &str) -> Self {
+        Self {
+            password: Zeroizing::new(password.to_string()),
+            cached: None,
+        }
+    }
+
+    /// Returns whether `password` is the one the vault was opened with.
+    pub fn check_password(&self, password: &str) -> bool {
+        *self.password == password
+    }
+
+    /// Encrypts `plaintext` into the encrypted keys file format under a fresh nonce.
+    pub fn seal(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut rng = OsRng;
+
+        if self.cached.is_none() {
+            let mut salt = [0u8; SALT_LEN];
+            rng.try_fill_bytes(&mut salt).map_err(Error::other)?;
+
+            let params = [
+                Params::DEFAULT_M_COST,
+                Params::DEFAULT_T_COST,
+                Params::DEFAULT_P_COST,
+            ];
+            self.derive_key(params, salt)?;
+        }
+        let cached = self.cached.as_ref().expect("key was derived above");
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.try_fill_bytes(&mut nonce).map_err(Error::other)?;
+
+        let mut data = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+        for param in cached.params {
+            data.extend_from_slice(&param.to_le_bytes());
+        }
+        data.extend_from_slice(&cached.salt);
+        data.extend_from_slice(&nonce);
+
+        let ciphertext = XChaCha20Poly1305::new(cached.key.as_ref().into())
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: &data,
+                },
+            )
+            .map_err(|_| Error::other("Encryption failed"))?;
+        data.extend_from_slice(&ciphertext);
+
+        Ok(data)
+    }
+
+    /// Decrypts an encrypted keys file, failing without side effects on a wrong password.
+    pub fn open(&mut self, data: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+        if !is_encrypted(data) || data.len() < HEADER_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Not an encrypted keys file",
+            ));
+        }
+
+        let (header, ciphertext) = data.split_at(HEADER_LEN);
+        if header[MAGIC.len()] != VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported keys file version: {}", header[MAGIC.len()]),
+            ));
+        }
+
+        let mut offset = MAGIC.len() + 1;
+        let mut params = [0u32; 3];
+        for param in &mut params {
+            let bytes = header[offset..offset + 4].try_into().expect("4 bytes");
+            *param = u32::from_le_bytes(bytes);
+            offset += 4;
+        }
+        let salt: [u8; SALT_LEN] = header[offset..offset + SALT_LEN]
+            .try_into()
+            .expect("salt length");
+        let nonce = &header[offset + SALT_LEN..];
+
+        let defaults = [
+            Params::DEFAULT_M_COST,
+            Params::DEFAULT_T_COST,
+            Params::DEFAULT_P_COST,
+        ];
+        if params
+            .iter()
+            .zip(defaults)
+            .any(|(&param, default)| param > default * MAX_COST_FACTOR)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Key derivation costs of the keys file are too high: m={}, t={}, p={}",
+                    params[0], params[1], params[2]
+                ),
+            ));
+        }
+
+        let cached = match &self.cached {
+            Some(cached) if cached.params == params && cached.salt == salt => cached,
+            _ => self.derive_key(params, salt)?,
+        };
+
+        let plaintext = XChaCha20Poly1305::new(cached.key.as_ref().into())
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| {
+                Error::new(
+                    ErrorKind::PermissionDenied,
+                    "Wrong password or corrupted keys file",
+                )
+            })?;
+
+        Ok(Zeroizing::new(plaintext))
+    }
+
+    fn derive_key(&mut self, params: [u32; 3], salt: [u8; SALT_LEN]) -> Result<&CachedKey, Error> {
+        let [m_cost, t_cost, p_cost] = params;
+        let argon2_params = Params::new(m_cost, t_cost, p_cost, Some(32))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+            .hash_password_into(self.password.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| Error::other(e.to_string()))?;
+
+        Ok(self.cached.insert(CachedKey { params, salt, key }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excessive_key_derivation_costs_are_refused() {
+        let sealed = Vault::new("secret").seal(b"keys").unwrap();
+        assert_eq!(*Vault::new("secret").open(&sealed).unwrap(), b"keys");
+
+        // A t_cost of u32::MAX would never finish deriving the key.
+        let mut tampered = sealed.clone();
+        let t_cost = MAGIC.len() + 1 + 4;
+        tampered[t_cost..t_cost + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            Vault::new("secret").open(&tampered).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
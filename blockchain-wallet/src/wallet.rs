@@ -0,0 +1,2097 @@
+//! Wallets, their keys, and the keys file they are kept in.
+//!
+//! The keys file holds one [`KeyEntry`] per line, optionally sealed as a whole by a
+//! [`Vault`]. Appends go straight to the end of a plaintext file, while rewrites replace
+//! it atomically after backing it up. Nothing here touches the terminal, so the UI and
+//! the headless commands share it.
+
+use rand::{rngs::OsRng, TryRngCore};
+use sp_core::{
+    crypto::{
+        AccountId32, DeriveJunction, ExposeSecret, Pair, PublicError, SecretUri, Ss58AddressFormat,
+        Ss58AddressFormatRegistry, Ss58Codec,
+    },
+    ecdsa::{self, Pair as EcdsaPair},
+    ed25519::{self, Pair as Ed25519Pair},
+    hashing::{blake2_256, keccak_256},
+    sr25519::{self, Pair as Sr25519Pair},
+};
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::vault::{self, Vault};
+
+/// Scheme name of keys file lines holding a watch-only address instead of a secret.
+const WATCH_ONLY_PREFIX: &str = "watch";
+
+/// Number of backups of the keys file kept; older ones are removed.
+const MAX_BACKUPS: usize = 5;
+
+/// Lengths in words a generated mnemonic phrase may have, shortest first.
+pub const MNEMONIC_WORDS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// The signature scheme a wallet's key pair is derived with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KeyScheme {
+    #[default]
+    Sr25519,
+    Ed25519,
+    Ecdsa,
+}
+
+impl KeyScheme {
+    /// Every supported scheme, in the order they are cycled through.
+    pub const ALL: [KeyScheme; 3] = [KeyScheme::Sr25519, KeyScheme::Ed25519, KeyScheme::Ecdsa];
+
+    /// Name used in the keys file and the UI.
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyScheme::Sr25519 => "sr25519",
+            KeyScheme::Ed25519 => "ed25519",
+            KeyScheme::Ecdsa => "ecdsa",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sr25519" => Some(KeyScheme::Sr25519),
+            "ed25519" => Some(KeyScheme::Ed25519),
+            "ecdsa" => Some(KeyScheme::Ecdsa),
+            _ => None,
+        }
+    }
+
+    /// The scheme that follows this one when cycling through the supported schemes.
+    pub fn next(self) -> Self {
+        match self {
+            KeyScheme::Sr25519 => KeyScheme::Ed25519,
+            KeyScheme::Ed25519 => KeyScheme::Ecdsa,
+            KeyScheme::Ecdsa => KeyScheme::Sr25519,
+        }
+    }
+}
+
+/// The secret material a wallet's key pair is derived from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Secret {
+    /// A 32-byte seed, together with the mnemonic phrase it was derived from if any
+    Seed {
+        seed: [u8; 32],
+        phrase: Option<String>,
+    },
+    /// A 64-byte sr25519 secret key in ed25519 byte order whose seed is unknown, as
+    /// stored in polkadot-js keystores
+    Sr25519Key([u8; 64]),
+    /// No secret at all: an account that is only watched, e.g. for its balance, together
+    /// with the address format it was imported in
+    WatchOnly(AccountId32, Ss58AddressFormat),
+    /// A mnemonic phrase whose seed also takes a passphrase that is not stored, as the
+    /// `///password` of a secret URI, together with the public key they give
+    Protected { phrase: String, public: Vec<u8> },
+}
+
+/// Scrubs the secret from memory when it is no longer needed. Copies made by moves are
+/// out of reach, but every clone and entry loaded from the keys file is cleared.
+impl Drop for Secret {
+    fn drop(&mut self) {
+        match self {
+            Secret::Seed { seed, phrase } => {
+                seed.zeroize();
+                phrase.zeroize();
+            }
+            Secret::Sr25519Key(key) => key.zeroize(),
+            Secret::WatchOnly(..) => {}
+            Secret::Protected { phrase, .. } => phrase.zeroize(),
+        }
+    }
+}
+
+/// A key pair of any of the supported schemes.
+// Pairs are derived on demand and dropped right away, so boxing buys nothing.
+#[allow(clippy::large_enum_variant)]
+pub enum KeyPair {
+    Sr25519(Sr25519Pair),
+    Ed25519(Ed25519Pair),
+    Ecdsa(EcdsaPair),
+}
+
+impl KeyPair {
+    /// The key pair `seed` gives under `scheme`.
+    pub fn from_seed(scheme: KeyScheme, seed: &[u8; 32]) -> Self {
+        match scheme {
+            KeyScheme::Sr25519 => KeyPair::Sr25519(Sr25519Pair::from_seed(seed)),
+            KeyScheme::Ed25519 => KeyPair::Ed25519(Ed25519Pair::from_seed(seed)),
+            KeyScheme::Ecdsa => KeyPair::Ecdsa(EcdsaPair::from_seed(seed)),
+        }
+    }
+
+    /// The raw public key: 32 bytes, or the 33-byte compressed key for ecdsa.
+    pub fn public(&self) -> Vec<u8> {
+        match self {
+            KeyPair::Sr25519(pair) => pair.public().0.to_vec(),
+            KeyPair::Ed25519(pair) => pair.public().0.to_vec(),
+            KeyPair::Ecdsa(pair) => pair.public().0.to_vec(),
+        }
+    }
+
+    /// Signs `message` as is (without any wrapping) and returns the raw signature bytes.
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            KeyPair::Sr25519(pair) => pair.sign(message).0.to_vec(),
+            KeyPair::Ed25519(pair) => pair.sign(message).0.to_vec(),
+            KeyPair::Ecdsa(pair) => pair.sign(message).0.to_vec(),
+        }
+    }
+
+    /// Derives the child key pair along `junctions`. Only sr25519 supports soft junctions.
+    pub fn derive(&self, junctions: &[DeriveJunction]) -> Result<KeyPair, String> {
+        let junctions = junctions.iter().cloned();
+        let pair = match self {
+            KeyPair::Sr25519(pair) => pair
+                .derive(junctions, None)
+                .map(|(p, _)| KeyPair::Sr25519(p)),
+            KeyPair::Ed25519(pair) => pair
+                .derive(junctions, None)
+                .map(|(p, _)| KeyPair::Ed25519(p)),
+            KeyPair::Ecdsa(pair) => pair.derive(junctions, None).map(|(p, _)| KeyPair::Ecdsa(p)),
+        };
+
+        pair.map_err(|_| "Soft derivation is only supported for sr25519".to_string())
+    }
+
+    /// The on-chain account id. For ecdsa this is the blake2-256 hash of the compressed
+    /// public key, as Substrate does for `MultiSigner::Ecdsa`.
+    pub fn account_id(&self) -> AccountId32 {
+        match self {
+            KeyPair::Sr25519(pair) => AccountId32::new(pair.public().0),
+            KeyPair::Ed25519(pair) => AccountId32::new(pair.public().0),
+            KeyPair::Ecdsa(pair) => AccountId32::new(blake2_256(&pair.public().0)),
+        }
+    }
+}
+
+/// A wallet from the keys file: its secret, the scheme its key pair is derived with, the
+/// derivation path of the account if it is not the secret's root account, an optional
+/// human-readable label and when it was added, if known.
+///
+/// Each entry is stored as one `<scheme>:<secret><path>` line, where the secret is a hex
+/// seed, a BIP39 mnemonic phrase, or a 64-byte hex sr25519 secret key and the path is a
+/// suri-style list of `//hard` and `/soft` junctions, followed by a tab and the label if it
+/// has one, and another tab and the creation time in seconds since the Unix epoch if that
+/// is known. Lines without a scheme, as written by older versions, are read as sr25519.
+///
+/// A passphrase-protected phrase is followed by `///` and the hex public key the phrase,
+/// passphrase and path give, where a secret URI would have the passphrase itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEntry {
+    pub scheme: KeyScheme,
+    pub secret: Secret,
+    pub path: Option<String>,
+    pub label: Option<String>,
+    /// When the wallet was added to a keys file, in seconds since the Unix epoch. Entries
+    /// written by older versions have none.
+    pub created: Option<u64>,
+}
+
+impl KeyEntry {
+    /// A watch-only entry for `account`, whose address is kept in `format`. Its scheme
+    /// means nothing and is left at sr25519.
+    pub fn watch_only(account: AccountId32, format: Ss58AddressFormat) -> Self {
+        Self {
+            scheme: KeyScheme::Sr25519,
+            secret: Secret::WatchOnly(account, format),
+            path: None,
+            label: None,
+            created: None,
+        }
+    }
+
+    /// The entry with its creation time set to now, unless it already has one.
+    pub fn stamped(self) -> Self {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .ok();
+        Self {
+            created: self.created.or(now),
+            ..self
+        }
+    }
+
+    /// Whether the entry is only an address, with no secret to sign with.
+    pub fn is_watch_only(&self) -> bool {
+        matches!(self.secret, Secret::WatchOnly(..))
+    }
+
+    /// Whether signing with the entry takes a passphrase, see [`KeyEntry::protect`].
+    pub fn is_protected(&self) -> bool {
+        matches!(self.secret, Secret::Protected { .. })
+    }
+
+    pub fn from_seed(scheme: KeyScheme, seed: [u8; 32]) -> Self {
+        Self {
+            scheme,
+            secret: Secret::Seed { seed, phrase: None },
+            path: None,
+            label: None,
+            created: None,
+        }
+    }
+
+    /// Builds an entry from a BIP39 mnemonic phrase, deriving the seed the same way
+    /// polkadot-js and subkey do.
+    pub fn from_phrase(scheme: KeyScheme, phrase: &str) -> Result<Self, std::io::Error> {
+        let (_, seed) = Sr25519Pair::from_phrase(phrase, None).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid mnemonic phrase: {:?}", e),
+            )
+        })?;
+
+        Ok(Self {
+            scheme,
+            secret: Secret::Seed {
+                seed,
+                phrase: Some(phrase.to_string()),
+            },
+            path: None,
+            label: None,
+            created: None,
+        })
+    }
+
+    /// Builds an entry from a secret URI as subkey and polkadot-js take it:
+    /// `<phrase><path>///<password>`, where the phrase is a BIP39 mnemonic or a `0x` hex
+    /// seed, and the derivation path and password are optional.
+    ///
+    /// With a password, the entry keeps the seed it gives rather than the phrase, since the
+    /// keys file has no place for the password. The account is checked against the one
+    /// `Pair::from_string` derives.
+    pub fn from_suri(scheme: KeyScheme, suri: &str) -> Result<Self, std::io::Error> {
+        let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let suri = suri.trim();
+        let uri: SecretUri = suri
+            .parse()
+            .map_err(|e| invalid(format!("Invalid secret URI: {:?}", e)))?;
+        if suri.starts_with('/') {
+            return Err(invalid(
+                "The secret URI must start with a mnemonic phrase or hex seed".to_string(),
+            ));
+        }
+
+        let phrase = uri.phrase.expose_secret().as_str();
+        let password = uri.password.as_ref().map(|p| p.expose_secret().as_str());
+        let root = match (phrase.strip_prefix("0x"), password) {
+            (Some(_), Some(_)) => {
+                return Err(invalid(
+                    "A password only applies to mnemonic phrases, not hex seeds".to_string(),
+                ))
+            }
+            (Some(hex_seed), None) => {
+                let bytes = Zeroizing::new(
+                    hex::decode(hex_seed)
+                        .map_err(|e| invalid(format!("Invalid hex seed: {}", e)))?,
+                );
+                let seed = <[u8; 32]>::try_from(&bytes[..])
+                    .map_err(|_| invalid("Seed must be 32 bytes".to_string()))?;
+                Self::from_seed(scheme, seed)
+            }
+            (None, None) => Self::from_phrase(scheme, phrase.trim_end())?,
+            (None, Some(password)) => {
+                let (_, seed) = Sr25519Pair::from_phrase(phrase.trim_end(), Some(password))
+                    .map_err(|e| invalid(format!("Invalid mnemonic phrase: {:?}", e)))?;
+                Self::from_seed(scheme, seed)
+            }
+        };
+
+        // The regex the URI was parsed with allows no `///` within the path.
+        let path = &suri[phrase.len()..];
+        let path = path.find("///").map_or(path, |end| &path[..end]);
+        let entry = root.with_path((!path.is_empty()).then_some(path))?;
+
+        let expected = match scheme {
+            KeyScheme::Sr25519 => Sr25519Pair::from_string(suri, None).map(|p| p.public().to_vec()),
+            KeyScheme::Ed25519 => Ed25519Pair::from_string(suri, None).map(|p| p.public().to_vec()),
+            KeyScheme::Ecdsa => EcdsaPair::from_string(suri, None).map(|p| p.public().to_vec()),
+        }
+        .map_err(|e| invalid(format!("Invalid secret URI: {:?}", e)))?;
+        match entry.pair() {
+            Some(pair) if pair.public() == expected => Ok(entry),
+            _ => Err(invalid(
+                "The secret URI gives another account than subkey would".to_string(),
+            )),
+        }
+    }
+
+    /// Builds an sr25519 entry from a 64-byte secret key in ed25519 byte order.
+    pub fn from_sr25519_key(key: [u8; 64]) -> Result<Self, std::io::Error> {
+        schnorrkel::SecretKey::from_ed25519_bytes(&key).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid sr25519 secret key: {}", e),
+            )
+        })?;
+
+        Ok(Self {
+            scheme: KeyScheme::Sr25519,
+            secret: Secret::Sr25519Key(key),
+            path: None,
+            label: None,
+            created: None,
+        })
+    }
+
+    /// Hash identifying the wallet an entry holds, regardless of its label and of whether
+    /// its seed is stored as a mnemonic phrase. Entries with the same fingerprint have the
+    /// same key pair.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let path = self.path.as_deref().unwrap_or_default();
+        // Sized up front so that no copy of the secret is left behind by a reallocation.
+        let mut data = Zeroizing::new(Vec::with_capacity(16 + 64 + path.len()));
+        data.extend_from_slice(self.scheme.name().as_bytes());
+        data.push(0);
+        match &self.secret {
+            Secret::Seed { seed, .. } => data.extend_from_slice(seed),
+            Secret::Sr25519Key(key) => data.extend_from_slice(key),
+            Secret::WatchOnly(account, _) => data.extend_from_slice(account.as_ref()),
+            Secret::Protected { public, .. } => data.extend_from_slice(public),
+        }
+        data.extend_from_slice(path.as_bytes());
+        blake2_256(&data)
+    }
+
+    /// The 32-byte seed, unless only an sr25519 secret key is known.
+    pub fn seed(&self) -> Option<&[u8; 32]> {
+        match &self.secret {
+            Secret::Seed { seed, .. } => Some(seed),
+            Secret::Sr25519Key(_) | Secret::WatchOnly(..) | Secret::Protected { .. } => None,
+        }
+    }
+
+    pub fn phrase(&self) -> Option<&str> {
+        match &self.secret {
+            Secret::Seed { phrase, .. } => phrase.as_deref(),
+            Secret::Protected { phrase, .. } => Some(phrase),
+            Secret::Sr25519Key(_) | Secret::WatchOnly(..) => None,
+        }
+    }
+
+    /// Turns an entry with a mnemonic phrase into one whose seed also takes `passphrase`,
+    /// the way the `///password` of a secret URI does. Only the phrase and the public key
+    /// are kept, so signing needs the passphrase again, see [`KeyEntry::unprotect`].
+    pub fn protect(&self, passphrase: &str) -> Result<Self, std::io::Error> {
+        let invalid = |e: &str| std::io::Error::new(std::io::ErrorKind::InvalidInput, e);
+        if passphrase.is_empty() {
+            return Err(invalid("Enter a passphrase"));
+        }
+        let Secret::Seed {
+            phrase: Some(phrase),
+            ..
+        } = &self.secret
+        else {
+            return Err(invalid(
+                "Only wallets with a mnemonic phrase can take a passphrase",
+            ));
+        };
+
+        let public = self
+            .with_passphrase(phrase, passphrase)?
+            .public_key()
+            .expect("entries with a seed have a key pair");
+        Ok(Self {
+            secret: Secret::Protected {
+                phrase: phrase.clone(),
+                public,
+            },
+            ..self.clone()
+        })
+    }
+
+    /// The entry a protected one gives with `passphrase`, which can sign. Fails with
+    /// [`std::io::ErrorKind::PermissionDenied`] if the passphrase gives another key.
+    /// Entries that are not protected are returned as they are.
+    pub fn unprotect(&self, passphrase: &str) -> Result<Self, std::io::Error> {
+        let Secret::Protected { phrase, public } = &self.secret else {
+            return Ok(self.clone());
+        };
+
+        let entry = self.with_passphrase(phrase, passphrase)?;
+        match entry.public_key().as_ref() == Some(public) {
+            true => Ok(entry),
+            false => Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "Wrong passphrase",
+            )),
+        }
+    }
+
+    /// This entry with the seed `phrase` and `passphrase` give, keeping its path.
+    fn with_passphrase(&self, phrase: &str, passphrase: &str) -> Result<Self, std::io::Error> {
+        let (_, seed) = Sr25519Pair::from_phrase(phrase, Some(passphrase)).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid mnemonic phrase: {:?}", e),
+            )
+        })?;
+        Ok(Self {
+            secret: Secret::Seed { seed, phrase: None },
+            ..self.clone()
+        })
+    }
+
+    /// The raw public key of the entry's account, which watch-only entries don't know.
+    pub fn public_key(&self) -> Option<Vec<u8>> {
+        match &self.secret {
+            Secret::Protected { public, .. } => Some(public.clone()),
+            _ => self.pair().map(|pair| pair.public()),
+        }
+    }
+
+    /// Why the secret of this entry does not look randomly generated, if it doesn't. The
+    /// entropy behind a mnemonic phrase is checked rather than the seed stretched from it.
+    pub fn weak_secret(&self) -> Option<&'static str> {
+        match &self.secret {
+            Secret::Seed {
+                phrase: Some(phrase),
+                ..
+            }
+            | Secret::Protected { phrase, .. } => {
+                let mnemonic = bip39::Mnemonic::parse_in(bip39::Language::English, phrase).ok()?;
+                let (entropy, len) = mnemonic.to_entropy_array();
+                let entropy = Zeroizing::new(entropy);
+                weak_bytes(&entropy[..len])
+            }
+            Secret::Seed { seed, .. } => weak_bytes(seed),
+            Secret::Sr25519Key(key) => weak_bytes(&key[..32]),
+            Secret::WatchOnly(..) => None,
+        }
+    }
+
+    /// The account derived from this entry's secret along `path`, replacing any path the
+    /// entry already has. `None` is the secret's root account.
+    pub fn with_path(&self, path: Option<&str>) -> Result<Self, std::io::Error> {
+        let entry = Self {
+            path: path.map(str::to_string),
+            label: None,
+            created: None,
+            ..self.clone()
+        };
+
+        if self.is_protected() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Deriving from a passphrase-protected wallet needs its passphrase",
+            ));
+        }
+        if let Some(path) = path {
+            let junctions = parse_derivation_path(path)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            entry
+                .root_pair()
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "Watch-only wallets have no accounts to derive",
+                    )
+                })?
+                .derive(&junctions)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        }
+
+        Ok(entry)
+    }
+
+    /// Parses a line of the keys file. Whitespace around the secret and its parts is
+    /// ignored, and hex may be in either case, so that hand-edited files load.
+    pub fn parse(line: &str) -> Result<Self, std::io::Error> {
+        let (line, label) = match line.split_once('\t') {
+            Some((line, label)) => (line.trim(), Some(label)),
+            None => (line.trim(), None),
+        };
+        let (label, created) = match label.and_then(|label| label.split_once('\t')) {
+            Some((label, created)) => {
+                let created = created.trim().parse().map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Invalid creation time: {}", created),
+                    )
+                })?;
+                ((!label.is_empty()).then_some(label), Some(created))
+            }
+            None => (label, None),
+        };
+        let (line, public) = match line.split_once("///") {
+            Some((line, public)) => (line, Some(public.trim())),
+            None => (line, None),
+        };
+        let (line, path) = match line.find('/') {
+            Some(i) => (&line[..i], Some(&line[i..])),
+            None => (line, None),
+        };
+
+        let mut entry = Self::parse_secret(line)?.with_path(path)?;
+        if let Some(public) = public {
+            let Secret::Seed {
+                phrase: Some(phrase),
+                ..
+            } = &entry.secret
+            else {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Only a mnemonic phrase can take a passphrase",
+                ));
+            };
+            entry.secret = Secret::Protected {
+                phrase: phrase.clone(),
+                public: parse_public_key(entry.scheme, public)?,
+            };
+        }
+
+        Ok(Self {
+            label: label.map(str::to_string),
+            created,
+            ..entry
+        })
+    }
+
+    fn parse_secret(line: &str) -> Result<Self, std::io::Error> {
+        let (scheme, secret) = match line.split_once(':') {
+            Some((name, address)) if name.trim_end() == WATCH_ONLY_PREFIX => {
+                let (account, format) = AccountId32::from_ss58check_with_version(
+                    address.trim_start(),
+                )
+                .map_err(|e| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Invalid watch-only address: {}", e),
+                    )
+                })?;
+                return Ok(Self::watch_only(account, format));
+            }
+            Some((name, secret)) => {
+                let (name, secret) = (name.trim_end(), secret.trim_start());
+                let scheme = KeyScheme::from_name(name).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Unknown key scheme: {}", name),
+                    )
+                })?;
+                (scheme, secret)
+            }
+            None => (KeyScheme::Sr25519, line),
+        };
+
+        if secret.contains(char::is_whitespace) {
+            return Self::from_phrase(scheme, secret);
+        }
+
+        let secret_bytes = Zeroizing::new(
+            hex::decode(secret)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+        );
+
+        if let (KeyScheme::Sr25519, Ok(key)) = (scheme, <[u8; 64]>::try_from(&secret_bytes[..])) {
+            return Self::from_sr25519_key(key);
+        }
+
+        let seed = <[u8; 32]>::try_from(&secret_bytes[..]).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "Seed must be 32 bytes")
+        })?;
+
+        Ok(Self::from_seed(scheme, seed))
+    }
+
+    pub fn to_line(&self) -> String {
+        let (kind, secret) = match &self.secret {
+            Secret::Seed {
+                phrase: Some(phrase),
+                ..
+            } => (self.scheme.name(), phrase.clone()),
+            Secret::Seed { seed, phrase: None } => (self.scheme.name(), hex::encode(seed)),
+            Secret::Sr25519Key(key) => (self.scheme.name(), hex::encode(key)),
+            Secret::WatchOnly(account, format) => (
+                WATCH_ONLY_PREFIX,
+                account.to_ss58check_with_version(*format),
+            ),
+            Secret::Protected { phrase, public } => (
+                self.scheme.name(),
+                format!(
+                    "{}{}///0x{}",
+                    phrase,
+                    self.path.as_deref().unwrap_or_default(),
+                    hex::encode(public)
+                ),
+            ),
+        };
+
+        let path = match &self.secret {
+            // Written along with the secret, ahead of the public key.
+            Secret::Protected { .. } => "",
+            _ => self.path.as_deref().unwrap_or_default(),
+        };
+        let label = self.label.as_deref().unwrap_or_default();
+        match (&self.label, self.created) {
+            (_, Some(created)) => format!("{}:{}{}\t{}\t{}", kind, secret, path, label, created),
+            (Some(_), None) => format!("{}:{}{}\t{}", kind, secret, path, label),
+            (None, None) => format!("{}:{}{}", kind, secret, path),
+        }
+    }
+
+    /// Derives this entry's key pair, following its derivation path if it has one.
+    /// Watch-only entries have none.
+    pub fn pair(&self) -> Option<KeyPair> {
+        let pair = self.root_pair()?;
+        match &self.path {
+            Some(path) => Some(
+                parse_derivation_path(path)
+                    .and_then(|junctions| pair.derive(&junctions))
+                    .expect("derivation paths are validated when the entry is built"),
+            ),
+            None => Some(pair),
+        }
+    }
+
+    /// Derives the key pair of the secret's root account, ignoring the derivation path.
+    fn root_pair(&self) -> Option<KeyPair> {
+        let pair = match &self.secret {
+            Secret::Seed { seed, .. } => KeyPair::from_seed(self.scheme, seed),
+            Secret::Sr25519Key(key) => {
+                let secret = schnorrkel::SecretKey::from_ed25519_bytes(key)
+                    .expect("sr25519 keys are validated when the entry is built");
+                KeyPair::Sr25519(secret.into())
+            }
+            Secret::WatchOnly(..) | Secret::Protected { .. } => return None,
+        };
+        Some(pair)
+    }
+
+    /// The account this entry's seed and derivation path give under `scheme`, which may
+    /// be another than the entry's own. Fails for entries without a seed, and for soft
+    /// junctions under a scheme other than sr25519.
+    pub fn account_under(&self, scheme: KeyScheme) -> Result<AccountId32, String> {
+        let seed = self
+            .seed()
+            .ok_or("Only the sr25519 secret key of this wallet is known")?;
+        let pair = KeyPair::from_seed(scheme, seed);
+        let pair = match &self.path {
+            Some(path) => pair.derive(&parse_derivation_path(path)?)?,
+            None => pair,
+        };
+        Ok(pair.account_id())
+    }
+
+    /// The account of this entry, which watch-only and protected entries know without a
+    /// key pair.
+    pub fn account_id(&self) -> AccountId32 {
+        match &self.secret {
+            Secret::WatchOnly(account, _) => account.clone(),
+            Secret::Protected { public, .. } => match self.scheme {
+                KeyScheme::Ecdsa => AccountId32::new(blake2_256(public)),
+                KeyScheme::Sr25519 | KeyScheme::Ed25519 => AccountId32::new(
+                    public[..]
+                        .try_into()
+                        .expect("public keys are checked when the entry is built"),
+                ),
+            },
+            _ => self
+                .pair()
+                .expect("entries with a secret have a key pair")
+                .account_id(),
+        }
+    }
+
+    /// Derives the SS58 address of this entry's account in the given format.
+    pub fn address(&self, format: Ss58AddressFormat) -> String {
+        self.account_id().to_ss58check_with_version(format)
+    }
+
+    /// What `subkey inspect --scheme <scheme> --network <format>` prints for this entry's
+    /// secret URI, line for line, so that the two can be diffed. Fails for wallets whose
+    /// secret subkey cannot take.
+    pub fn inspect(&self, format: Ss58AddressFormat) -> Result<Zeroizing<String>, String> {
+        let path = self.path.as_deref().unwrap_or_default();
+        let uri = Zeroizing::new(match &self.secret {
+            Secret::Seed {
+                phrase: Some(phrase),
+                ..
+            } => format!("{}{}", phrase, path),
+            Secret::Seed { seed, phrase: None } => format!("0x{}{}", hex::encode(seed), path),
+            Secret::Sr25519Key(_) => {
+                return Err("subkey cannot inspect a wallet kept as an sr25519 secret key".into())
+            }
+            Secret::WatchOnly(..) => return Err("Watch-only wallets have no secret".into()),
+            Secret::Protected { .. } => {
+                return Err("Protected wallets need their passphrase to be inspected".into())
+            }
+        });
+
+        match self.scheme {
+            KeyScheme::Sr25519 => inspect_uri::<Sr25519Pair>(&uri, format, |public| {
+                AccountId32::new(public.try_into().expect("sr25519 public keys are 32 bytes"))
+            }),
+            KeyScheme::Ed25519 => inspect_uri::<Ed25519Pair>(&uri, format, |public| {
+                AccountId32::new(public.try_into().expect("ed25519 public keys are 32 bytes"))
+            }),
+            KeyScheme::Ecdsa => inspect_uri::<EcdsaPair>(&uri, format, |public| {
+                AccountId32::new(blake2_256(public))
+            }),
+        }
+    }
+
+    /// The address format a watch-only entry was imported in. Entries with a secret are
+    /// shown in whichever format is selected and have none of their own.
+    pub fn ss58_format(&self) -> Option<Ss58AddressFormat> {
+        match &self.secret {
+            Secret::WatchOnly(_, format) => Some(*format),
+            _ => None,
+        }
+    }
+
+    /// Derives the EIP-55 checksummed H160 address used by EVM-compatible chains.
+    ///
+    /// Only ecdsa entries have one.
+    pub fn eth_address(&self) -> Option<String> {
+        if self.scheme != KeyScheme::Ecdsa {
+            return None;
+        }
+        let public = <[u8; 33]>::try_from(self.public_key()?).ok()?;
+
+        let uncompressed = libsecp256k1::PublicKey::parse_compressed(&public)
+            .ok()?
+            .serialize();
+        let hash = keccak_256(&uncompressed[1..]);
+        let address = hex::encode(&hash[12..]);
+
+        let checksum = keccak_256(address.as_bytes());
+        let checksummed: String = address
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let nibble = (checksum[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+                if nibble >= 8 {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect();
+
+        Some(format!("0x{}", checksummed))
+    }
+}
+
+/// A new wallet, configured with the `with_` methods and then generated:
+///
+/// ```ignore
+/// let (address, entry) = Wallet::new(KeyScheme::Ed25519)
+///     .with_mnemonic_words(Some(24))
+///     .with_label("savings")
+///     .generate()?;
+/// ```
+///
+/// Without further settings the wallet gets a raw seed, no label and a generic Substrate
+/// address.
+#[derive(Debug, Clone)]
+pub struct Wallet {
+    scheme: KeyScheme,
+    mnemonic_words: Option<usize>,
+    ss58_format: Ss58AddressFormat,
+    label: Option<String>,
+}
+
+impl Wallet {
+    pub fn new(scheme: KeyScheme) -> Self {
+        Self {
+            scheme,
+            mnemonic_words: None,
+            ss58_format: Ss58AddressFormat::custom(42),
+            label: None,
+        }
+    }
+
+    /// Derives the wallet from a mnemonic phrase of `words` words instead of a raw seed
+    /// when given. BIP39 allows 12, 15, 18, 21 and 24.
+    pub fn with_mnemonic_words(mut self, words: Option<usize>) -> Self {
+        self.mnemonic_words = words;
+        self
+    }
+
+    /// The format of the address [`Wallet::generate`] returns.
+    pub fn with_ss58_format(mut self, ss58_format: Ss58AddressFormat) -> Self {
+        self.ss58_format = ss58_format;
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Generates the wallet from the OS random number generator, returning its address
+    /// and its keys file entry.
+    pub fn generate(&self) -> Result<(String, KeyEntry), std::io::Error> {
+        self.generate_with_rng(&mut OsRng)
+    }
+
+    /// Generates the wallet from the randomness of `rng`, refusing to if it fails or gives
+    /// nothing but zeros. A wallet derived from a predictable seed would be worthless.
+    fn generate_with_rng<R: TryRngCore>(
+        &self,
+        rng: &mut R,
+    ) -> Result<(String, KeyEntry), std::io::Error> {
+        let mut fill = |bytes: &mut [u8]| {
+            rng.try_fill_bytes(bytes).map_err(|e| {
+                std::io::Error::other(format!("Random number generator failed: {}", e))
+            })?;
+            if bytes.iter().all(|&byte| byte == 0) {
+                return Err(std::io::Error::other(
+                    "Random number generator returned only zeros",
+                ));
+            }
+            Ok(())
+        };
+
+        let entry = match self.mnemonic_words {
+            Some(words) => {
+                if !MNEMONIC_WORDS.contains(&words) {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("A mnemonic phrase can't have {} words", words),
+                    ));
+                }
+                // Every 3 words encode 32 bits of entropy.
+                let mut entropy = Zeroizing::new(vec![0u8; words / 3 * 4]);
+                fill(&mut entropy)?;
+                let mnemonic = bip39::Mnemonic::from_entropy(&entropy)
+                    .expect("entropy length is valid for the supported word counts");
+
+                KeyEntry::from_phrase(self.scheme, &Zeroizing::new(mnemonic.to_string()))
+                    .expect("a freshly generated mnemonic is valid")
+            }
+            None => {
+                let mut seed = Zeroizing::new([0u8; 32]);
+                fill(seed.as_mut())?;
+
+                KeyEntry::from_seed(self.scheme, *seed)
+            }
+        };
+        let entry = KeyEntry {
+            label: self.label.clone(),
+            ..entry.stamped()
+        };
+
+        Ok((entry.address(self.ss58_format), entry))
+    }
+}
+
+/// The keys and address of a wallet, in the form the UI shows them.
+///
+/// The private key is kept out of `Debug` output and can only be read through
+/// [`WalletKeys::private_key`], which is meant for the explicit reveal flow.
+#[derive(Clone)]
+pub struct WalletKeys {
+    /// Hex-encoded public key, unless the wallet is watch-only
+    pub public_key: Option<String>,
+    /// Hex seed or secret key, followed by the derivation path if any
+    private_key: Option<Zeroizing<String>>,
+    pub address: String,
+}
+
+impl WalletKeys {
+    pub fn from_seed(seed: [u8; 32], scheme: KeyScheme, ss58_format: Ss58AddressFormat) -> Self {
+        Self::from_entry(&KeyEntry::from_seed(scheme, seed), ss58_format)
+    }
+
+    /// The keys of `entry`, with its address in `ss58_format` unless it is watch-only and
+    /// has a format of its own.
+    pub fn from_entry(entry: &KeyEntry, ss58_format: Ss58AddressFormat) -> Self {
+        let secret = match &entry.secret {
+            Secret::Seed { seed, .. } => Some(Zeroizing::new(hex::encode(seed))),
+            Secret::Sr25519Key(key) => Some(Zeroizing::new(hex::encode(key))),
+            Secret::WatchOnly(..) | Secret::Protected { .. } => None,
+        };
+        let path = entry.path.as_deref().unwrap_or_default();
+
+        Self {
+            public_key: entry
+                .public_key()
+                .map(|public| format!("0x{}", hex::encode(public))),
+            private_key: secret.map(|secret| Zeroizing::new(format!("0x{}{}", *secret, path))),
+            address: entry.address(entry.ss58_format().unwrap_or(ss58_format)),
+        }
+    }
+
+    /// The secret as a suri, which watch-only wallets don't have. Only to be shown when
+    /// the user asked for it.
+    pub fn private_key(&self) -> Option<&str> {
+        self.private_key.as_deref().map(String::as_str)
+    }
+}
+
+impl std::fmt::Debug for WalletKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalletKeys")
+            .field("public_key", &self.public_key)
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Prints `uri` the way `subkey inspect` does with `P` as the scheme: a bare mnemonic
+/// phrase under a heading of its own, anything else as a secret URI whose seed is only
+/// known while no soft junction was taken. `account` turns a public key into its account.
+fn inspect_uri<P: Pair>(
+    uri: &str,
+    format: Ss58AddressFormat,
+    account: impl Fn(&[u8]) -> AccountId32,
+) -> Result<Zeroizing<String>, String>
+where
+    P::Public: Ss58Codec,
+{
+    let (pair, seed, heading) = match P::from_phrase(uri, None) {
+        Ok((pair, seed)) => (pair, Some(seed), format!("Secret phrase:       {}", uri)),
+        Err(_) => {
+            let (pair, seed) = P::from_string_with_seed(uri, None)
+                .map_err(|e| format!("Invalid secret URI: {:?}", e))?;
+            (pair, seed, format!("Secret Key URI `{}` is account:", uri))
+        }
+    };
+    let heading = Zeroizing::new(heading);
+    let seed = Zeroizing::new(match seed {
+        Some(seed) => format!("0x{}", hex::encode(seed.as_ref())),
+        None => "n/a".to_string(),
+    });
+    let public = pair.public();
+    let account = account(public.as_ref());
+
+    Ok(Zeroizing::new(format!(
+        "{}\n  \
+         Network ID:        {}\n  \
+         Secret seed:       {}\n  \
+         Public key (hex):  0x{}\n  \
+         Account ID:        0x{}\n  \
+         Public key (SS58): {}\n  \
+         SS58 Address:      {}",
+        *heading,
+        format,
+        *seed,
+        hex::encode(public.as_ref()),
+        hex::encode(&account),
+        public.to_ss58check_with_version(format),
+        account.to_ss58check_with_version(format),
+    )))
+}
+
+/// Why a string is not an SS58 address that is safe to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressError {
+    /// Characters outside the base58 alphabet, or not an address at all
+    BadBase58,
+    /// Too few or too many characters for an account address
+    BadLength,
+    /// The checksum does not match, so the address was most likely mistyped
+    BadChecksum,
+    /// A prefix that is malformed or reserved
+    BadPrefix,
+    /// A well-formed prefix that belongs to no network in the SS58 registry
+    UnknownNetwork(u16),
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressError::BadBase58 => f.write_str("Not an SS58 address"),
+            AddressError::BadLength => f.write_str("Wrong length for an SS58 address"),
+            AddressError::BadChecksum => f.write_str("Bad checksum, check for typos"),
+            AddressError::BadPrefix => f.write_str("Invalid or reserved network prefix"),
+            AddressError::UnknownNetwork(prefix) => {
+                write!(f, "Network prefix {} belongs to no known network", prefix)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// Decodes an SS58 address that is typed or pasted in, telling apart the ways it can be
+/// malformed. Unlike the keys file, which may hold anything that decodes, this rejects
+/// prefixes unknown to the SS58 registry.
+pub fn parse_address(address: &str) -> Result<(AccountId32, Ss58AddressFormat), AddressError> {
+    let (account, format) =
+        AccountId32::from_ss58check_with_version(address).map_err(|e| match e {
+            PublicError::BadLength => AddressError::BadLength,
+            PublicError::InvalidChecksum => AddressError::BadChecksum,
+            PublicError::InvalidPrefix | PublicError::FormatNotAllowed => AddressError::BadPrefix,
+            PublicError::UnknownSs58AddressFormat(format) => {
+                AddressError::UnknownNetwork(format.prefix())
+            }
+            _ => AddressError::BadBase58,
+        })?;
+
+    if Ss58AddressFormatRegistry::try_from(format).is_err() {
+        return Err(AddressError::UnknownNetwork(format.prefix()));
+    }
+    Ok((account, format))
+}
+
+/// Checks `signature` of `message` against the account `account_id`, returning the scheme
+/// whose signature matched.
+///
+/// 64-byte signatures are tried as sr25519 and ed25519, whose account ids are the public
+/// key itself. 65-byte signatures are ecdsa: the public key is recovered from the
+/// signature and matched against the account id by its blake2-256 hash.
+pub fn verify_signature(
+    account_id: &AccountId32,
+    message: &[u8],
+    signature: &[u8],
+) -> Option<KeyScheme> {
+    let public: &[u8; 32] = account_id.as_ref();
+
+    if let Ok(raw) = <[u8; 64]>::try_from(signature) {
+        if Sr25519Pair::verify(
+            &sr25519::Signature::from_raw(raw),
+            message,
+            &sr25519::Public::from_raw(*public),
+        ) {
+            return Some(KeyScheme::Sr25519);
+        }
+        if Ed25519Pair::verify(
+            &ed25519::Signature::from_raw(raw),
+            message,
+            &ed25519::Public::from_raw(*public),
+        ) {
+            return Some(KeyScheme::Ed25519);
+        }
+    } else if let Ok(raw) = <[u8; 65]>::try_from(signature) {
+        let recovered = ecdsa::Signature::from_raw(raw).recover(message)?;
+        if &blake2_256(&recovered.0) == public {
+            return Some(KeyScheme::Ecdsa);
+        }
+    }
+
+    None
+}
+
+/// Parses the hex public key, with or without `0x`, of a `scheme` key pair: 33 bytes
+/// for ecdsa and 32 otherwise.
+fn parse_public_key(scheme: KeyScheme, hex_key: &str) -> Result<Vec<u8>, std::io::Error> {
+    let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+    let public = hex::decode(hex_key.strip_prefix("0x").unwrap_or(hex_key))
+        .map_err(|e| invalid(format!("Invalid public key: {}", e)))?;
+    let len = match scheme {
+        KeyScheme::Ecdsa => 33,
+        KeyScheme::Sr25519 | KeyScheme::Ed25519 => 32,
+    };
+    match public.len() == len {
+        true => Ok(public),
+        false => Err(invalid(format!(
+            "A {} public key has {} bytes, not {}",
+            scheme.name(),
+            len,
+            public.len()
+        ))),
+    }
+}
+
+/// Parses a suri-style derivation path such as `//polkadot//0/soft` into its junctions.
+///
+/// `//` starts a hard junction and `/` a soft one; numeric junctions are encoded as
+/// integers, the same way polkadot-js and subkey do.
+pub fn parse_derivation_path(path: &str) -> Result<Vec<DeriveJunction>, String> {
+    let mut junctions = Vec::new();
+    let mut rest = path;
+
+    while !rest.is_empty() {
+        let Some(junction) = rest.strip_prefix('/') else {
+            return Err(format!("Derivation path must start with '/': {}", path));
+        };
+        let (hard, junction) = match junction.strip_prefix('/') {
+            Some(junction) => (true, junction),
+            None => (false, junction),
+        };
+        let end = junction.find('/').unwrap_or(junction.len());
+        let (code, remainder) = junction.split_at(end);
+
+        if code.is_empty() {
+            return Err(format!("Empty junction in derivation path: {}", path));
+        }
+        if code.contains(char::is_whitespace) {
+            return Err(format!("Junctions must not contain spaces: {}", code));
+        }
+
+        junctions.push(if hard {
+            DeriveJunction::from(format!("/{}", code))
+        } else {
+            DeriveJunction::from(code)
+        });
+        rest = remainder;
+    }
+
+    Ok(junctions)
+}
+
+/// Wallets read from a keys file, and the lines that could not be read as one.
+#[derive(Debug, Default)]
+pub struct LoadedWallets {
+    pub seeds: Vec<KeyEntry>,
+    /// Line number, counting from 1, and parse error of each skipped line
+    pub skipped: Vec<(usize, String)>,
+    /// Number of entries left out because an earlier line holds the same wallet
+    pub duplicates: usize,
+}
+
+/// Why `bytes` do not look random, if they don't: all the same, counting up or down in
+/// even steps, or made of a handful of values repeated. This only catches obvious
+/// mistakes, like a seed typed in by hand; passing says little about real entropy.
+fn weak_bytes(bytes: &[u8]) -> Option<&'static str> {
+    let (first, rest) = bytes.split_first()?;
+    if rest.iter().all(|byte| byte == first) {
+        return Some("every byte is the same");
+    }
+    let step = bytes[1].wrapping_sub(bytes[0]);
+    if bytes
+        .windows(2)
+        .all(|pair| pair[1].wrapping_sub(pair[0]) == step)
+    {
+        return Some("its bytes count up or down in even steps");
+    }
+
+    // Shannon entropy of the byte values, in bits per byte. Random bytes come close to
+    // log2 of their count; well under that means few values, often repeated.
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    let len = bytes.len() as f64;
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+    (entropy < 0.6 * len.min(256.0).log2()).then_some("it repeats a few byte values")
+}
+
+/// Directory containing the file at `path`, which is the current one for bare file names.
+pub fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Permission bits of the file at `path` when users other than its owner may read or
+/// write it. Always `None` where files have no Unix mode, or when the file can't be read.
+#[cfg(unix)]
+pub fn exposed_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    (mode & 0o066 != 0).then_some(mode)
+}
+
+#[cfg(not(unix))]
+pub fn exposed_mode(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Options that create files readable and writable by their owner only, so that new
+/// keys files are private from the start.
+fn private_file_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+}
+
+/// Makes the file at `path` readable and writable by its owner only.
+#[cfg(unix)]
+pub fn restrict_permissions(path: &Path) -> Result<(), std::io::Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+pub fn restrict_permissions(_path: &Path) -> Result<(), std::io::Error> {
+    Ok(())
+}
+
+/// Generates a wallet from a fresh random seed, or from a fresh mnemonic phrase with
+/// `mnemonic_words` words when given. Shorthand for configuring a [`Wallet`].
+pub fn generate_random_wallet(
+    scheme: KeyScheme,
+    mnemonic_words: Option<usize>,
+    ss58_format: Ss58AddressFormat,
+) -> Result<(String, KeyEntry), std::io::Error> {
+    Wallet::new(scheme)
+        .with_mnemonic_words(mnemonic_words)
+        .with_ss58_format(ss58_format)
+        .generate()
+}
+
+/// Appends `entry` to the keys file. An encrypted file is sealed as a whole, so with a
+/// `vault` the file is decrypted, extended and rewritten instead.
+pub fn save_wallet_to_file(
+    file_path: &str,
+    entry: &KeyEntry,
+    vault: Option<&mut Vault>,
+) -> Result<(), std::io::Error> {
+    save_wallets_to_file(file_path, std::slice::from_ref(entry), vault)
+}
+
+/// Appends all of `entries` to the keys file in a single write, the same way
+/// [`save_wallet_to_file`] appends one.
+///
+/// Nothing is written if any entry has an all-zero secret or is already in the file, as
+/// either points to a bug or a bad import rather than a wallet worth keeping.
+pub fn save_wallets_to_file(
+    file_path: &str,
+    entries: &[KeyEntry],
+    vault: Option<&mut Vault>,
+) -> Result<(), std::io::Error> {
+    if let Some(vault) = vault {
+        let mut seeds = load_wallets_from_file(file_path, Some(vault))?.seeds;
+        check_new_entries(&seeds, entries)?;
+        seeds.extend_from_slice(entries);
+        return write_wallets_to_file(file_path, &seeds, Some(vault));
+    }
+
+    check_new_entries(&load_wallets_from_file(file_path, None)?.seeds, entries)?;
+
+    let path = Path::new(file_path);
+    if entries.len() > 1 {
+        back_up_keys_file(path)?;
+    }
+
+    let mut lines = Zeroizing::new(String::new());
+    for entry in entries {
+        lines.push_str(&entry.to_line());
+        lines.push('\n');
+    }
+
+    let mut file = private_file_options()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+
+    // A write cut short by a crash can leave the last line unterminated. Start a new
+    // line so that the entries written now stay intact.
+    if file.metadata()?.len() > 0 {
+        let mut last = [0u8];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            lines.insert(0, '\n');
+        }
+    }
+
+    file.write_all(lines.as_bytes())?;
+    file.flush()?;
+    file.sync_all()?;
+
+    Ok(())
+}
+
+/// Checks that `label` can be written to the keys file: a line break would start another
+/// entry and a tab would shift the columns after it.
+pub fn check_label(label: &str) -> Result<(), String> {
+    match label.chars().any(char::is_control) {
+        true => Err("Label must not contain control characters".to_string()),
+        false => Ok(()),
+    }
+}
+
+/// Fails if any of `entries` has an all-zero secret or a label [`check_label`] rejects, or
+/// is in `existing` or twice in `entries`.
+pub fn check_new_entries(
+    existing: &[KeyEntry],
+    entries: &[KeyEntry],
+) -> Result<(), std::io::Error> {
+    let mut seen: HashSet<[u8; 32]> = existing.iter().map(KeyEntry::fingerprint).collect();
+    for entry in entries {
+        if let Some(label) = &entry.label {
+            check_label(label)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        }
+        let all_zero = match &entry.secret {
+            Secret::Seed { seed, .. } => seed.iter().all(|&byte| byte == 0),
+            Secret::Sr25519Key(key) => key.iter().all(|&byte| byte == 0),
+            Secret::WatchOnly(..) | Secret::Protected { .. } => false,
+        };
+        if all_zero {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Refusing to save an all-zero secret",
+            ));
+        }
+        if !seen.insert(entry.fingerprint()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "The wallet is already in the keys file",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites the whole keys file with `seeds`, one entry per line, in order, encrypting
+/// it when a `vault` is given. The previous file is backed up first.
+pub fn write_wallets_to_file(
+    file_path: &str,
+    seeds: &[KeyEntry],
+    vault: Option<&mut Vault>,
+) -> Result<(), std::io::Error> {
+    let path = Path::new(file_path);
+    back_up_keys_file(path)?;
+
+    let mut contents = Zeroizing::new(String::new());
+    for entry in seeds {
+        contents.push_str(&entry.to_line());
+        contents.push('\n');
+    }
+
+    match vault {
+        Some(vault) => replace_file(path, &vault.seal(contents.as_bytes())?),
+        None => replace_file(path, contents.as_bytes()),
+    }
+}
+
+/// Path of the keys file the wallets replaced by a new key are kept in, next to the keys
+/// file at `file_path`, so that funds left at their addresses can still be swept.
+pub fn retired_keys_path(file_path: &str) -> String {
+    format!("{}.retired", file_path)
+}
+
+/// Copies the keys file at `path`, if there is one, to `<path>.bak.<timestamp>` before
+/// it is rewritten or bulk-modified, with the timestamp in milliseconds since the Unix
+/// epoch. Only the newest [`MAX_BACKUPS`] backups are kept.
+fn back_up_keys_file(path: &Path) -> Result<(), std::io::Error> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Not a file path"))?;
+    let prefix = format!("{}.bak.", file_name);
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(std::io::Error::other)?
+        .as_millis();
+    std::fs::copy(
+        path,
+        path.with_file_name(format!("{}{}", prefix, timestamp)),
+    )
+    .map_err(|e| std::io::Error::new(e.kind(), format!("Failed to back up: {}", e)))?;
+
+    let mut backups: Vec<(u128, std::path::PathBuf)> = std::fs::read_dir(parent_dir(path))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let timestamp = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix(&prefix)?
+                .parse()
+                .ok()?;
+            Some((timestamp, entry.path()))
+        })
+        .collect();
+    backups.sort_unstable_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+
+    for (_, backup) in backups.into_iter().skip(MAX_BACKUPS) {
+        std::fs::remove_file(backup)?;
+    }
+    Ok(())
+}
+
+/// Replaces the file at `path` with `contents` atomically, like [`replace_file`], leaving
+/// it readable and writable by its owner only whatever it was before. For exports that
+/// hold secrets.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> Result<(), std::io::Error> {
+    // The replacement takes over the permissions of the file it replaces.
+    if path.exists() {
+        restrict_permissions(path)?;
+    }
+    replace_file(path, contents)
+}
+
+/// Replaces the file at `path` with `contents` atomically: they are written and synced
+/// to a temporary file next to it, which is then renamed over it. A crash leaves
+/// either the old or the new file in place, never a mix of both.
+fn replace_file(path: &Path, contents: &[u8]) -> Result<(), std::io::Error> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Not a file path"))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = (|| {
+        let mut file = private_file_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp_path)?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result?;
+
+    // Persist the rename itself. Directories can't be opened for syncing everywhere,
+    // so this is best effort.
+    if let Ok(dir) = std::fs::File::open(parent_dir(path)) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Reads the wallets in the keys file, skipping the lines that are not a valid entry
+/// rather than failing on them.
+pub fn load_wallets_from_file(
+    file_path: &str,
+    vault: Option<&mut Vault>,
+) -> Result<LoadedWallets, std::io::Error> {
+    let path = Path::new(file_path);
+
+    if !path.exists() {
+        return Ok(LoadedWallets::default());
+    }
+
+    let mut contents = Zeroizing::new(std::fs::read(path)?);
+
+    if vault::is_encrypted(&contents) {
+        let vault = vault.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "Keys file is encrypted but no password was given",
+            )
+        })?;
+        contents = vault.open(&contents)?;
+    }
+
+    Ok(parse_wallets(&contents))
+}
+
+/// Reads `contents` line by line the way a keys file is read, skipping blank lines,
+/// lines that are not a wallet and repeats of an earlier wallet.
+pub fn parse_wallets(contents: &[u8]) -> LoadedWallets {
+    let mut loaded = LoadedWallets::default();
+    let mut seen = HashSet::new();
+
+    for (index, line) in contents.split(|&byte| byte == b'\n').enumerate() {
+        let entry = std::str::from_utf8(line)
+            .map_err(|_| "not valid UTF-8".to_string())
+            .and_then(|line| {
+                // Also drops the `\r` of files saved with Windows line endings.
+                let line = line.trim();
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                KeyEntry::parse(line).map(Some).map_err(|e| e.to_string())
+            });
+
+        match entry {
+            // The first line of a wallet wins, along with its label.
+            Ok(Some(entry)) if !seen.insert(entry.fingerprint()) => loaded.duplicates += 1,
+            Ok(Some(entry)) => loaded.seeds.push(entry),
+            Ok(None) => {}
+            Err(e) => loaded.skipped.push((index + 1, e)),
+        }
+    }
+
+    loaded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::NETWORKS;
+
+    #[test]
+    fn seeds_that_do_not_look_random_are_flagged() {
+        let seed = |seed| KeyEntry::from_seed(KeyScheme::Sr25519, seed).weak_secret();
+        let counting: [u8; 32] = std::array::from_fn(|i| 200u8.wrapping_add(3 * i as u8));
+        let repeated: [u8; 32] = std::array::from_fn(|i| b"abcd"[i % 4]);
+
+        assert!(seed([7u8; 32]).is_some());
+        assert!(seed(counting).is_some());
+        assert!(seed(repeated).is_some());
+        assert_eq!(seed(sp_core::blake2_256(b"random enough")), None);
+
+        let zero_entropy = ["abandon"; 11].join(" ") + " about";
+        let phrase = KeyEntry::from_phrase(KeyScheme::Sr25519, &zero_entropy).unwrap();
+        assert_eq!(phrase.weak_secret(), Some("every byte is the same"));
+        for _ in 0..20 {
+            let (_, entry) =
+                generate_random_wallet(KeyScheme::Sr25519, Some(12), NETWORKS[0].ss58_format())
+                    .unwrap();
+            assert_eq!(entry.weak_secret(), None);
+        }
+    }
+
+    #[test]
+    fn inspection_matches_subkey_output() {
+        let phrase =
+            "caution juice atom organ advance problem want pledge someone senior holiday very";
+        let entry = KeyEntry::from_phrase(KeyScheme::Sr25519, phrase).unwrap();
+        let substrate = Ss58AddressFormatRegistry::SubstrateAccount.into();
+        assert_eq!(
+            *entry.inspect(substrate).unwrap(),
+            format!(
+                "Secret phrase:       {}
+  Network ID:        substrate
+  Secret seed:       0xc8fa03532fb22ee1f7f6908b9c02b4e72483f0dbd66e4cd456b8f34c6230b849
+  Public key (hex):  0xd6a3105d6768e956e9e5d41050ac29843f98561410d3a47f9dd5b3b227ab8746
+  Account ID:        0xd6a3105d6768e956e9e5d41050ac29843f98561410d3a47f9dd5b3b227ab8746
+  Public key (SS58): 5Gv8YYFu8H1btvmrJy9FjjAWfb99wrhV3uhPFoNEr918utyR
+  SS58 Address:      5Gv8YYFu8H1btvmrJy9FjjAWfb99wrhV3uhPFoNEr918utyR",
+                phrase
+            )
+        );
+
+        // Soft junctions lose the seed; the heading then names the whole secret URI.
+        let derived = entry.with_path(Some("//polkadot/0")).unwrap();
+        let output = derived.inspect(substrate).unwrap();
+        assert!(output.starts_with(&format!(
+            "Secret Key URI `{}//polkadot/0` is account:",
+            phrase
+        )));
+        assert!(output.contains("  Secret seed:       n/a\n"));
+        assert!(output.ends_with(&derived.address(substrate)));
+
+        let ecdsa = KeyEntry::from_seed(KeyScheme::Ecdsa, [40; 32]);
+        let output = ecdsa.inspect(substrate).unwrap();
+        assert!(output.contains(&format!(
+            "  Secret seed:       0x{}\n",
+            hex::encode([40; 32])
+        )));
+        assert!(output.contains(&format!(
+            "  Account ID:        0x{}\n",
+            hex::encode(ecdsa.account_id())
+        )));
+
+        let watch_only = KeyEntry::watch_only(entry.account_id(), substrate);
+        assert!(watch_only.inspect(substrate).is_err());
+    }
+
+    #[test]
+    fn secret_uris_give_the_accounts_subkey_does() {
+        let phrase = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+        for suri in [
+            format!("{}//polkadot/0", phrase),
+            format!("{}//polkadot///hunter2", phrase),
+            format!("0x{}//Alice", hex::encode([40u8; 32])),
+        ] {
+            let entry = KeyEntry::from_suri(KeyScheme::Sr25519, &suri).unwrap();
+            let expected = Sr25519Pair::from_string(&suri, None).unwrap();
+            assert_eq!(entry.pair().unwrap().public(), expected.public().to_vec());
+            let parsed = KeyEntry::parse(&entry.to_line()).unwrap();
+            assert_eq!(parsed.account_id(), entry.account_id());
+        }
+
+        let with_password = format!("{}///hunter2", phrase);
+        let entry = KeyEntry::from_suri(KeyScheme::Ed25519, &with_password).unwrap();
+        assert_eq!(entry.phrase(), None);
+        assert_ne!(
+            entry.account_id(),
+            KeyEntry::from_phrase(KeyScheme::Ed25519, phrase)
+                .unwrap()
+                .account_id()
+        );
+
+        for invalid in [
+            "//Alice".to_string(),
+            format!("{}//a/b", phrase.replace("walk", "wall")),
+            format!("{}/soft", phrase),
+            format!("0x{}///password", hex::encode([40u8; 32])),
+            "0x1234".to_string(),
+        ] {
+            let scheme = match invalid.contains("/soft") {
+                true => KeyScheme::Ed25519,
+                false => KeyScheme::Sr25519,
+            };
+            assert!(
+                KeyEntry::from_suri(scheme, &invalid).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn protected_wallets_need_their_passphrase_to_sign() {
+        let phrase = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+        for scheme in KeyScheme::ALL {
+            let entry = KeyEntry::from_phrase(scheme, phrase)
+                .unwrap()
+                .with_path(Some("//polkadot"))
+                .unwrap();
+            assert!(KeyEntry::from_seed(scheme, [41u8; 32])
+                .protect("pw")
+                .is_err());
+            assert!(entry.protect("").is_err());
+
+            let protected = entry.protect("hunter2").unwrap();
+            assert!(protected.pair().is_none() && protected.seed().is_none());
+            let suri = format!("{}//polkadot///hunter2", phrase);
+            let expected = KeyEntry::from_suri(scheme, &suri).unwrap();
+            assert_eq!(protected.account_id(), expected.account_id());
+            assert_eq!(protected.eth_address(), expected.eth_address());
+
+            let parsed = KeyEntry::parse(&protected.to_line()).unwrap();
+            assert_eq!(parsed, protected);
+            assert_eq!(
+                parsed.unprotect("hunter3").unwrap_err().kind(),
+                std::io::ErrorKind::PermissionDenied
+            );
+            let unlocked = parsed.unprotect("hunter2").unwrap();
+            assert_eq!(
+                unlocked.pair().unwrap().public(),
+                expected.pair().unwrap().public()
+            );
+        }
+    }
+
+    #[test]
+    fn generated_seeds_round_trip_through_the_keys_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let path_str = path.to_str().unwrap();
+        let format = NETWORKS[0].ss58_format();
+
+        let mut generated = Vec::new();
+        for scheme in [KeyScheme::Sr25519, KeyScheme::Ed25519, KeyScheme::Ecdsa] {
+            let (address, entry) = generate_random_wallet(scheme, None, format).unwrap();
+            save_wallet_to_file(path_str, &entry, None).unwrap();
+            generated.push((address, entry));
+        }
+
+        let loaded = load_wallets_from_file(path_str, None).unwrap();
+        assert!(loaded.skipped.is_empty());
+        assert_eq!(loaded.seeds.len(), generated.len());
+        for ((address, entry), loaded) in generated.iter().zip(&loaded.seeds) {
+            assert_eq!(loaded.seed(), entry.seed());
+            assert_eq!(loaded.scheme, entry.scheme);
+            assert_eq!(&loaded.address(format), address);
+        }
+    }
+
+    #[test]
+    fn empty_and_missing_files_hold_no_wallets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let path_str = path.to_str().unwrap();
+
+        let loaded = load_wallets_from_file(path_str, None).unwrap();
+        assert!(loaded.seeds.is_empty() && loaded.skipped.is_empty());
+
+        for contents in ["", "\n\n", "  \n\t\n"] {
+            std::fs::write(&path, contents).unwrap();
+            let loaded = load_wallets_from_file(path_str, None).unwrap();
+            assert!(loaded.seeds.is_empty(), "{:?}", contents);
+            assert!(loaded.skipped.is_empty(), "{:?}", contents);
+        }
+    }
+
+    #[test]
+    fn blank_lines_between_seeds_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let path_str = path.to_str().unwrap();
+        let seeds: Vec<KeyEntry> = (1..=3)
+            .map(|byte| KeyEntry::from_seed(KeyScheme::Sr25519, [byte; 32]))
+            .collect();
+
+        let contents = format!(
+            "\n{}\n\n\n{}\n   \n{}\n\n",
+            seeds[0].to_line(),
+            seeds[1].to_line(),
+            seeds[2].to_line()
+        );
+        std::fs::write(&path, contents).unwrap();
+
+        let loaded = load_wallets_from_file(path_str, None).unwrap();
+        assert_eq!(loaded.seeds, seeds);
+        assert!(loaded.skipped.is_empty());
+
+        // Appending after the trailing blank lines keeps every seed in order.
+        let fourth = KeyEntry::from_seed(KeyScheme::Sr25519, [4u8; 32]);
+        save_wallet_to_file(path_str, &fourth, None).unwrap();
+        let loaded = load_wallets_from_file(path_str, None).unwrap();
+        assert_eq!(loaded.seeds.len(), 4);
+        assert_eq!(loaded.seeds[3], fourth);
+    }
+
+    #[test]
+    fn interrupted_rewrite_leaves_keys_file_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let path_str = path.to_str().unwrap();
+        let seeds = vec![
+            KeyEntry::from_seed(KeyScheme::Sr25519, [1u8; 32]),
+            KeyEntry::from_seed(KeyScheme::Ed25519, [2u8; 32]),
+        ];
+        write_wallets_to_file(path_str, &seeds, None).unwrap();
+
+        // A crash while writing the replacement leaves it truncated next to the original.
+        let full = std::fs::read(&path).unwrap();
+        let temp_path = dir.path().join(".keys.txt.tmp");
+        std::fs::write(&temp_path, &full[..full.len() / 2]).unwrap();
+
+        assert_eq!(load_wallets_from_file(path_str, None).unwrap().seeds, seeds);
+
+        write_wallets_to_file(path_str, &seeds[..1], None).unwrap();
+        assert_eq!(
+            load_wallets_from_file(path_str, None).unwrap().seeds,
+            seeds[..1]
+        );
+        assert!(!temp_path.exists());
+    }
+
+    #[test]
+    fn append_after_unterminated_line_keeps_both_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let path_str = path.to_str().unwrap();
+        let first = KeyEntry::from_seed(KeyScheme::Sr25519, [3u8; 32]);
+        let second = KeyEntry::from_seed(KeyScheme::Ecdsa, [4u8; 32]);
+
+        // An append cut short before its newline.
+        std::fs::write(&path, first.to_line()).unwrap();
+
+        save_wallet_to_file(path_str, &second, None).unwrap();
+        assert_eq!(
+            load_wallets_from_file(path_str, None).unwrap().seeds,
+            vec![first, second]
+        );
+    }
+
+    #[test]
+    fn corrupt_lines_are_skipped_with_their_line_numbers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let first = KeyEntry::from_seed(KeyScheme::Sr25519, [5u8; 32]);
+        let second = KeyEntry::from_seed(KeyScheme::Ed25519, [6u8; 32]);
+        let contents = format!(
+            "{}\nnot hex\n\nsr25519:abcd\n{}\n",
+            first.to_line(),
+            second.to_line()
+        );
+        std::fs::write(&path, contents).unwrap();
+
+        let loaded = load_wallets_from_file(path.to_str().unwrap(), None).unwrap();
+        assert_eq!(loaded.seeds, vec![first, second]);
+        let lines: Vec<usize> = loaded.skipped.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![2, 4]);
+    }
+
+    #[test]
+    fn crlf_whitespace_and_uppercase_hex_are_accepted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let first = KeyEntry::from_seed(KeyScheme::Sr25519, [0xab; 32]);
+        let second = KeyEntry::from_seed(KeyScheme::Ed25519, [0xcd; 32]);
+        let contents = format!(
+            "  {} \r\n\r\ned25519 : {}\t\r\n",
+            hex::encode_upper([0xab; 32]),
+            "Cd".repeat(32)
+        );
+        std::fs::write(&path, contents).unwrap();
+
+        let loaded = load_wallets_from_file(path.to_str().unwrap(), None).unwrap();
+        assert!(loaded.skipped.is_empty(), "{:?}", loaded.skipped);
+        assert_eq!(loaded.seeds, vec![first, second]);
+    }
+
+    #[test]
+    fn duplicate_wallets_are_loaded_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let entry = KeyEntry::from_seed(KeyScheme::Sr25519, [7u8; 32]);
+        let labelled = KeyEntry {
+            label: Some("again".to_string()),
+            ..entry.clone()
+        };
+        let other_scheme = KeyEntry::from_seed(KeyScheme::Ed25519, [7u8; 32]);
+        let contents = [&entry, &labelled, &other_scheme, &entry]
+            .map(|entry| entry.to_line() + "\n")
+            .concat();
+        std::fs::write(&path, contents).unwrap();
+
+        let loaded = load_wallets_from_file(path.to_str().unwrap(), None).unwrap();
+        assert_eq!(loaded.seeds, vec![entry, other_scheme]);
+        assert_eq!(loaded.duplicates, 2);
+    }
+
+    /// An OS random number generator that has stopped working, or never worked.
+    struct BrokenRng {
+        fails: bool,
+    }
+
+    impl TryRngCore for BrokenRng {
+        type Error = std::io::Error;
+
+        fn try_next_u32(&mut self) -> Result<u32, Self::Error> {
+            let mut bytes = [0u8; 4];
+            self.try_fill_bytes(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        }
+
+        fn try_next_u64(&mut self) -> Result<u64, Self::Error> {
+            let mut bytes = [0u8; 8];
+            self.try_fill_bytes(&mut bytes)?;
+            Ok(u64::from_le_bytes(bytes))
+        }
+
+        fn try_fill_bytes(&mut self, _: &mut [u8]) -> Result<(), Self::Error> {
+            match self.fails {
+                true => Err(std::io::Error::other("entropy source unavailable")),
+                false => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn broken_rng_never_yields_a_wallet() {
+        // A failing generator and one that "succeeds" with zeros would both have produced
+        // the all-zero seed.
+        for fails in [true, false] {
+            for words in [None, Some(12), Some(24)] {
+                let result = Wallet::new(KeyScheme::Sr25519)
+                    .with_mnemonic_words(words)
+                    .generate_with_rng(&mut BrokenRng { fails });
+                assert!(result.is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn zero_and_known_seeds_are_not_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.txt");
+        let path_str = path.to_str().unwrap();
+        let seed = KeyEntry::from_seed(KeyScheme::Sr25519, [5u8; 32]);
+        save_wallet_to_file(path_str, &seed, None).unwrap();
+
+        let zero = KeyEntry::from_seed(KeyScheme::Sr25519, [0u8; 32]);
+        let e = save_wallet_to_file(path_str, &zero, None).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+        let e = save_wallet_to_file(path_str, &seed, None).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::AlreadyExists);
+
+        // A batch is written whole or not at all.
+        let other = KeyEntry::from_seed(KeyScheme::Ed25519, [6u8; 32]);
+        assert!(save_wallets_to_file(path_str, &[other, zero], None).is_err());
+        assert_eq!(
+            load_wallets_from_file(path_str, None).unwrap().seeds,
+            [seed]
+        );
+    }
+
+    #[test]
+    fn wallet_builder_applies_its_settings() {
+        let format = NETWORKS[1].ss58_format();
+        let (address, entry) = Wallet::new(KeyScheme::Ed25519)
+            .with_mnemonic_words(Some(24))
+            .with_ss58_format(format)
+            .with_label("savings")
+            .generate()
+            .unwrap();
+
+        assert_eq!(entry.scheme, KeyScheme::Ed25519);
+        assert_eq!(entry.phrase().unwrap().split(' ').count(), 24);
+        assert_eq!(entry.label.as_deref(), Some("savings"));
+        assert_eq!(address, entry.address(format));
+
+        let (_, entry) = Wallet::new(KeyScheme::Sr25519).generate().unwrap();
+        assert!(entry.phrase().is_none() && entry.label.is_none());
+        assert!(Wallet::new(KeyScheme::Sr25519)
+            .with_mnemonic_words(Some(13))
+            .generate()
+            .is_err());
+    }
+
+    #[test]
+    fn wallet_keys_keep_the_private_key_out_of_debug_output() {
+        let format = NETWORKS[0].ss58_format();
+        let keys = WalletKeys::from_seed([0x42; 32], KeyScheme::Ed25519, format);
+        let entry = KeyEntry::from_seed(KeyScheme::Ed25519, [0x42; 32]);
+
+        assert_eq!(keys.address, entry.address(format));
+        assert_eq!(
+            keys.public_key,
+            Some(format!("0x{}", hex::encode(entry.pair().unwrap().public())))
+        );
+        assert_eq!(
+            keys.private_key(),
+            Some(format!("0x{}", "42".repeat(32)).as_str())
+        );
+        assert!(!format!("{:?}", keys).contains(&"42".repeat(32)));
+
+        let derived = WalletKeys::from_entry(&entry.with_path(Some("//0")).unwrap(), format);
+        assert!(derived.private_key().unwrap().ends_with("//0"));
+    }
+
+    #[test]
+    fn malformed_addresses_are_told_apart() {
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let (account, format) = parse_address(alice).unwrap();
+        assert_eq!(format.prefix(), 42);
+
+        let mistyped = alice.replace("Ku", "Kv");
+        assert_eq!(parse_address(&mistyped), Err(AddressError::BadChecksum));
+        assert_eq!(
+            parse_address(&alice[..alice.len() - 3]),
+            Err(AddressError::BadLength)
+        );
+        assert_eq!(
+            parse_address(&alice.replace('G', "0")),
+            Err(AddressError::BadBase58)
+        );
+
+        let unknown = account.to_ss58check_with_version(Ss58AddressFormat::custom(9999));
+        assert_eq!(
+            parse_address(&unknown),
+            Err(AddressError::UnknownNetwork(9999))
+        );
+    }
+
+    #[test]
+    fn secret_is_zeroed_on_drop() {
+        let mut secret = std::mem::ManuallyDrop::new(Secret::Seed {
+            seed: [0xab; 32],
+            phrase: None,
+        });
+        let seed = match &*secret {
+            Secret::Seed { seed, .. } => seed.as_ptr(),
+            Secret::Sr25519Key(_) | Secret::WatchOnly(..) | Secret::Protected { .. } => {
+                unreachable!()
+            }
+        };
+
+        // The bytes stay allocated inside `secret` after the drop, so they can still be
+        // read back.
+        let bytes = unsafe {
+            std::mem::ManuallyDrop::drop(&mut secret);
+            std::slice::from_raw_parts(seed, 32)
+        };
+        assert_eq!(bytes, &[0u8; 32]);
+    }
+
+    #[test]
+    fn seeds_give_an_account_under_every_scheme() {
+        let seed = [31u8; 32];
+        let entry = KeyEntry::from_seed(KeyScheme::Sr25519, seed);
+        for scheme in KeyScheme::ALL {
+            assert_eq!(
+                entry.account_under(scheme),
+                Ok(KeyEntry::from_seed(scheme, seed).account_id())
+            );
+        }
+
+        let hard = entry.with_path(Some("//0")).unwrap();
+        assert_eq!(
+            hard.account_under(KeyScheme::Ecdsa),
+            Ok(KeyEntry::from_seed(KeyScheme::Ecdsa, seed)
+                .with_path(Some("//0"))
+                .unwrap()
+                .account_id())
+        );
+        let soft = entry.with_path(Some("/0")).unwrap();
+        assert!(soft.account_under(KeyScheme::Sr25519).is_ok());
+        assert!(soft.account_under(KeyScheme::Ed25519).is_err());
+        assert!(KeyEntry::from_sr25519_key([1u8; 64])
+            .unwrap()
+            .account_under(KeyScheme::Ed25519)
+            .is_err());
+    }
+
+    #[test]
+    fn watch_only_entries_round_trip_without_a_key() {
+        let line = "watch:5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY\tAlice";
+        let entry = KeyEntry::parse(line).unwrap();
+
+        assert!(entry.is_watch_only());
+        assert!(entry.pair().is_none());
+        assert_eq!(
+            entry.address(NETWORKS[0].ss58_format()),
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+        assert_eq!(entry.to_line(), line);
+        assert!(entry.with_path(Some("//0")).is_err());
+    }
+
+    #[test]
+    fn creation_times_follow_the_label() {
+        let seed = "sr25519:0101010101010101010101010101010101010101010101010101010101010101";
+        for (line, label, created) in [
+            (
+                format!("{}\tSavings\t1700000000", seed),
+                Some("Savings"),
+                Some(1_700_000_000),
+            ),
+            (format!("{}\t\t1700000000", seed), None, Some(1_700_000_000)),
+            (format!("{}\tSavings", seed), Some("Savings"), None),
+        ] {
+            let entry = KeyEntry::parse(&line).unwrap();
+            assert_eq!(entry.label.as_deref(), label);
+            assert_eq!(entry.created, created);
+            assert_eq!(entry.to_line(), line);
+        }
+        assert!(KeyEntry::parse(&format!("{}\tSavings\tyesterday", seed)).is_err());
+
+        let generated = Wallet::new(KeyScheme::Sr25519).generate().unwrap().1;
+        assert!(generated.created.is_some());
+        assert_eq!(generated.with_path(Some("//0")).unwrap().created, None);
+    }
+
+    #[test]
+    fn watch_only_entries_keep_their_network() {
+        let kusama = Ss58AddressFormat::custom(2);
+        let address = AccountId32::new([1; 32]).to_ss58check_with_version(kusama);
+        let entry = KeyEntry::parse(&format!("watch:{}", address)).unwrap();
+
+        assert_eq!(entry.ss58_format(), Some(kusama));
+        assert_eq!(entry.to_line(), format!("watch:{}", address));
+        assert_eq!(
+            WalletKeys::from_entry(&entry, NETWORKS[0].ss58_format()).address,
+            address
+        );
+        assert_eq!(
+            KeyEntry::from_seed(KeyScheme::Sr25519, [1; 32]).ss58_format(),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_files_are_kept_from_other_users() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.json");
+        std::fs::write(&path, b"old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private_file(&path, b"secrets").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"secrets");
+        assert_eq!(exposed_mode(&path), None);
+    }
+}